@@ -4,3 +4,4 @@ cargo build --release
 install target/release/epgdump /usr/local/bin
 mkdir -p /usr/local/etc/epgdump
 install conf/tsid.conf /usr/local/etc/epgdump/tsid.conf
+install conf/drcs_map.conf /usr/local/etc/epgdump/drcs_map.conf
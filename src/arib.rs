@@ -1,6 +1,14 @@
 
 use encoding_rs::ISO_2022_JP;
-use std::sync::atomic::{AtomicI32, AtomicU32, Ordering::*};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::drcs::{DRCS_PLACEHOLDER, DrcsTable, drcs_table};
+use crate::symbol::{SymbolTable, symbol_table};
+use crate::width::{TextWidth, text_width};
 
 // グラフィックセット
 const CODE_UNKNOWN: u32            = 0;  // 不明なグラフィックセット(非対応)
@@ -19,9 +27,12 @@ const CODE_JIS_X0201_KATAKANA: u32 = 12; // JIS X 0201 Katakana
 const CODE_JIS_KANJI_PLANE_1: u32  = 13; // JIS compatible Kanji Plane 1
 const CODE_JIS_KANJI_PLANE_2: u32  = 14; // JIS compatible Kanji Plane 2
 const CODE_ADDITIONAL_SYMBOLS: u32 = 15; // Additional symbols
+const CODE_DRCS_0: u32             = 16; // DRCS-0(DRCS-1〜15は17〜31)
+const CODE_DRCS_15: u32            = 31; // DRCS-15
+const CODE_MACRO: u32              = 32; // Macro
 
 // グラフィックセットのキャラクターサイズテーブル
-const AB_CHAR_SIZE_TABLE: [bool; 16] = [
+const AB_CHAR_SIZE_TABLE: [bool; 33] = [
     false, // CODE_UNKNOWN               不明なグラフィックセット(非対応)
     true,  // CODE_KANJI                 Kanji
     false, // CODE_ALPHANUMERIC          Alphanumeric
@@ -38,10 +49,37 @@ const AB_CHAR_SIZE_TABLE: [bool; 16] = [
     true,  // CODE_JIS_KANJI_PLANE_1     JIS compatible Kanji Plane 1
     true,  // CODE_JIS_KANJI_PLANE_2     JIS compatible Kanji Plane 2
     true,  // CODE_ADDITIONAL_SYMBOLS    Additional symbols
+    true,  // CODE_DRCS_0                DRCS-0
+    false, false, false, false, false,   // CODE_DRCS_0 + 1〜5   DRCS-1〜5
+    false, false, false, false, false,   // CODE_DRCS_0 + 6〜10  DRCS-6〜10
+    false, false, false, false, false,   // CODE_DRCS_0 + 11〜15 DRCS-11〜15
+    false, // CODE_MACRO                 Macro
+];
+
+// デフォルトマクロ(マクロ符号0x60〜0x6f)の定義内容(ARIB STD-B24 第2部 デフォルトマクロ文)
+const DEFAULT_MACRO_TABLE: [&[u8]; 16] = [
+    &[0x1b, 0x24, 0x42, 0x1b, 0x29, 0x4a, 0x1b, 0x2a, 0x30, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],        // 0x60
+    &[0x1b, 0x24, 0x42, 0x1b, 0x29, 0x31, 0x1b, 0x2a, 0x30, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],        // 0x61
+    &[0x1b, 0x24, 0x42, 0x1b, 0x29, 0x20, 0x41, 0x1b, 0x2a, 0x30, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x62
+    &[0x1b, 0x28, 0x32, 0x1b, 0x29, 0x34, 0x1b, 0x2a, 0x35, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],        // 0x63
+    &[0x1b, 0x28, 0x32, 0x1b, 0x29, 0x33, 0x1b, 0x2a, 0x35, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],        // 0x64
+    &[0x1b, 0x28, 0x32, 0x1b, 0x29, 0x20, 0x41, 0x1b, 0x2a, 0x35, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x65
+    &[0x1b, 0x28, 0x20, 0x41, 0x1b, 0x29, 0x20, 0x42, 0x1b, 0x2a, 0x20, 0x43, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x66
+    &[0x1b, 0x28, 0x20, 0x44, 0x1b, 0x29, 0x20, 0x45, 0x1b, 0x2a, 0x20, 0x46, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x67
+    &[0x1b, 0x28, 0x20, 0x47, 0x1b, 0x29, 0x20, 0x48, 0x1b, 0x2a, 0x20, 0x49, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x68
+    &[0x1b, 0x28, 0x20, 0x4a, 0x1b, 0x29, 0x20, 0x4b, 0x1b, 0x2a, 0x20, 0x4c, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x69
+    &[0x1b, 0x28, 0x20, 0x4d, 0x1b, 0x29, 0x20, 0x4e, 0x1b, 0x2a, 0x20, 0x4f, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x6a
+    &[0x1b, 0x24, 0x42, 0x1b, 0x29, 0x20, 0x42, 0x1b, 0x2a, 0x30, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x6b
+    &[0x1b, 0x24, 0x42, 0x1b, 0x29, 0x20, 0x43, 0x1b, 0x2a, 0x30, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x6c
+    &[0x1b, 0x24, 0x42, 0x1b, 0x29, 0x20, 0x44, 0x1b, 0x2a, 0x30, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x6d
+    &[0x1b, 0x28, 0x31, 0x1b, 0x29, 0x30, 0x1b, 0x2a, 0x4a, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],        // 0x6e
+    &[0x1b, 0x28, 0x4a, 0x1b, 0x29, 0x32, 0x1b, 0x2a, 0x20, 0x41, 0x1b, 0x2b, 0x20, 0x70, 0x0f, 0x1b, 0x7d],  // 0x6f
 ];
 
+// マクロの入れ子の上限(マクロ内のマクロ呼び出しの無限ループ防止)
+const MACRO_DEPTH_MAX: u32 = 4;
+
 // キャラクターサイズ
-#[allow(dead_code)]
 const STR_SMALL: i32     = 0;
 #[allow(dead_code)]
 const STR_MEDIUM: i32    = 1;
@@ -60,42 +98,94 @@ const STR_SPECIAL_1: i32 = 7;
 #[allow(dead_code)]
 const STR_SPECIAL_2: i32 = 8;
 
-// ESCカウンター
-static ESC_SEQ_COUNT: AtomicU32 =  AtomicU32::new(0);
-
-// グラフィックセットカウンター
-/*static M_CODE_G: [AtomicU32; 4] = [
-    AtomicU32::new(0),
-    AtomicU32::new(0),
-    AtomicU32::new(0),
-    AtomicU32::new(0),
-];*/
-
-#[allow(dead_code,unused_variables)]
-static M_LOCKING_GL: AtomicU32 = AtomicU32::new(0);
-#[allow(dead_code,unused_variables)]
-static M_LOCKING_GR: AtomicU32 = AtomicU32::new(0);
-#[allow(dead_code,unused_variables)]
-static M_SINGLE_GL: AtomicU32 = AtomicU32::new(0);
-#[allow(dead_code,unused_variables)]
-static M_EM_STR_SIZE: AtomicU32 = AtomicU32::new(0);
-
-// GLカウンター
-static GL_NUM: AtomicU32 = AtomicU32::new(0);
-
-// GRカウンター
-static GR_NUM: AtomicU32 = AtomicU32::new(0);
-
-// グラフィックページ
-static G_PAGE: [AtomicU32; 4] = [
-    AtomicU32::new(0),
-    AtomicU32::new(0),
-    AtomicU32::new(0),
-    AtomicU32::new(0),
-];
+// プロセス共通の改行(APR)の出力有無(起動時に設定)
+static NEWLINE: AtomicBool = AtomicBool::new(false);
+
+// 変換エラーのログ出力済み(最初のエラーのみwarn、以降はdebug)
+static DECODE_ERROR_LOGGED: AtomicBool = AtomicBool::new(false);
+
+// 変換エラーの代替文字
+const REPLACEMENT_CHAR: &str = "\u{fffd}";
+
+// 変換エラーの種類
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeErrorKind {
+    TruncatedEscape,        // エスケープシーケンスの途中でデータが終了
+    UnsupportedSet(u8),     // 非対応のグラフィックセット(終端符号)
+    InvalidCodePoint(u32),  // 変換できない文字コード
+}
+
+// 変換エラー構造体
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub kind: DecodeErrorKind,  // エラーの種類
+    pub offset: usize,          // エラーの位置(データ先頭からのバイト数)
+}
+
+impl fmt::Display for DecodeError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        match self.kind {
+            DecodeErrorKind::TruncatedEscape => write!(f, "truncated escape sequence at offset {}", self.offset),
+            DecodeErrorKind::UnsupportedSet(final_code) => write!(f, "unsupported graphic set (final=0x{:02x}) at offset {}", final_code, self.offset),
+            DecodeErrorKind::InvalidCodePoint(code) => write!(f, "invalid code point 0x{:04x} at offset {}", code, self.offset),
+        }
 
-// 文字サイズ
-static STR_SIZE: AtomicI32 = AtomicI32::new(0);
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// 文字列デコーダー(他の符号化方式の文字列デコーダーを追加する場合に実装)
+pub trait TextDecoder {
+
+    // デコーダーの状態初期化処理
+    fn reset(&mut self);
+
+    // 文字コード -> 文字への変換処理(リターン:文字列長、文字列(String))
+    fn decode(&mut self, data: &[u8], length: i32) -> (i32, String);
+
+}
+
+// ARIB 8単位符号の文字列デコーダー構造体
+#[derive(Debug, Clone)]
+pub struct AribDecoder {
+    g_page: [u32; 4],              // G0〜G3のグラフィックセット
+    locking_gl: usize,             // GLに呼び出したGセット(ロッキングシフト)
+    locking_gr: usize,             // GRに呼び出したGセット(ロッキングシフト)
+    str_size: i32,                 // 文字サイズ
+    macros: HashMap<u8, Vec<u8>>,  // マクロ符号 -> 定義内容(MACROで定義したマクロ)
+    macro_depth: u32,              // 実行中のマクロの入れ子数
+    offset: usize,                 // 変換中の文字、制御コードの位置
+    errors: Vec<DecodeError>,      // 変換エラー
+    drcs: Option<Arc<DrcsTable>>,  // DRCS変換表
+    symbols: Option<Arc<SymbolTable>>,  // 追加記号変換表
+    width: TextWidth,              // 文字幅の正規化方式
+    is_newline: bool,              // 改行(APR)を"\n"、APFをスペースで出力
+}
+
+impl Default for AribDecoder {
+
+    fn default() -> Self {
+
+        AribDecoder {
+            g_page: [CODE_KANJI, CODE_ALPHANUMERIC, CODE_HIRAGANA, CODE_KATAKANA],
+            locking_gl: 0,
+            locking_gr: 2,
+            str_size: STR_NORMAL,
+            macros: HashMap::new(),
+            macro_depth: 0,
+            offset: 0,
+            errors: vec![],
+            drcs: drcs_table(),
+            symbols: symbol_table(),
+            width: text_width(),
+            is_newline: NEWLINE.load(Ordering::Relaxed),
+        }
+
+    }
+}
 
 // マルチバイト文字の処理(リターン:文字(STring))
 fn multi_byte(g_code: u32, c1: u8, c2: u8) -> String {
@@ -605,7 +695,7 @@ const ASZ_SYMBOLES_TABLE5_U8: [&[u8]; 0x5e] = [
     &[0xe6, 0xab, 0x9b],                                          // 0x7554 "櫛"
     &[0xf0,0xa3, 0x8f, 0x8c],                                     // 0x7555 U+233CC "𣏌"
     &[0xf0,0xa3, 0x8f, 0xbe],                                     // 0x7556 U+233FE "𣏾"
-    &[0xf0,0xa3, 0x97, 0xf4],                                     // 0x7557 U+235C4 "𣗄"
+    &[0xf0, 0xa3, 0x97, 0x84],                                    // 0x7557 U+235C4 "𣗄"
     &[0xe6, 0xaf, 0xb1],                                          // 0x7558 "毱"
     &[0xe6, 0xb3, 0xa0],                                          // 0x7559 "泠"
     &[0xe6, 0xb4, 0xae],                                          // 0x755a "洮"
@@ -698,58 +788,58 @@ const ASZ_SYMBOLES_TABLE6_U8: [&[u8]; 0x2b] = [
 pub fn put_symbols_char(code: u32) -> &'static str {
 
     // シンボルテーブル１の処理
-    if code >= 0x7a50 && code <= 0x7a74 {
+    if (0x7a50..=0x7a74).contains(&code) {
 
         // 文字コード -> 文字変換
-        let utf8_str: &str = std::str::from_utf8(&ASZ_SYMBOLES_TABLE1_U8[code as usize - 0x7a50]).unwrap();
+        let utf8_str: &str = std::str::from_utf8(ASZ_SYMBOLES_TABLE1_U8[code as usize - 0x7a50]).unwrap();
 
         utf8_str
 
     }
     // シンボルテーブル２の処理
-    else if code >= 0x7c21 && code <= 0x7c7b {
+    else if (0x7c21..=0x7c7b).contains(&code) {
 
         // 文字コード -> 文字変換
-        let utf8_str: &str = std::str::from_utf8(&ASZ_SYMBOLES_TABLE2_U8[code as usize - 0x7c21]).unwrap();
+        let utf8_str: &str = std::str::from_utf8(ASZ_SYMBOLES_TABLE2_U8[code as usize - 0x7c21]).unwrap();
 
         utf8_str
 
     }
     // シンボルテーブル３の処理
-    else if code >= 0x7d21 && code <= 0x7d7b {
+    else if (0x7d21..=0x7d7b).contains(&code) {
 
         // 文字コード -> 文字変換
-        let utf8_str: &str = std::str::from_utf8(&ASZ_SYMBOLES_TABLE3_U8[code as usize - 0x7d21]).unwrap();
+        let utf8_str: &str = std::str::from_utf8(ASZ_SYMBOLES_TABLE3_U8[code as usize - 0x7d21]).unwrap();
 
         // リターン情報
         utf8_str
 
     }
     // シンボルテーブル４の処理
-    else if code >= 0x7e21 && code <= 0x7e7d {
+    else if (0x7e21..=0x7e7d).contains(&code) {
 
         // 文字コード -> 文字変換
-        let utf8_str: &str = std::str::from_utf8(&ASZ_SYMBOLES_TABLE4_U8[code as usize - 0x7e21]).unwrap();
+        let utf8_str: &str = std::str::from_utf8(ASZ_SYMBOLES_TABLE4_U8[code as usize - 0x7e21]).unwrap();
 
         // リターン情報
         utf8_str
 
     }
     // シンボルテーブル５の処理
-    else if code >= 0x7521 && code <= 0x757e {
+    else if (0x7521..=0x757e).contains(&code) {
 
         // 文字コード -> 文字変換
-        let utf8_str: &str = std::str::from_utf8(&ASZ_SYMBOLES_TABLE5_U8[code as usize - 0x7521]).unwrap();
+        let utf8_str: &str = std::str::from_utf8(ASZ_SYMBOLES_TABLE5_U8[code as usize - 0x7521]).unwrap();
 
         // リターン情報
         utf8_str
 
     }
     // シンボルテーブル６の処理
-    else if code >= 0x7621 && code <= 0x764b {
+    else if (0x7621..=0x764b).contains(&code) {
 
         // 文字コード -> 文字変換
-        let utf8_str: &str = std::str::from_utf8(&ASZ_SYMBOLES_TABLE6_U8[code as usize - 0x7621]).unwrap();
+        let utf8_str: &str = std::str::from_utf8(ASZ_SYMBOLES_TABLE6_U8[code as usize - 0x7621]).unwrap();
 
         // リターン情報
         utf8_str
@@ -763,775 +853,1383 @@ pub fn put_symbols_char(code: u32) -> &'static str {
     }
 }
 
-// シングルバイト文字の処理(リターン:文字(String))
-fn single_byte(g_code: u32, code: u8) -> String {
-
-    match g_code {
+impl AribDecoder {
 
-        CODE_ALPHANUMERIC | CODE_PROP_ALPHANUMERIC => {
+    // デコーダーの作成処理
+    pub fn new() -> Self {
 
-            if STR_SIZE.load(Acquire) == STR_MEDIUM {
+        AribDecoder::default()
 
-                // ミディアム文字の場合
-                // リターン情報(ASCII -> UTF-8変換)
-                std::str::from_utf8(&[code as u8].clone()).unwrap().to_string()
+    }
 
-            }
-            else {
+    // DRCS変換表の設定処理(プロセス共通の変換表と異なる変換表を使用する場合)
+    pub fn with_drcs(mut self, drcs: Arc<DrcsTable>) -> Self {
 
-                // ミディアム文字以外の場合
-                // リターン情報(AC_ALPHANUMERIC_TABLEの内容)
-                put_alphanumeric_char(code).to_string()
+        self.drcs = Some(drcs);
+        self
 
-            }
+    }
 
-        },
-        CODE_HIRAGANA | CODE_PROP_HIRAGANA => {
+    // 追加記号変換表の設定処理(プロセス共通の変換表と異なる変換表を使用する場合)
+    pub fn with_symbols(mut self, symbols: Arc<SymbolTable>) -> Self {
 
-            // リターン情報(AC_HIRAGANA_TABLEの内容)
-            put_hiragana_char(code).to_string()
+        self.symbols = Some(symbols);
+        self
 
-        },
-        CODE_PROP_KATAKANA | CODE_KATAKANA => {
+    }
 
-            // リターン情報(AC_KATAKANA_TABLEの内容)
-            put_katakana_char(code).to_string()
+    // 文字幅の正規化方式の設定処理(TextWidth::Rawの場合は放送のままの文字列)
+    pub fn with_width(mut self, width: TextWidth) -> Self {
 
-        },
-        CODE_JIS_X0201_KATAKANA => {
+        self.width = width;
+        self
 
-            // リターン情報(AC_JIS_KATAKANA_TABLEの内容)
-            put_jis_katakana_char(code).to_string()
+    }
 
-        },
-        _ => {
+    // 改行(APR)の出力有無の設定処理
+    pub fn with_newline(mut self, is_newline: bool) -> Self {
 
-            // リターン情報
-            "".to_string()
+        self.is_newline = is_newline;
+        self
 
-        },
     }
 
-}
+    // 変換エラーの登録処理(リターン:代替文字(String))
+    fn decode_error(&mut self, kind: DecodeErrorKind, index: usize) -> String {
 
-// グラフィックセットページ情報の設定処理(リターン:処理状況(bool))
-fn designation_set_graphic(g_num: usize, code: u8) -> bool {
+        // マクロの実行中はマクロを呼び出した位置
+        let offset = if self.macro_depth == 0 { index } else { self.offset };
+        self.errors.push(DecodeError { kind, offset });
 
-    match code {
-        0x42 => {  // Kanji
+        String::from(REPLACEMENT_CHAR)
 
-            G_PAGE[g_num].store(CODE_KANJI.try_into().unwrap(), Release);
-            true
+    }
 
-        },
-        0x4a => {  // Alphanumeric
+    // 変換エラーの取得処理
+    pub fn errors(&self) -> &[DecodeError] {
 
-            G_PAGE[g_num].store(CODE_ALPHANUMERIC.try_into().unwrap(), Release);
-            true
+        &self.errors
 
-        },
-        0x30 => {  // Hiragana
+    }
 
-            G_PAGE[g_num].store(CODE_HIRAGANA.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x31 => {  // Katakana
+    // 追加記号の処理(リターン:文字(String)、変換表が無い場合は括弧付きの文字列)
+    fn symbol_char(&mut self, code: u32, index: usize) -> String {
 
-            G_PAGE[g_num].store(CODE_KATAKANA.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x32 => {  // Mosaic A
+        match &self.symbols {
+            Some(symbols) if symbols.contains(code) => symbols.lookup(code).to_string(),
+            _ if !is_symbols_code(code) => self.decode_error(DecodeErrorKind::InvalidCodePoint(code), index),
+            Some(symbols) => symbols.lookup(code).to_string(),
+            None => put_symbols_char(code).to_string(),
+        }
 
-            G_PAGE[g_num].store(CODE_MOSAIC_A.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x33 => {  // Mosaic B
+    }
 
-            G_PAGE[g_num].store(CODE_MOSAIC_B.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x34 => {  // Mosaic C
+    // DRCS文字の処理(リターン:文字(String)、変換表に無い場合は代替文字)
+    fn drcs_char(&self, character_code: u16) -> String {
 
-            G_PAGE[g_num].store(CODE_MOSAIC_C.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x35 => {  // Mosaic D
+        match &self.drcs {
+            Some(drcs) => drcs.lookup(character_code).to_string(),
+            None => DRCS_PLACEHOLDER.to_string(),
+        }
 
-            G_PAGE[g_num].store(CODE_MOSAIC_D.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x36 => {  // Proportional Alphanumeric
+    }
 
-            G_PAGE[g_num].store(CODE_PROP_ALPHANUMERIC.try_into().unwrap(), Release);
-            true
+    // マルチバイト文字の処理(DRCS-0、追加記号を含む)(リターン:文字(String))
+    fn multi_byte_char(&mut self, g_code: u32, c1: u8, c2: u8, index: usize) -> String {
 
-        },
-        0x37 => {  // Proportional Hiragana
+        if g_code == CODE_DRCS_0 {
 
-            G_PAGE[g_num].store(CODE_PROP_HIRAGANA.try_into().unwrap(), Release);
-            true
+            self.drcs_char(((c1 as u16) << 8) + c2 as u16)
 
-        },
-        0x38 => {  // Proportional Katakana
+        }
+        else if g_code == CODE_ADDITIONAL_SYMBOLS {
 
-            G_PAGE[g_num].store(CODE_PROP_KATAKANA.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x49 => {  // JIS X 0201 Katakana
+            self.symbol_char(((c1 as u32) << 8) + c2 as u32, index)
 
-            G_PAGE[g_num].store(CODE_JIS_X0201_KATAKANA.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x39 => {  // JIS compatible Kanji Plane 1
+        }
+        else {
 
-            G_PAGE[g_num].store(CODE_JIS_KANJI_PLANE_1.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x3a => { // JIS compatible Kanji Plane 2
+            // 漢字に無い文字コードは変換エラー
+            let ret_str = multi_byte(g_code, c1, c2);
+            if ret_str.contains(REPLACEMENT_CHAR) {
 
-            G_PAGE[g_num].store(CODE_JIS_KANJI_PLANE_2.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x3b => {  // Additional symbols
+                self.decode_error(DecodeErrorKind::InvalidCodePoint(((c1 as u32) << 8) + c2 as u32), index)
 
-            G_PAGE[g_num].store(CODE_ADDITIONAL_SYMBOLS.try_into().unwrap(), Release);
-            true
-                
-        },
-        _ => {  // 不明なグラフィックセット
+            }
+            else {
 
-            false
+                ret_str
+
+            }
+
+        }
 
-        },
     }
-}
 
-// グラフィックセットDRCS情報の設定処理(リターン:処理状況(bool))
-fn designation_set_drcsgraphic(g_num: usize, code: u8) -> bool {
+    // GLのグラフィックセット取得処理
+    fn gl(&self) -> u32 {
 
+        self.g_page[self.locking_gl]
 
-    match code {
-        0x40 => {  // DRCS-0
+    }
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
+    // GRのグラフィックセット取得処理
+    fn gr(&self) -> u32 {
 
-        },
-        0x41 => {  // DRCS-1
+        self.g_page[self.locking_gr]
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x42 => {  // DRCS-2
+    }
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
+    // シングルバイト文字の処理(リターン:文字(String))
+    fn single_byte(&self, g_code: u32, code: u8) -> String {
 
-        },
-        0x43 => {  // DRCS-3
+        match g_code {
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x44 => {  // DRCS-4
+            CODE_ALPHANUMERIC | CODE_PROP_ALPHANUMERIC => {
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x45 => {  // DRCS-5
+                if self.str_size == STR_MEDIUM {
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
+                    // ミディアム文字の場合
+                    // リターン情報(ASCII -> UTF-8変換)
+                    std::str::from_utf8(&[code]).unwrap().to_string()
 
-        },
-        0x46 => {  // DRCS-6
+                }
+                else {
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x47 => {  // DRCS-7
+                    // ミディアム文字以外の場合
+                    // リターン情報(AC_ALPHANUMERIC_TABLEの内容)
+                    put_alphanumeric_char(code).to_string()
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x48 => {  // DRCS-8
+                }
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
+            },
+            CODE_HIRAGANA | CODE_PROP_HIRAGANA => {
 
-        },
-        0x49 => {  // DRCS-9
+                // リターン情報(AC_HIRAGANA_TABLEの内容)
+                put_hiragana_char(code).to_string()
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x4a => {  // DRCS-10
+            },
+            CODE_PROP_KATAKANA | CODE_KATAKANA => {
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x4b => {  // DRCS-11
+                // リターン情報(AC_KATAKANA_TABLEの内容)
+                put_katakana_char(code).to_string()
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x4c => {  // DRCS-12
+            },
+            CODE_JIS_X0201_KATAKANA => {
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x4d => {  // DRCS-13
+                // リターン情報(AC_JIS_KATAKANA_TABLEの内容)
+                put_jis_katakana_char(code).to_string()
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x4e => {  // DRCS-14
+            },
+            CODE_DRCS_0..=CODE_DRCS_15 => {
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        0x4f => {  // DRCS-15
+                // リターン情報(DRCS-1〜15は終端符号(0x41〜0x4f)と1バイト符号でDRCS変換表を検索)
+                self.drcs_char((((0x40 + g_code - CODE_DRCS_0) as u16) << 8) + code as u16)
 
-            G_PAGE[g_num].store(CODE_UNKNOWN.try_into().unwrap(), Release);
-            true
-                
-        },
-        _ => {  // 不明なグラフィックセット
+            },
+            _ => {
 
-            false
+                // リターン情報
+                "".to_string()
+
+            },
+        }
 
-        },
     }
 
-}
+    // シングルバイト文字の処理(マクロを含む)(リターン:文字(String))
+    fn single_byte_char(&mut self, g_code: u32, code: u8) -> String {
 
-// エスケープシーケンスの処理(リターン:処理バイト数)
-fn escape_control(data: &[u8], index: usize) -> i32 {
+        if g_code == CODE_MACRO {
 
-    let mut _len: i32 = 0;
+            self.macro_exec(code)
 
-    if data.len() >= index {
-        // GLページの処理
-        if data[index + 1] == 0x24 && data.len() > index + 2 {
+        }
+        // 非対応のグラフィックセット(指示時に変換エラー登録済み)
+        else if g_code == CODE_UNKNOWN {
 
-            if data[index + 2] >= 0x28 && data[index + 2] <= 0x2b && data.len() > index + 3 {
+            String::from(REPLACEMENT_CHAR)
 
-                if data[index + 3] == 0x20 && data.len() > index + 4 {
+        }
+        else {
 
-                    // グラフィックセットDRCSの設定シーケンス
-                    match data[index + 2] {
+            self.single_byte(g_code, code)
 
-                        0x28 => {
+        }
 
-                            designation_set_drcsgraphic(0, data[index + 4]);
-                            _len = 5;
+    }
 
-                        },
-                        0x29 => {
+    // マクロの実行処理(定義の無いマクロ符号は何もしない)(リターン:文字(String))
+    fn macro_exec(&mut self, code: u8) -> String {
 
-                            designation_set_drcsgraphic(1, data[index + 4]);
-                            _len = 5;
+        // MACROで定義したマクロを優先し、無い場合はデフォルトマクロ
+        let body = match self.macros.get(&code) {
+            Some(body) => body.clone(),
+            None if (0x60..=0x6f).contains(&code) => DEFAULT_MACRO_TABLE[(code - 0x60) as usize].to_vec(),
+            None => return String::new(),
+        };
 
-                        },
-                        0x2a => {
+        if self.macro_depth >= MACRO_DEPTH_MAX {
 
-                            designation_set_drcsgraphic(2, data[index + 4]);
-                            _len = 5;
+            return String::new();
 
-                        },
-                        0x2b => {
+        };
 
-                            designation_set_drcsgraphic(3, data[index + 4]);
-                            _len = 5;
+        // 定義内容を現在の状態で変換
+        let mut ret_str = String::new();
+        let mut index: usize = 0;
 
-                        },
-                        _ => {
+        self.macro_depth += 1;
+        while index < body.len() {
 
-                            _len = 1;
+            let (len, work_str) = self.arib_parse(&body, index);
+            ret_str.push_str(&work_str);
+            index += len.max(1) as usize;
 
-                        },
-                    }
-                }
-                else {
-                    // グラフィックセットページの設定シーケンス
-                    match data[index + 2] {
-                        0x29 => {
+        };
+        self.macro_depth -= 1;
 
-                            designation_set_graphic(1,  data[index + 3]);
-                            _len = 4;
+        ret_str
 
-                        },
-                        0x2a => {
+    }
 
-                            designation_set_graphic(2,  data[index + 3]);
-                            _len = 4;
+    // マクロ定義の処理(MACRO 0x40/0x41 マクロ符号 定義内容 MACRO 0x4f)(リターン:処理バイト数、文字(String))
+    fn macro_define(&mut self, data: &[u8], index: usize) -> (i32, String) {
 
-                        },
-                        0x2b => {
+        match (data.get(index + 1), data.get(index + 2)) {
+            (Some(&mode @ (0x40 | 0x41)), Some(&code)) => {
 
-                            designation_set_graphic(3,  data[index + 3]);
-                            _len = 4;
+                // 定義終了(0x95 0x4f)までを定義内容として登録
+                let body_start = index + 3;
+                let body_end = data[body_start..].windows(2)
+                    .position(|codes| codes == [0x95, 0x4f])
+                    .map_or(data.len(), |position| body_start + position);
+                self.macros.insert(code, data[body_start..body_end].to_vec());
 
-                        },
-                        _ => {
+                let len = (body_end + 2).min(data.len()) - index;
 
-                            _len = 1;
+                // 0x41は定義と同時に実行
+                if mode == 0x41 {
+
+                    (len as i32, self.macro_exec(code))
 
-                        },
-                    }
                 }
-            }
-            else {
+                else {
 
-                // グラフィックセットページの解除シーケンス
-                designation_set_graphic(0, data[index + 2]);
-                _len = 3;
+                    (len as i32, String::new())
 
-            }
+                }
+
+            },
+            _ => (control_length(data, index, 2), String::new()),
         }
-        else if data[index + 1] >= 0x28 && data[index + 1] <= 0x2b && data.len() > index + 2 {
 
-            if data[index + 2] == 0x20 && data.len() > index + 3 {
+    }
 
-                // グラフィックセットDRCSの設定シーケンス
-                match data[index + 1] {
-                    0x28 => {
+    // シングルシフトの処理(次の1文字のみGセットをGLに呼び出し)(リターン:処理バイト数、文字(String))
+    fn single_shift(&mut self, g_num: usize, data: &[u8], index: usize) -> (i32, String) {
 
-                        designation_set_drcsgraphic(0, data[index + 3]);
-                        _len = 4;
+        // GL、GRの文字コードの場合のみ呼び出したGセットで変換
+        let code = match data.get(index + 1) {
+            Some(code) if (0x21..=0x7e).contains(&(code & 0x7f)) => code & 0x7f,
+            _ => return (1, String::new()),
+        };
+        let g_code = self.g_page[g_num];
 
-                    },
-                    0x29 => {
+        // ２バイト文字
+        if AB_CHAR_SIZE_TABLE[g_code as usize] {
 
-                        designation_set_drcsgraphic(1, data[index + 3]);
-                        _len = 4;
+            match data.get(index + 2) {
+                Some(c2) => (3, self.multi_byte_char(g_code, code, c2 & 0x7f, index + 1)),
+                None => (2, self.decode_error(DecodeErrorKind::InvalidCodePoint(code as u32), index + 1)),
+            }
 
-                    },
-                    0x2a => {
+        }
+        // １バイト文字
+        else {
 
-                        designation_set_drcsgraphic(2, data[index + 3]);
-                        _len = 4;
+            (2, self.single_byte_char(g_code, code))
 
-                    },
-                    0x2b => {
+        }
 
-                        designation_set_drcsgraphic(3, data[index + 3]);
-                        _len = 4;
+    }
 
-                    },
-                    _ => {
+    // 繰り返しの処理(次の1文字をパラメータの回数出力)(リターン:処理バイト数、文字(String))
+    // (STD-B24では0x40は行末までの繰り返しだが、表示書式(行の文字数、文字サイズ)を管理しないため1回で代用)
+    fn repeat_char(&mut self, data: &[u8], index: usize) -> (i32, String) {
 
-                        _len = 1;
+        let count = match data.get(index + 1) {
+            Some(p1) => (p1 & 0x3f).max(1) as usize,
+            None => return (1, String::new()),
+        };
 
-                    },
-                }
+        if data.len() <= index + 2 {
 
-            }
-            else {
+            return (2, String::new());
 
-                // グラフィックセットページの設定シーケンス
-                match data[index + 1] {
-                    0x28 => {
+        };
+        let (len, ret_str) = self.arib_parse(data, index + 2);
 
-                        designation_set_graphic(0, data[index + 2]);
-                        _len = 3;
+        (len + 2, ret_str.repeat(count))
 
-                    },
-                    0x29 => {
+    }
 
-                        designation_set_graphic(1, data[index + 2]);
-                        _len = 3;
+    // グラフィックセットページ情報の設定処理(リターン:処理状況(bool))
+    fn designation_set_graphic(&mut self, g_num: usize, code: u8) -> bool {
 
-                    },
-                    0x2a => {
+        match code {
+            0x42 => {  // Kanji
 
-                        designation_set_graphic(2, data[index + 2]);
-                        _len = 3;
+                self.g_page[g_num] = CODE_KANJI;
+                true
 
-                    },
-                    0x2b => {
+            },
+            0x4a => {  // Alphanumeric
 
-                        designation_set_graphic(3, data[index + 2]);
-                        _len = 3;
+                self.g_page[g_num] = CODE_ALPHANUMERIC;
+                true
 
-                    },
-                    _ => {
+            },
+            0x30 => {  // Hiragana
 
-                        _len = 1;
+                self.g_page[g_num] = CODE_HIRAGANA;
+                true
+                
+            },
+            0x31 => {  // Katakana
 
-                    },
-                }
+                self.g_page[g_num] = CODE_KATAKANA;
+                true
+                
+            },
+            0x32 => {  // Mosaic A
 
-            }
-        }
-        else {
+                self.g_page[g_num] = CODE_MOSAIC_A;
+                true
+                
+            },
+            0x33 => {  // Mosaic B
 
-            _len = 1;
+                self.g_page[g_num] = CODE_MOSAIC_B;
+                true
+                
+            },
+            0x34 => {  // Mosaic C
 
-        };
-    }
-    else {
-        _len = 1;
-    };
+                self.g_page[g_num] = CODE_MOSAIC_C;
+                true
+                
+            },
+            0x35 => {  // Mosaic D
 
-    _len
-}
+                self.g_page[g_num] = CODE_MOSAIC_D;
+                true
+                
+            },
+            0x36 => {  // Proportional Alphanumeric
 
-// 制御コード文字の処理(リターン:処理バイト数、文字(String))
-fn invocation_set_graphic(data: &[u8], index: usize) -> (i32, String) {
+                self.g_page[g_num] = CODE_PROP_ALPHANUMERIC;
+                true
 
-    #[allow(unused_assignments)]
-    let mut len: i32 = 0;
-    let mut _ret_str: String = "".to_string();
+            },
+            0x37 => {  // Proportional Hiragana
 
-    let gl: u32 = G_PAGE[GL_NUM.load(Acquire) as usize].load(Acquire);
-    let gr: u32 = G_PAGE[GR_NUM.load(Acquire) as usize].load(Acquire);
+                self.g_page[g_num] = CODE_PROP_HIRAGANA;
+                true
 
-    // GLの処理
-    #[allow(unused_comparisons)]
-    if data[index] >= 0x00 && data[index] <= 0x20 {
+            },
+            0x38 => {  // Proportional Katakana
 
-        match data[index] {
-            0x0f => { // LS0
+                self.g_page[g_num] = CODE_PROP_KATAKANA;
+                true
+                
+            },
+            0x49 => {  // JIS X 0201 Katakana
 
-                GL_NUM.store(0.try_into().unwrap(), Release);
-                len = 1;
+                self.g_page[g_num] = CODE_JIS_X0201_KATAKANA;
+                true
+                
+            },
+            0x39 => {  // JIS compatible Kanji Plane 1
 
+                self.g_page[g_num] = CODE_JIS_KANJI_PLANE_1;
+                true
+                
             },
-            0x0e => { // LS1
+            0x3a => { // JIS compatible Kanji Plane 2
 
-                GL_NUM.store(1.try_into().unwrap(), Release);
-                len = 1;
+                self.g_page[g_num] = CODE_JIS_KANJI_PLANE_2;
+                true
+                
+            },
+            0x3b => {  // Additional symbols
 
+                self.g_page[g_num] = CODE_ADDITIONAL_SYMBOLS;
+                true
+                
             },
-            0x0d => { // LF
+            _ => {  // 不明なグラフィックセット
 
-                _ret_str = String::from("");
-                len = 1;
+                false
 
             },
-            0x1b => { // ESC
+        }
+    }
 
-                // 後続データがある場合にエスケープコード処理
-                if data.len() > index + 1 {
-                    match data[index + 1] {
-                        0x6e => { // LS2
+    // グラフィックセットDRCS情報の設定処理(リターン:処理状況(bool))
+    fn designation_set_drcsgraphic(&mut self, g_num: usize, code: u8) -> bool {
 
-                            GL_NUM.store(2.try_into().unwrap(), Release);
-                            len = 2;
 
-                        },
-                        0x6f => { // LS3
+        match code {
+            0x40 => {  // DRCS-0
 
-                            GL_NUM.store(3.try_into().unwrap(), Release);
-                            len = 2;
+                self.g_page[g_num] = CODE_DRCS_0;
+                true
 
-                        },
-                        0x7c => { // LS3R
+            },
+            0x41 => {  // DRCS-1
 
-                            GR_NUM.store(3.try_into().unwrap(), Release);
-                            len = 2;
+                self.g_page[g_num] = CODE_DRCS_0 + 1;
+                true
+                
+            },
+            0x42 => {  // DRCS-2
 
-                        },
-                        0x7d => { // LS2R
+                self.g_page[g_num] = CODE_DRCS_0 + 2;
+                true
 
-                            GR_NUM.store(2.try_into().unwrap(), Release);
-                            len = 2;
+            },
+            0x43 => {  // DRCS-3
 
-                        },
-                        0x7e => { // LS1R
+                self.g_page[g_num] = CODE_DRCS_0 + 3;
+                true
+                
+            },
+            0x44 => {  // DRCS-4
 
-                            GR_NUM.store(1.try_into().unwrap(), Release);
-                            len = 2;
+                self.g_page[g_num] = CODE_DRCS_0 + 4;
+                true
+                
+            },
+            0x45 => {  // DRCS-5
 
-                        },
-                        _ => {
+                self.g_page[g_num] = CODE_DRCS_0 + 5;
+                true
 
-                            len = escape_control(&data, index);
+            },
+            0x46 => {  // DRCS-6
 
-                        },
-                    };
-                }
-                else {
-                    len = 1;
-                };
+                self.g_page[g_num] = CODE_DRCS_0 + 6;
+                true
+                
             },
-            0x19 => { // SS2
+            0x47 => {  // DRCS-7
 
-                // GLコードページの退避
-                let ss2 = GL_NUM.load(Acquire);
-                GL_NUM.store(2.try_into().unwrap(), Release);
+                self.g_page[g_num] = CODE_DRCS_0 + 7;
+                true
+                
+            },
+            0x48 => {  // DRCS-8
 
-                // 後続データがある場合にarib_parse処理の再呼び出し
-                if data.len() > index + 1 {
+                self.g_page[g_num] = CODE_DRCS_0 + 8;
+                true
 
-                    (len, _ret_str) = arib_parse(&data, index + 1);
+            },
+            0x49 => {  // DRCS-9
 
-                };
+                self.g_page[g_num] = CODE_DRCS_0 + 9;
+                true
+                
+            },
+            0x4a => {  // DRCS-10
 
-                // GLコードページの復旧
-                GL_NUM.store(ss2.try_into().unwrap(), Release);
+                self.g_page[g_num] = CODE_DRCS_0 + 10;
+                true
+                
+            },
+            0x4b => {  // DRCS-11
 
-                len += 1;
+                self.g_page[g_num] = CODE_DRCS_0 + 11;
+                true
+                
+            },
+            0x4c => {  // DRCS-12
 
+                self.g_page[g_num] = CODE_DRCS_0 + 12;
+                true
+                
             },
-            0x1d => { // SS3
+            0x4d => {  // DRCS-13
 
-                // GLコードページの退避
-                let ss3 = GL_NUM.load(Acquire);
-                GL_NUM.store(3.try_into().unwrap(), Release);
+                self.g_page[g_num] = CODE_DRCS_0 + 13;
+                true
+                
+            },
+            0x4e => {  // DRCS-14
 
-                // 後続データがある場合にarib_parse処理の再呼び出し
-                if data.len() > index + 1 {
+                self.g_page[g_num] = CODE_DRCS_0 + 14;
+                true
+                
+            },
+            0x4f => {  // DRCS-15
 
-                    (len, _ret_str) = arib_parse(&data, index + 1);
+                self.g_page[g_num] = CODE_DRCS_0 + 15;
+                true
+                
+            },
+            0x70 => {  // Macro
 
-                };
+                self.g_page[g_num] = CODE_MACRO;
+                true
 
-                // GLコードページの復旧
-                GL_NUM.store(ss3.try_into().unwrap(), Release);
+            },
+            _ => {  // 不明なグラフィックセット
 
-                len += 1;
+                false
 
             },
-            0x20 => { // スペース
-                // 半角スペース
-                if STR_SIZE.load(Acquire) == STR_MEDIUM {
+        }
 
-                    _ret_str = String::from(" ");
+    }
 
-                }
-                // 全角スペース
-                else if STR_SIZE.load(Acquire) == STR_NORMAL {
+    // グラフィックセットの指示処理(非対応のグラフィックセットは変換エラー)
+    fn designation(&mut self, g_num: usize, code: u8, is_drcs: bool, index: usize) {
 
-                    _ret_str = String::from("　");
+        let designated = if is_drcs {
+            self.designation_set_drcsgraphic(g_num, code)
+        }
+        else {
+            self.designation_set_graphic(g_num, code)
+        };
 
-                };
+        if !designated {
 
-                len = 1;
+            // 以降の文字は代替文字
+            self.g_page[g_num] = CODE_UNKNOWN;
+            self.decode_error(DecodeErrorKind::UnsupportedSet(code), index);
 
-            },
-            _ => { // 上記以外は文字位置を進めるだけ
+        };
 
-                // 文字サイズ大
-                if AB_CHAR_SIZE_TABLE[gl as usize] == true {
+    }
 
-                    len = 2;
+    // エスケープシーケンスの処理(リターン:処理バイト数、文字(String))
+    fn escape_control(&mut self, data: &[u8], index: usize) -> (i32, String) {
 
-                }
-                // 文字サイズ小
-                else {
+        // 中間文字(0x20〜0x2f)に続く終端文字までを取得(終端文字が無い場合は変換エラー)
+        let seq_len = match data[index + 1..].iter().position(|code| !(0x20..=0x2f).contains(code)) {
+            Some(position) => position + 2,
+            None => return ((data.len() - index) as i32, self.decode_error(DecodeErrorKind::TruncatedEscape, index)),
+        };
+
+        match data[index + 1..index + seq_len] {
+            // グラフィックセットページ(2バイトGセット)のG0への設定シーケンス
+            [0x24, final_code] => self.designation(0, final_code, false, index),
+            // グラフィックセットページ(2バイトGセット)の設定シーケンス
+            [0x24, g @ 0x28..=0x2b, final_code] => self.designation((g - 0x28) as usize, final_code, false, index),
+            // グラフィックセットDRCS(2バイトDRCS)の設定シーケンス
+            [0x24, g @ 0x28..=0x2b, 0x20, final_code] => self.designation((g - 0x28) as usize, final_code, true, index),
+            // グラフィックセットページ(1バイトGセット)の設定シーケンス
+            [g @ 0x28..=0x2b, final_code] => self.designation((g - 0x28) as usize, final_code, false, index),
+            // グラフィックセットDRCS(1バイトDRCS)の設定シーケンス
+            [g @ 0x28..=0x2b, 0x20, final_code] => self.designation((g - 0x28) as usize, final_code, true, index),
+            // 上記以外は読み飛ばし
+            _ => debug!("unknown escape sequence {:02x?}", &data[index..index + seq_len]),
+        };
+
+        (seq_len as i32, String::new())
+
+    }
+
+    // 制御コード文字の処理(リターン:処理バイト数、文字(String))
+    fn invocation_set_graphic(&mut self, data: &[u8], index: usize) -> (i32, String) {
+
+        #[allow(unused_assignments)]
+        let mut len: i32 = 0;
+        let mut _ret_str: String = "".to_string();
+
+        // GLの処理
+        #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
+        if data[index] >= 0x00 && data[index] <= 0x20 {
+
+            match data[index] {
+                0x0f => { // LS0
 
+                    self.locking_gl = 0;
                     len = 1;
 
-                };
+                },
+                0x0e => { // LS1
 
-            },
-        };
+                    self.locking_gl = 1;
+                    len = 1;
 
-    }
-    // GRの処理
-    else {
+                },
+                0x0d => { // APR(改行)
 
-        match data[index] {
-            0x89 => { // MSZ
+                    if self.is_newline {
 
-                STR_SIZE.store(STR_MEDIUM.try_into().unwrap(), Release);
-                len = 1;
+                        _ret_str = String::from("\n");
 
-            },
-            0x8a => { // NSZ
+                    };
+                    len = 1;
 
-                STR_SIZE.store(STR_NORMAL.try_into().unwrap(), Release);
-                len = 1;
+                },
+                0x09 => { // APF(1文字進める)
 
-            },
-            0xa0 => { // スペース
+                    if self.is_newline {
 
-                // 半角スペース
-                if STR_SIZE.load(Acquire) == STR_MEDIUM {
+                        _ret_str = String::from(" ");
 
-                    _ret_str = String::from(" ");
+                    };
+                    len = 1;
 
-                }
-                // 全角スペース
-                else if STR_SIZE.load(Acquire) == STR_NORMAL {
+                },
+                0x00 | 0x07 | 0x08 | 0x0a | 0x0b | 0x0c | 0x18 | 0x1e | 0x1f => { // NUL、BEL、APB、APD、APU、CS、CAN、RS、US
 
-                    _ret_str = String::from("　");
+                    len = 1;
 
-                };
+                },
+                0x16 => { // PAPF(パラメータ1バイト)
 
-                len = 1;
+                    len = control_length(data, index, 2);
 
-            },
-            _ => { // 上記以外は文字位置を進めるだけ
-                // 文字サイズ大
-                if AB_CHAR_SIZE_TABLE[gr as usize] == true {
+                },
+                0x1c => { // APS(パラメータ2バイト)
+
+                    len = control_length(data, index, 3);
+
+                },
+                0x1b => { // ESC
+
+                    // 後続データがある場合にエスケープコード処理
+                    if data.len() > index + 1 {
+                        match data[index + 1] {
+                            0x6e => { // LS2
+
+                                self.locking_gl = 2;
+                                len = 2;
+
+                            },
+                            0x6f => { // LS3
+
+                                self.locking_gl = 3;
+                                len = 2;
+
+                            },
+                            0x7c => { // LS3R
+
+                                self.locking_gr = 3;
+                                len = 2;
+
+                            },
+                            0x7d => { // LS2R
+
+                                self.locking_gr = 2;
+                                len = 2;
+
+                            },
+                            0x7e => { // LS1R
+
+                                self.locking_gr = 1;
+                                len = 2;
+
+                            },
+                            _ => {
+
+                                (len, _ret_str) = self.escape_control(data, index);
+
+                            },
+                        };
+                    }
+                    else {
+
+                        _ret_str = self.decode_error(DecodeErrorKind::TruncatedEscape, index);
+                        len = 1;
+
+                    };
+                },
+                0x19 => { // SS2(次の1文字のみG2をGLに呼び出し)
+
+                    (len, _ret_str) = self.single_shift(2, data, index);
+
+                },
+                0x1d => { // SS3(次の1文字のみG3をGLに呼び出し)
+
+                    (len, _ret_str) = self.single_shift(3, data, index);
+
+                },
+                0x20 => { // スペース
+                    // 半角スペース
+                    if self.str_size == STR_MEDIUM {
+
+                        _ret_str = String::from(" ");
+
+                    }
+                    // 全角スペース
+                    else {
+
+                        _ret_str = String::from("　");
+
+                    };
+
+                    len = 1;
+
+                },
+                _ => { // 上記以外(未定義の制御コード)は読み飛ばし
+
+                    len = 1;
+
+                },
+            };
+
+        }
+        // GRの処理
+        else {
+
+            match data[index] {
+                0x89 => { // MSZ
+
+                    self.str_size = STR_MEDIUM;
+                    len = 1;
+
+                },
+                0x8a => { // NSZ
+
+                    self.str_size = STR_NORMAL;
+                    len = 1;
+
+                },
+                0x88 => { // SSZ
+
+                    self.str_size = STR_SMALL;
+                    len = 1;
+
+                },
+                0x80..=0x87 | 0x99 | 0x9a => { // BKF〜WHF(文字色)、SPL、STL
+
+                    len = 1;
+
+                },
+                0x8b | 0x91 | 0x93 | 0x94 | 0x97 => { // SZX、FLC、POL、WMM、HLC(パラメータ1バイト)
+
+                    len = control_length(data, index, 2);
+
+                },
+                0x90 | 0x92 => { // COL、CDC(パラメータ1バイト、拡張時は2バイト)
 
+                    len = if data.get(index + 1) == Some(&0x20) { control_length(data, index, 3) } else { control_length(data, index, 2) };
+
+                },
+                0x9b => { // CSI(終端文字までのパラメータ)
+
+                    len = match data[index + 1..].iter().position(|code| (0x40..=0x6f).contains(code)) {
+                        Some(position) => position as i32 + 2,
+                        None => (data.len() - index) as i32,
+                    };
+
+                },
+                0xa0 => { // スペース
+
+                    // 半角スペース
+                    if self.str_size == STR_MEDIUM {
+
+                        _ret_str = String::from(" ");
+
+                    }
+                    // 全角スペース
+                    else {
+
+                        _ret_str = String::from("　");
+
+                    };
+
+                    len = 1;
+
+                },
+                0x95 => { // MACRO(マクロ定義)
+
+                    (len, _ret_str) = self.macro_define(data, index);
+
+                },
+                0x98 => { // RPC(次の1文字を繰り返し)
+
+                    (len, _ret_str) = self.repeat_char(data, index);
+
+                },
+                0x9d => { // TIME(処理待ちはパラメータ2バイト、時刻制御は終端文字までのパラメータ)
+
+                    len = if data.get(index + 1) == Some(&0x20) {
+                        control_length(data, index, 3)
+                    }
+                    else {
+                        match data.get(index + 2..).and_then(|params| params.iter().position(|code| (0x40..=0x43).contains(code))) {
+                            Some(position) => position as i32 + 3,
+                            None => (data.len() - index) as i32,
+                        }
+                    };
+
+                },
+                _ => { // 上記以外(DEL、未定義の制御コード)は読み飛ばし
+
+                    len = 1;
+
+                },
+            };
+        };
+
+        // リターン情報
+        (len, _ret_str)
+
+    }
+
+    // 文字コード変換処理(リターン情報：文字長、文字(String))
+    fn arib_parse(&mut self, data: &[u8], index: usize) -> (i32, String) {
+
+        #[allow(unused_assignments)]
+        let mut len: i32 = 0;
+        #[allow(unused_assignments)]
+        let mut ret_str: String = String::new();
+
+        let gl: u32 = self.gl();
+        let gr: u32 = self.gr();
+
+        // GL処理
+        if data[index] >= 0x21 && data[index] <= 0x7e {
+
+            // ２バイト文字
+            if AB_CHAR_SIZE_TABLE[gl as usize] {
+
+                if data.len() - index > 1 {
+
+                    // ２バイトコード処理
+                    ret_str = self.multi_byte_char(gl, data[index], data[index + 1], index);
                     len = 2;
 
                 }
-                // 文字サイズ小
                 else {
 
+                    // ２バイト目が無い場合は変換エラー
+                    ret_str = self.decode_error(DecodeErrorKind::InvalidCodePoint(data[index] as u32), index);
                     len = 1;
 
                 };
-            },
-        };
-    };
+            }
+            // １バイト文字
+            else {
 
-    // リターン情報
-    (len, _ret_str)
+                // １バイトコード処理
+                ret_str = self.single_byte_char(gl, data[index]);
 
-}
+                len = 1;
 
-// 文字コード変換処理(リターン情報：文字長、文字(String))
-fn arib_parse(data: &[u8], index: usize) -> (i32, String) {
+            };
+        }
+        // GR処理
+        else if data[index] >= 0xa1 && data[index] <= 0xfe {
 
-    #[allow(unused_assignments)]
-    let mut len: i32 = 0;
-    let mut ret_str: String = String::new();
+            // ２バイト文字
+            if AB_CHAR_SIZE_TABLE[gr as usize] {
 
-    let gl: u32 = G_PAGE[GL_NUM.load(Acquire) as usize].load(Acquire);
-    let gr: u32 = G_PAGE[GR_NUM.load(Acquire) as usize].load(Acquire);
+                if data.len() - index  > 1 {
 
-    // GL処理
-    if data[index] >= 0x21 && data[index] <= 0x7e {
+                    // ２バイトコード処理
+                    ret_str = self.multi_byte_char(gr, data[index] & 0x7f, data[index + 1] & 0x7f, index);
 
-        // ２バイト文字
-        if AB_CHAR_SIZE_TABLE[gl as usize] == true {
+                    len = 2;
 
-            if data.len() - index > 1 {
+                }
+                else {
 
-                // ２バイトコード処理
-                ret_str = multi_byte(gl, data[index], data[index + 1]);
-                len = 2;
+                    // ２バイト目が無い場合は変換エラー
+                    ret_str = self.decode_error(DecodeErrorKind::InvalidCodePoint((data[index] & 0x7f) as u32), index);
+                    len = 1;
 
+                }
             }
+            // １バイト文字
             else {
 
+                // １バイトコード処理
+                ret_str = self.single_byte_char(gr, data[index] & 0x7f);
+
                 len = 1;
 
             };
         }
-        // １バイト文字
+        // 制御コード
         else {
 
-            // １バイトコード処理
-            ret_str = single_byte(gl, data[index]);
-
-            len = 1;
+            //ret_str = "".to_string();
+            (len, ret_str) = self.invocation_set_graphic(data, index);
 
         };
+
+        // リターン情報
+        (len, ret_str)
+
     }
-    // GR処理
-    else if data[index] >= 0xa1 && data[index] <= 0xfe {
+}
 
-        // ２バイト文字
-        if AB_CHAR_SIZE_TABLE[gr as usize] == true {
+impl TextDecoder for AribDecoder {
 
-            if data.len() - index  > 1 {
+    // デコーダーの状態初期化処理
+    // (DRCS変換表、追加記号変換表、文字幅の正規化方式、改行の出力有無は引き継ぐ)
+    fn reset(&mut self) {
 
-                // ２バイトコード処理
-                ret_str = multi_byte(gr, data[index] & 0x7f, data[index + 1] & 0x7f);
+        let drcs = self.drcs.take();
+        let symbols = self.symbols.take();
+        let width = self.width;
+        let is_newline = self.is_newline;
+        *self = AribDecoder { drcs, symbols, width, is_newline, ..AribDecoder::default() };
 
-                len = 2;
+    }
 
-            }
-            else {
+    // 文字コード -> 文字への変換処理(リターン:文字列長、文字列(String))
+    // (変換エラーの文字は代替文字(U+FFFD)、エラー内容はerrors()で取得)
+    fn decode(&mut self, data: &[u8], length: i32) -> (i32, String) {
 
-                len = 1;
+        let mut _len: i32 = 0;
+        let mut ret_data: String = String::new();
+        let mut _ret_str: String = String::new();
+        let mut index: usize = 0;
 
-            }
-        }
-        // １バイト文字
-        else {
+        // グラフィックセット、GL.GR、文字サイズ情報の初期化
+        self.reset();
+
+        // 文字列長(データ長を超えない長さ)までを変換
+        let data = &data[..(length.max(0) as usize).min(data.len())];
+
+        // データが無くなるまでループ
+        while index < data.len() {
 
-            // １バイトコード処理
-            ret_str = single_byte(gr, data[index] & 0x7f);
+            // 文字コード変換処理呼び出し
+            self.offset = index;
+            (_len, _ret_str) = self.arib_parse(data, index);
+
+            // リターン文字列作成
+            ret_data.push_str(&_ret_str);
 
-            len = 1;
+            // カウンター更新
+            index += _len.max(1) as usize;
 
         };
+
+        // 文字幅の正規化
+        let ret_data = self.width.normalize(&ret_data);
+
+        // リターン情報
+        (ret_data.len().try_into().unwrap(), ret_data)
+
     }
-    // 制御コード
-    else {
+}
 
-        //ret_str = "".to_string();
-        (len, ret_str) = invocation_set_graphic(&data, index);
+impl AribDecoder {
 
-    };
+    // 文字コード -> 文字への変換処理(リターン:文字列、または最初の変換エラー)
+    pub fn decode_checked(&mut self, data: &[u8], length: i32) -> Result<String, DecodeError> {
+
+        let (_, ret_data) = self.decode(data, length);
+
+        match self.errors.first() {
+            Some(err) => Err(err.clone()),
+            None => Ok(ret_data),
+        }
 
-    // リターン情報
-    (len, ret_str)
+    }
+}
+
+// 制御コードの処理バイト数取得処理(データの終端を超えない長さ)
+fn control_length(data: &[u8], index: usize, len: usize) -> i32 {
+
+    len.min(data.len() - index) as i32
+
+}
+
+// 改行(APR)の出力有無の設定処理
+pub fn newline_init(is_newline: bool) {
+
+    NEWLINE.store(is_newline, Ordering::Relaxed);
+
+}
+
+// 追加記号の文字コード判定処理
+fn is_symbols_code(code: u32) -> bool {
+
+    (0x7a50..=0x7a74).contains(&code) || (0x7c21..=0x7c7b).contains(&code) || (0x7d21..=0x7d7b).contains(&code)
+        || (0x7e21..=0x7e7d).contains(&code) || (0x7521..=0x757e).contains(&code) || (0x7621..=0x764b).contains(&code)
+
+}
+
+// 文字コード -> 文字への変換処理(リターン:文字列、または最初の変換エラー)
+pub fn arib_to_string_checked(data: &[u8], length: i32) -> Result<String, DecodeError> {
+
+    AribDecoder::new().decode_checked(data, length)
 
 }
 
 // 文字コード -> 文字への変換処理(リターン:文字列長、文字列(String))
+// (呼び出し毎にデコーダーを作成するため、複数スレッドから同時に呼び出し可能)
+// (変換エラーの文字は代替文字(U+FFFD)、エラーのログ出力は最初の1回のみwarn)
 pub fn arib_to_string(data: &[u8], length: i32) -> (i32, String) {
 
-    let mut _len: i32 = 0;
-    let mut ret_data: String = String::new();
-    let mut _ret_str: String = String::new();
-    let mut index: usize = 0;
-    let mut loop_len: i32 = length;
+    let mut decoder = AribDecoder::new();
+    let ret = decoder.decode(data, length);
 
-    // エスケープカウンター初期化
-    ESC_SEQ_COUNT.store(0.try_into().unwrap(), Release);
+    if let Some(err) = decoder.errors().first() {
 
-    // グラフィックセットページ情報初期化
-    G_PAGE[0].store(CODE_KANJI.try_into().unwrap(), Release);
-    G_PAGE[1].store(CODE_ALPHANUMERIC.try_into().unwrap(), Release);
-    G_PAGE[2].store(CODE_HIRAGANA.try_into().unwrap(), Release);
-    G_PAGE[3].store(CODE_KATAKANA.try_into().unwrap(), Release);
+        if DECODE_ERROR_LOGGED.swap(true, Ordering::Relaxed) {
 
-    // GL.GR情報の初期化
-    GL_NUM.store(0.try_into().unwrap(), Release);
-    GR_NUM.store(2.try_into().unwrap(), Release);
+            debug!("arib decode error: {}", err);
 
-    // 文字サイズ情報の初期化
-    STR_SIZE.store(STR_NORMAL.try_into().unwrap(), Release);
+        }
+        else {
 
-    // データが無くなるまでループ
-    while loop_len > 0 {
+            warn!("arib decode error: {}", err);
 
-        // 文字コード変換処理呼び出し
-        (_len, _ret_str) = arib_parse(&data, index);
+        };
+    };
+
+    ret
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn decode(data: &[u8]) -> String {
+
+        AribDecoder::new().with_width(TextWidth::Raw).with_newline(false).decode(data, data.len() as i32).1
+
+    }
+
+    fn decode_newline(data: &[u8]) -> String {
+
+        AribDecoder::new().with_width(TextWidth::Raw).with_newline(true).decode(data, data.len() as i32).1
+
+    }
+
+    // G3にマクロを指示してSS3でマクロ符号を呼び出した後のGセット
+    fn default_macro_g_page(code: u8) -> [u32; 4] {
+
+        let data = [0x1b, 0x2b, 0x20, 0x70, 0x1d, code];
+        let mut decoder = AribDecoder::new();
+        decoder.decode(&data, data.len() as i32);
+
+        assert_eq!((decoder.locking_gl, decoder.locking_gr), (0, 2));
+        decoder.g_page
+
+    }
+
+    #[test]
+    fn c0_ignored_controls() {
+
+        // NUL、BEL、APB、APD、APU、CS、CAN、RS、US
+        for code in [0x00, 0x07, 0x08, 0x0a, 0x0b, 0x0c, 0x18, 0x1e, 0x1f] {
+
+            assert_eq!(decode(&[0x24, 0x22, code, 0x24, 0x24]), "あい", "code=0x{:02x}", code);
 
-        // リターン文字列作成
-        if _len > 0 {
-            ret_data.push_str(&_ret_str);
         };
 
-        // カウンター更新
-        index += _len as usize;
-        loop_len -= _len;
+    }
 
-    };
+    #[test]
+    fn c0_apr_apf() {
+
+        assert_eq!(decode(&[0x24, 0x22, 0x0d, 0x24, 0x24, 0x09, 0x24, 0x26]), "あいう");
+        assert_eq!(decode_newline(&[0x24, 0x22, 0x0d, 0x24, 0x24, 0x09, 0x24, 0x26]), "あ\nい う");
+
+    }
+
+    #[test]
+    fn c0_papf_aps() {
+
+        assert_eq!(decode(&[0x16, 0x24, 0x24, 0x22]), "あ");
+        assert_eq!(decode(&[0x1c, 0x24, 0x22, 0x24, 0x24]), "い");
+
+    }
+
+    #[test]
+    fn c0_locking_shift() {
+
+        // LS1(英数)、LS0(漢字)
+        assert_eq!(decode(&[0x89, 0x0e, 0x41, 0x0f, 0x8a, 0x24, 0x22]), "Aあ");
+
+        // LS2(ひらがな)、LS3(カタカナ)
+        assert_eq!(decode(&[0x1b, 0x6e, 0x22, 0x1b, 0x6f, 0x22]), "あア");
+
+    }
+
+    #[test]
+    fn c0_single_shift() {
+
+        // SS2(ひらがな)、SS3(カタカナ)は次の1文字のみ
+        assert_eq!(decode(&[0x19, 0x22, 0x1d, 0x22, 0x24, 0x22]), "あアあ");
+
+        // GRの文字コードもシングルシフトしたGセットで変換
+        assert_eq!(decode(&[0x19, 0xa2, 0x1d, 0xa2]), "あア");
+
+        // SS3で2バイトGセット(追加記号)
+        assert_eq!(decode(&[0x1b, 0x24, 0x2b, 0x3b, 0x1d, 0x7a, 0x56, 0x1d, 0xfa, 0xd6]), "[字][字]");
+
+    }
+
+    #[test]
+    fn c0_escape_locking_shift_right() {
 
-    // リターン情報
-    (ret_data.len().try_into().unwrap(), ret_data)
+        // LS1R(英数)、LS3R(カタカナ)、LS2R(ひらがな)
+        assert_eq!(decode(&[0x89, 0x1b, 0x7e, 0xc1, 0x8a, 0x1b, 0x7c, 0xa2, 0x1b, 0x7d, 0xa2]), "Aアあ");
+
+    }
+
+    #[test]
+    fn c0_escape_designation() {
+
+        // 2バイトGセットのG0への指示(中間文字あり)
+        assert_eq!(decode(&[0x1b, 0x28, 0x30, 0x1b, 0x24, 0x28, 0x42, 0x30, 0x21]), "亜");
+
+    }
+
+    #[test]
+    fn c0_space() {
+
+        assert_eq!(decode(&[0x20, 0x89, 0x20, 0x88, 0x20]), "　 　");
+
+    }
+
+    #[test]
+    fn c1_size() {
+
+        // MSZ(半角)、NSZ(全角)、SSZ(小型)
+        assert_eq!(decode(&[0x0e, 0x89, 0x41, 0x8a, 0x41, 0x88, 0x41]), "AＡＡ");
+
+    }
+
+    #[test]
+    fn c1_no_parameter() {
+
+        // BKF〜WHF、SPL、STL
+        for code in [0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x99, 0x9a] {
+
+            assert_eq!(decode(&[code, 0x24, 0x22]), "あ", "code=0x{:02x}", code);
+
+        };
+
+    }
 
+    #[test]
+    fn c1_one_parameter() {
+
+        // SZX、FLC、POL、WMM、HLC
+        for code in [0x8b, 0x91, 0x93, 0x94, 0x97] {
+
+            assert_eq!(decode(&[code, 0x24, 0x24, 0x22]), "あ", "code=0x{:02x}", code);
+
+        };
+
+    }
+
+    #[test]
+    fn c1_col_cdc() {
+
+        for code in [0x90, 0x92] {
+
+            assert_eq!(decode(&[code, 0x48, 0x24, 0x22]), "あ", "code=0x{:02x}", code);
+            assert_eq!(decode(&[code, 0x20, 0x40, 0x24, 0x22]), "あ", "code=0x{:02x}", code);
+
+        };
+
+    }
+
+    #[test]
+    fn c1_csi() {
+
+        // SWF(0x53)
+        assert_eq!(decode(&[0x9b, 0x37, 0x3b, 0x33, 0x20, 0x53, 0x24, 0x22]), "あ");
+
+    }
+
+    #[test]
+    fn c1_time() {
+
+        // 処理待ち
+        assert_eq!(decode(&[0x9d, 0x20, 0x41, 0x24, 0x22]), "あ");
+
+        // 時刻制御モード
+        assert_eq!(decode(&[0x9d, 0x28, 0x41, 0x24, 0x22]), "あ");
+
+        // 提示開始時刻
+        assert_eq!(decode(&[0x9d, 0x29, 0x31, 0x32, 0x43, 0x24, 0x22]), "あ");
+
+    }
+
+    #[test]
+    fn c1_rpc() {
+
+        assert_eq!(decode(&[0x98, 0x43, 0x24, 0x22, 0x24, 0x24]), "あああい");
+
+    }
+
+    #[test]
+    fn c1_macro_define() {
+
+        // マクロ符号0x21を定義(G0にカタカナを指示)し、G3にマクロを指示して呼び出し
+        let data = [
+            0x95, 0x40, 0x21, 0x1b, 0x28, 0x31, 0x95, 0x4f,
+            0x24, 0x22,
+            0x1b, 0x2b, 0x20, 0x70, 0x1d, 0x21,
+            0x22,
+        ];
+        assert_eq!(decode(&data), "あア");
+
+        // 定義と同時に実行(0x41)
+        assert_eq!(decode(&[0x95, 0x41, 0x21, 0x1b, 0x28, 0x31, 0x95, 0x4f, 0x22]), "ア");
+
+        // 定義内容の文字も出力
+        assert_eq!(decode(&[0x95, 0x41, 0x21, 0x24, 0x22, 0x95, 0x4f]), "あ");
+
+    }
+
+    #[test]
+    fn c1_macro_recursive() {
+
+        // 自身を呼び出すマクロも終了
+        let data = [0x1b, 0x2b, 0x20, 0x70, 0x95, 0x41, 0x21, 0x24, 0x22, 0x1d, 0x21, 0x95, 0x4f];
+        assert_eq!(decode(&data), "あ".repeat(MACRO_DEPTH_MAX as usize));
+
+    }
+
+    #[test]
+    fn del() {
+
+        assert_eq!(decode(&[0x24, 0x22, 0x7f, 0xff, 0x24, 0x24]), "あい");
+
+    }
+
+    #[test]
+    fn checked_truncated_escape() {
+
+        for data in [&[0x24, 0x22, 0x1b][..], &[0x24, 0x22, 0x1b, 0x24], &[0x24, 0x22, 0x1b, 0x29, 0x20]] {
+
+            let err = arib_to_string_checked(data, data.len() as i32).unwrap_err();
+            assert_eq!(err, DecodeError { kind: DecodeErrorKind::TruncatedEscape, offset: 2 }, "data={:02x?}", data);
+
+        };
+
+    }
+
+    #[test]
+    fn checked_unsupported_set() {
+
+        let data = [0x24, 0x22, 0x1b, 0x28, 0x7b, 0x22];
+        assert_eq!(arib_to_string_checked(&data, data.len() as i32).unwrap_err(),
+            DecodeError { kind: DecodeErrorKind::UnsupportedSet(0x7b), offset: 2 });
+
+        // 非対応のGセットの文字は代替文字
+        assert_eq!(decode(&data), "あ\u{fffd}");
+
+    }
+
+    #[test]
+    fn checked_invalid_code_point() {
+
+        // JIS X 0208の未定義領域
+        let data = [0x24, 0x22, 0x2f, 0x21];
+        assert_eq!(arib_to_string_checked(&data, data.len() as i32).unwrap_err(),
+            DecodeError { kind: DecodeErrorKind::InvalidCodePoint(0x2f21), offset: 2 });
+
+        // ２バイト目が無い文字
+        let data = [0x24, 0x22, 0x24];
+        assert_eq!(arib_to_string_checked(&data, data.len() as i32).unwrap_err(),
+            DecodeError { kind: DecodeErrorKind::InvalidCodePoint(0x24), offset: 2 });
+
+        // 追加記号の未定義領域
+        let data = [0x1b, 0x24, 0x2b, 0x3b, 0x1d, 0x7b, 0x21];
+        assert_eq!(arib_to_string_checked(&data, data.len() as i32).unwrap_err(),
+            DecodeError { kind: DecodeErrorKind::InvalidCodePoint(0x7b21), offset: 5 });
+
+    }
+
+    #[test]
+    fn checked_ok() {
+
+        let data = [0x24, 0x22, 0x89, 0x0e, 0x41];
+        assert_eq!(arib_to_string_checked(&data, data.len() as i32), Ok(String::from("あA")));
+
+    }
+
+    #[test]
+    fn lenient_replacement() {
+
+        let data = [0x24, 0x22, 0x2f, 0x21, 0x24, 0x24, 0x1b];
+        assert_eq!(arib_to_string(&data, data.len() as i32).1, "あ\u{fffd}い\u{fffd}");
+
+        // 文字列長がデータ長を超える場合もデータの終端まで
+        assert_eq!(arib_to_string(&data[..2], 10).1, "あ");
+
+    }
+
+    #[test]
+    fn default_macros() {
+
+        let hira = CODE_HIRAGANA;
+        let kata = CODE_KATAKANA;
+        let alnum = CODE_ALPHANUMERIC;
+        let kanji = CODE_KANJI;
+        let drcs = |n: u32| CODE_DRCS_0 + n;
+
+        let expected: [(u8, [u32; 4]); 16] = [
+            (0x60, [kanji, alnum, hira, CODE_MACRO]),
+            (0x61, [kanji, kata, hira, CODE_MACRO]),
+            (0x62, [kanji, drcs(1), hira, CODE_MACRO]),
+            (0x63, [CODE_MOSAIC_A, CODE_MOSAIC_C, CODE_MOSAIC_D, CODE_MACRO]),
+            (0x64, [CODE_MOSAIC_A, CODE_MOSAIC_B, CODE_MOSAIC_D, CODE_MACRO]),
+            (0x65, [CODE_MOSAIC_A, drcs(1), CODE_MOSAIC_D, CODE_MACRO]),
+            (0x66, [drcs(1), drcs(2), drcs(3), CODE_MACRO]),
+            (0x67, [drcs(4), drcs(5), drcs(6), CODE_MACRO]),
+            (0x68, [drcs(7), drcs(8), drcs(9), CODE_MACRO]),
+            (0x69, [drcs(10), drcs(11), drcs(12), CODE_MACRO]),
+            (0x6a, [drcs(13), drcs(14), drcs(15), CODE_MACRO]),
+            (0x6b, [kanji, drcs(2), hira, CODE_MACRO]),
+            (0x6c, [kanji, drcs(3), hira, CODE_MACRO]),
+            (0x6d, [kanji, drcs(4), hira, CODE_MACRO]),
+            (0x6e, [kata, hira, alnum, CODE_MACRO]),
+            (0x6f, [alnum, CODE_MOSAIC_A, drcs(1), CODE_MACRO]),
+        ];
+
+        for (code, g_page) in expected.iter() {
+
+            assert_eq!(default_macro_g_page(*code), *g_page, "macro=0x{:02x}", code);
+
+        };
+
+    }
+
+    #[test]
+    fn default_macro_decode() {
+
+        // マクロ0x6e(G0:カタカナ、G1:ひらがな、G2:英数)の後はGLがカタカナ、GRが英数
+        assert_eq!(decode(&[0x1b, 0x2b, 0x20, 0x70, 0x1d, 0x6e, 0x22, 0x0e, 0x22, 0x89, 0xc1]), "アあA");
+
+    }
+
+    #[test]
+    fn with_drcs_decode() {
+
+        let pattern = crate::drcs::DrcsPattern {
+            character_code: 0x4121, font_id: 0, mode: 0, depth: 0, width: 16, height: 2,
+            pattern_data: vec![0x12, 0x34, 0x56, 0x78],
+        };
+        let mut table = DrcsTable::default();
+        table.add_pattern(&pattern);
+        table.add_map(&pattern.hash(), "♡");
+
+        // G0にDRCS-1を指示(対応表にある文字、無い文字)
+        let data = [0x1b, 0x28, 0x20, 0x41, 0x21, 0x22];
+        let text = AribDecoder::new().with_drcs(Arc::new(table)).with_width(TextWidth::Raw)
+            .decode(&data, data.len() as i32).1;
+        assert_eq!(text, format!("♡{}", DRCS_PLACEHOLDER));
+
+    }
 }
@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::Write;
+
+use crate::{CommanLineOpt, newline_flatten, programme_list, programme_repair};
+use crate::genre::{GenreLang, genre_names};
+use crate::tot::{TotState};
+use crate::ts::{EitControl, SvtControl, SvtControlTop};
+
+// 区切り文字形式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Delimiter {
+    Csv,   // カンマ区切り
+    Tsv,   // タブ区切り
+}
+
+// 出力項目
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Column {
+    Channel,    // チャンネル
+    Service,    // サービス名
+    EventId,    // イベントID
+    Start,      // 開始日時
+    End,        // 終了日時
+    Duration,   // 時間長(秒)
+    Title,      // タイトル
+    Subtitle,   // サブタイトル(説明)
+    Desc,       // 詳細説明
+    Genre,      // ジャンル名称
+    Episode,    // 話数
+    Flags,      // タイトルの記号
+}
+
+// 出力項目の名称(ヘッダー、--columnsの指定)
+const COLUMN_NAME_TABLE: [(Column, &str); 12] = [
+    (Column::Channel,  "channel"),
+    (Column::Service,  "service"),
+    (Column::EventId,  "event_id"),
+    (Column::Start,    "start"),
+    (Column::End,      "end"),
+    (Column::Duration, "duration"),
+    (Column::Title,    "title"),
+    (Column::Subtitle, "subtitle"),
+    (Column::Desc,     "desc"),
+    (Column::Genre,    "genre"),
+    (Column::Episode,  "episode"),
+    (Column::Flags,    "flags"),
+];
+
+// 既定の出力項目
+pub const DEFAULT_COLUMNS: [Column; 8] = [
+    Column::Channel, Column::Start, Column::End, Column::Title,
+    Column::Subtitle, Column::Genre, Column::Episode, Column::Flags,
+];
+
+// Excel向けのBOM(UTF-8)
+const UTF8_BOM: &str = "\u{feff}";
+
+impl Delimiter {
+
+    //
+    // 区切り文字の取得処理
+    //
+    fn separator(&self) -> char {
+
+        match self {
+            Delimiter::Csv => ',',
+            Delimiter::Tsv => '\t',
+        }
+
+    }
+
+    //
+    // 項目の引用処理
+    // (区切り文字、ダブルクォート、改行を含む場合はダブルクォートで囲み、ダブルクォートは2つに置き換え)
+    //
+    fn quote(&self, field: &str) -> String {
+
+        if field.contains([self.separator(), '"', '\r', '\n']) {
+
+            format!("\"{}\"", field.replace('"', "\"\""))
+
+        }
+        else {
+
+            field.to_string()
+
+        }
+
+    }
+}
+
+impl Column {
+
+    //
+    // 項目名の取得処理
+    //
+    fn name(&self) -> &'static str {
+
+        COLUMN_NAME_TABLE.iter().find(|(column, _)| column == self).map(|(_, name)| *name).unwrap()
+
+    }
+}
+
+//
+// 出力項目の解析処理(csv形式、不明な項目名がある場合はNone)
+//
+pub fn column_list_parse(text: &str) -> Option<Vec<Column>> {
+
+    let columns: Option<Vec<Column>> = text.split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| COLUMN_NAME_TABLE.iter().find(|(_, column_name)| *column_name == name).map(|(column, _)| *column))
+        .collect();
+
+    columns.filter(|columns| !columns.is_empty())
+
+}
+
+//
+// 番組データの項目作成処理(未定義の項目は空)
+//
+fn column_value(cmd_opt: &CommanLineOpt, tot: &TotState, svtcur: &SvtControl, eitcur: &EitControl, column: Column) -> String {
+
+    let text = |text: &str| newline_flatten(text, cmd_opt.is_flatten_newline);
+
+    match column {
+        Column::Channel => svtcur.ontv.clone(),
+        Column::Service => text(&svtcur.servicename),
+        Column::EventId => eitcur.event_id.to_string(),
+        Column::Start => eitcur.start().map(|start_time| tot.format_time(start_time, cmd_opt.time_format)).unwrap_or_default(),
+        Column::End => eitcur.end().map(|end_time| tot.format_time(end_time, cmd_opt.time_format)).unwrap_or_default(),
+        Column::Duration => if eitcur.is_duration_undefined() { String::new() } else { eitcur.duration.to_string() },
+        Column::Title => text(&eitcur.title),
+        Column::Subtitle => text(&eitcur.subtitle),
+        Column::Desc => text(&eitcur.desc),
+        Column::Genre => genre_names(&eitcur.genres, GenreLang::Ja).join(" / "),
+        Column::Episode => if eitcur.episode_number > 0 { eitcur.episode_number.to_string() } else { String::new() },
+        Column::Flags => eitcur.markers.iter().map(|marker| marker.name()).collect::<Vec<&str>>().join(","),
+    }
+
+}
+
+//
+// 1行の出力処理(改行はCRLF)
+//
+fn write_row(outfile: &mut File, delimiter: Delimiter, fields: &[String]) {
+
+    let row: Vec<String> = fields.iter().map(|field| delimiter.quote(field)).collect();
+    write!(outfile, "{}\r\n", row.join(&delimiter.separator().to_string())).unwrap();
+
+}
+
+//
+// サービス毎の番組データ出力処理
+//
+fn delimited_service(cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, delimiter: Delimiter, svtcur: &mut SvtControl) {
+
+    // 番組データ補正処理
+    programme_repair(cmd_opt, tot, svtcur);
+
+    // 出力対象の番組作成(EIT SCHに無いEIT PFの番組を追加)
+    for eitcur in programme_list(cmd_opt, svtcur) {
+
+        let fields: Vec<String> = cmd_opt.columns.iter()
+            .map(|column| column_value(cmd_opt, tot, svtcur, eitcur, *column))
+            .collect();
+        write_row(outfile, delimiter, &fields);
+
+    };
+}
+
+//
+// CSV/TSVデータ作成処理
+//
+pub fn dump_delimited(cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, delimiter: Delimiter, svttop: &mut [SvtControlTop]) {
+
+    // BOM出力
+    if cmd_opt.is_bom {
+
+        write!(outfile, "{}", UTF8_BOM).unwrap();
+
+    };
+
+    // ヘッダー出力
+    let header: Vec<String> = cmd_opt.columns.iter().map(|column| column.name().to_string()).collect();
+    write_row(outfile, delimiter, &header);
+
+    // svttop配列分ループ
+    for svt in svttop.iter_mut() {
+
+        delimited_service(cmd_opt, tot, outfile, delimiter, &mut svt.svt_control_sub[0]);
+
+    };
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn quote_field() {
+
+        // 引用不要
+        assert_eq!(Delimiter::Csv.quote("ニュース"), "ニュース");
+        assert_eq!(Delimiter::Tsv.quote("a,b"), "a,b");
+
+        // 区切り文字、ダブルクォート、改行を含む場合
+        assert_eq!(Delimiter::Csv.quote("a,b"), "\"a,b\"");
+        assert_eq!(Delimiter::Tsv.quote("a\tb"), "\"a\tb\"");
+        assert_eq!(Delimiter::Csv.quote("\"再\"放送"), "\"\"\"再\"\"放送\"");
+        assert_eq!(Delimiter::Csv.quote("a\r\nb"), "\"a\r\nb\"");
+        assert_eq!(Delimiter::Tsv.quote("a\nb"), "\"a\nb\"");
+
+    }
+
+    #[test]
+    fn column_list() {
+
+        assert_eq!(column_list_parse("channel, start,title,,event_id"),
+            Some(vec![Column::Channel, Column::Start, Column::Title, Column::EventId]));
+
+        // 不明な項目名、項目なし
+        assert_eq!(column_list_parse("channel,unknown"), None);
+        assert_eq!(column_list_parse(" , "), None);
+
+    }
+
+}
@@ -0,0 +1,355 @@
+
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+// DRCSの代替文字(パターン、対応表が無い場合)
+pub const DRCS_PLACEHOLDER: &str = "〓";
+
+// DRCS対応表の定義ファイル
+const DRCS_MAP_FILE: [&str; 2] = [
+    "/etc/epgdump/drcs_map.conf",
+    "/usr/local/etc/epgdump/drcs_map.conf",
+];
+
+// DRCSパターンのモード
+const DRCS_MODE_2_TONE: u8 = 0x00;      // 2階調
+const DRCS_MODE_MULTI_TONE: u8 = 0x01;  // 多階調
+
+// プロセス共通のDRCS変換表(起動時に1度だけ設定)
+static DRCS_TABLE: OnceLock<Arc<DrcsTable>> = OnceLock::new();
+
+// DRCSパターン構造体
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct DrcsPattern {
+    pub character_code: u16,   // DRCS-0は2バイト符号、DRCS-1〜15は終端符号(0x41〜0x4f)+1バイト符号
+    pub font_id: u8,
+    pub mode: u8,
+    pub depth: u8,
+    pub width: u8,
+    pub height: u8,
+    pub pattern_data: Vec<u8>,
+}
+
+impl DrcsPattern {
+
+    //
+    // パターンのハッシュ(MD5)作成処理
+    //
+    pub fn hash(&self) -> String {
+
+        format!("{:X}", md5::compute(&self.pattern_data))
+
+    }
+}
+
+// DRCS変換表構造体
+#[derive(Debug, Clone, Default)]
+pub struct DrcsTable {
+    patterns: HashMap<u16, String>,    // 文字符号 -> パターンのハッシュ
+    map: HashMap<String, String>,      // パターンのハッシュ -> 置き換え文字列
+}
+
+impl DrcsTable {
+
+    //
+    // DRCSパターンの登録処理
+    //
+    pub fn add_pattern(&mut self, pattern: &DrcsPattern) {
+
+        debug!("DRCS character_code=0x{:04x} size={}x{} hash={}",
+            pattern.character_code, pattern.width, pattern.height, pattern.hash());
+        self.patterns.insert(pattern.character_code, pattern.hash());
+
+    }
+
+    //
+    // 置き換え文字列の登録処理
+    //
+    pub fn add_map(&mut self, hash: &str, text: &str) {
+
+        self.map.insert(hash.to_uppercase(), text.to_string());
+
+    }
+
+    //
+    // 対応表ファイルの読込み処理(「ハッシュ=文字列」形式、「#」「;」はコメント行、「[」はセクション行)
+    //
+    pub fn load_map(&mut self, path: &str) -> std::io::Result<usize> {
+
+        let buffer = BufReader::new(File::open(path)?);
+        let mut map_cnt = 0;
+
+        // ファイルからリード処理(１行づつ)
+        for line in buffer.lines() {
+
+            let line_data = line?;
+            let line_data = line_data.trim();
+
+            // コメント行、セクション行は読み飛ばし
+            if line_data.is_empty() || line_data.starts_with(['#', ';', '[']) {
+
+                continue;
+
+            };
+
+            // 「=」で分割して登録
+            if let Some((hash, text)) = line_data.split_once('=') {
+
+                self.add_map(hash.trim(), text.trim());
+                map_cnt += 1;
+
+            };
+        };
+
+        debug!("drcs map file = {} map_cnt={}", path, map_cnt);
+
+        Ok(map_cnt)
+
+    }
+
+    //
+    // DRCSデータ構造ファイルの読込み処理
+    //
+    pub fn load_pattern(&mut self, path: &str) -> std::io::Result<usize> {
+
+        let mut data: Vec<u8> = vec![];
+        File::open(path)?.read_to_end(&mut data)?;
+
+        let patterns = drcs_parse(&data);
+        for pattern in patterns.iter() {
+
+            self.add_pattern(pattern);
+
+        };
+
+        Ok(patterns.len())
+
+    }
+
+    //
+    // DRCS文字符号の文字列変換処理(対応が無い場合は代替文字)
+    //
+    pub fn lookup(&self, character_code: u16) -> &str {
+
+        self.patterns.get(&character_code)
+            .and_then(|hash| self.map.get(hash))
+            .map_or(DRCS_PLACEHOLDER, |text| text.as_str())
+
+    }
+}
+
+//
+// DRCSデータ構造(ARIB STD-B24 DRCS_data_structure)の解析処理
+// (不正なデータの場合は解析できた分まで)
+//
+pub fn drcs_parse(data: &[u8]) -> Vec<DrcsPattern> {
+
+    let mut patterns: Vec<DrcsPattern> = vec![];
+
+    // 符号数の取得
+    let number_of_code = match data.first() {
+        Some(number_of_code) => *number_of_code,
+        None => return patterns,
+    };
+    let mut index = 1;
+
+    // 符号数分ループ
+    for _ in 0..number_of_code {
+
+        // 文字符号、フォント数の取得
+        if index + 3 > data.len() {
+
+            break;
+
+        };
+        let character_code = ((data[index] as u16) << 8) + data[index + 1] as u16;
+        let number_of_font = data[index + 2];
+        index += 3;
+
+        // フォント数分ループ
+        for _ in 0..number_of_font {
+
+            if index + 1 > data.len() {
+
+                return patterns;
+
+            };
+            let font_id = data[index] >> 4;
+            let mode = data[index] & 0x0f;
+            index += 1;
+
+            // パターンデータの処理
+            if mode == DRCS_MODE_2_TONE || mode == DRCS_MODE_MULTI_TONE {
+
+                if index + 3 > data.len() {
+
+                    return patterns;
+
+                };
+                let depth = data[index];
+                let width = data[index + 1];
+                let height = data[index + 2];
+                index += 3;
+
+                // 1画素のビット数(2階調は1、多階調は階調数から算出)
+                let bits = if mode == DRCS_MODE_2_TONE {
+                    1
+                }
+                else {
+                    (depth as u32 + 2).next_power_of_two().trailing_zeros() as usize
+                };
+                let pattern_length = (width as usize * height as usize * bits).div_ceil(8);
+
+                if index + pattern_length > data.len() {
+
+                    return patterns;
+
+                };
+                patterns.push(DrcsPattern {
+                    character_code,
+                    font_id,
+                    mode,
+                    depth,
+                    width,
+                    height,
+                    pattern_data: data[index..index + pattern_length].to_vec(),
+                });
+                index += pattern_length;
+
+            }
+            // 幾何図形データは読み飛ばし
+            else {
+
+                if index + 4 > data.len() {
+
+                    return patterns;
+
+                };
+                let geometric_data_length = ((data[index + 2] as usize) << 8) + data[index + 3] as usize;
+                index += 4 + geometric_data_length;
+
+            };
+        };
+    };
+
+    patterns
+
+}
+
+//
+// プロセス共通のDRCS変換表の設定処理
+//
+pub fn drcs_table_init(table: DrcsTable) {
+
+    if DRCS_TABLE.set(Arc::new(table)).is_err() {
+
+        warn!("DRCS table is already initialized");
+
+    };
+
+}
+
+//
+// プロセス共通のDRCS変換表の取得処理
+//
+pub fn drcs_table() -> Option<Arc<DrcsTable>> {
+
+    DRCS_TABLE.get().cloned()
+
+}
+
+//
+// DRCS変換表の作成処理(対応表ファイルの指定が無い場合は定義ファイルを検索)
+//
+pub fn drcs_table_read(map_file: Option<&str>, pattern_files: &[String]) -> DrcsTable {
+
+    let mut table = DrcsTable::default();
+
+    // 対応表ファイルの読込み
+    let map_file = map_file.or_else(|| DRCS_MAP_FILE.iter().copied().find(|file| Path::new(file).exists()));
+    if let Some(map_file) = map_file {
+
+        if let Err(err) = table.load_map(map_file) {
+
+            warn!("File Open Error({}:{})", map_file, err);
+
+        };
+    };
+
+    // DRCSデータ構造ファイルの読込み
+    for pattern_file in pattern_files.iter() {
+
+        if let Err(err) = table.load_pattern(pattern_file) {
+
+            warn!("File Open Error({}:{})", pattern_file, err);
+
+        };
+    };
+
+    table
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fs;
+
+    // テスト用の一時ファイル作成処理
+    fn temp_file(name: &str, data: &[u8]) -> String {
+
+        let path = std::env::temp_dir().join(format!("epgdump_drcs_{}_{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+
+        path.to_str().unwrap().to_string()
+
+    }
+
+    #[test]
+    fn load_map_file() {
+
+        let path = temp_file("map.conf", "[DRCS]\n# コメント\n; コメント\n\n0123456789abcdef0123456789ABCDEF = ♡\nFEDCBA9876543210FEDCBA9876543210=【ハート】\n不正な行\n".as_bytes());
+
+        let mut table = DrcsTable::default();
+        assert_eq!(table.load_map(&path).unwrap(), 2);
+        fs::remove_file(&path).unwrap();
+
+        // ハッシュは大文字で登録、前後の空白は削除
+        assert_eq!(table.map.get("0123456789ABCDEF0123456789ABCDEF").map(|text| text.as_str()), Some("♡"));
+        assert_eq!(table.map.get("FEDCBA9876543210FEDCBA9876543210").map(|text| text.as_str()), Some("【ハート】"));
+
+        // ファイルが無い場合はエラー
+        assert!(table.load_map(&path).is_err());
+
+    }
+
+    #[test]
+    fn load_pattern_hash() {
+
+        // 符号数2(DRCS-1の0x21:2階調16x2、DRCS-0の0x2121:多階調4階調4x2)
+        let path = temp_file("pattern.bin", &[
+            0x02,
+            0x41, 0x21, 0x01, 0x00, 0x00, 0x10, 0x02, 0x12, 0x34, 0x56, 0x78,
+            0x21, 0x21, 0x01, 0x01, 0x02, 0x04, 0x02, 0xe4, 0x1b,
+        ]);
+
+        let mut table = DrcsTable::default();
+        assert_eq!(table.load_pattern(&path).unwrap(), 2);
+        fs::remove_file(&path).unwrap();
+
+        // パターンデータのMD5(大文字)で対応表を参照
+        assert_eq!(table.patterns.get(&0x4121).map(|hash| hash.as_str()), Some("891A26E0581A7F2C9A574CEFF1549EE1"));
+        table.add_map("891a26e0581a7f2c9a574ceff1549ee1", "♡");
+        assert_eq!(table.lookup(0x4121), "♡");
+        assert_eq!(table.lookup(0x2121), DRCS_PLACEHOLDER);
+        assert_eq!(table.patterns.get(&0x2121).map(|hash| hash.len()), Some(32));
+
+    }
+
+}
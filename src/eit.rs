@@ -1,15 +1,21 @@
 extern crate chrono;
 
-use chrono::{DateTime, Local, TimeZone};
+use chrono::{DateTime, FixedOffset, TimeZone};
 use log::{error};
+use std::collections::{BTreeSet};
 //use chrono::prelude::{Datelike, Timelike};
 #[allow(unused_imports)]
 use log::{debug, info};
 
 use crate::arib::{arib_to_string};
 use crate::{CommanLineOpt};
+use crate::genre::{genre_parse};
+use crate::marker::{marker_title};
+use crate::tot::{jst, mjd_to_timestamp};
 use crate::sdt::{service_id_cehck};
-use crate::ts::{MAXSECLEN, EitControl, SvtControlTop};
+use crate::ts::{MAXSECLEN, START_TIME_UNDEFINED, EitAudio, EitControl, EitDescItem, EitLangText, EitNullSegment, EitRelatedItem,
+    EitSeries, SvtControlTop};
+use crate::store::{EitStore};
 
 // EITヘッダー構造体
 #[derive(Debug, Copy, Clone)]
@@ -60,6 +66,7 @@ struct SevtDesc {
     iso_639_language_code: String,
     event_name_length: i32,
     event_name: String,
+    marker_name: String,       // イベント名(記号の判定用)
     text_length: i32,
     text: String,
 }
@@ -149,6 +156,7 @@ struct EevtdItem {
     item: String,
     //退避用
     descriptor_number: i32,
+    iso_639_language_code: String,
 }
 
 // 拡張イベント記述子終端構造体
@@ -197,139 +205,158 @@ fn chk_bcd(src: u8) -> bool {
 }
 
 //
-// eittopへ追加、挿入処理
+// 言語別文字情報の取得処理(未登録の言語は追加)
 //
-fn eittop_data_update(eittop: &mut Vec<EitControl>,
-    eith: &EitHead, eitb: &EitBody, sevtd: &SevtDesc) -> () {
+fn lang_text_entry<'a>(eitcur: &'a mut EitControl, lang: &str) -> &'a mut EitLangText {
+
+    // 登録済み言語の検索
+    let pos = match eitcur.lang_text.iter().position(|lang_text| lang_text.lang == lang) {
+        Some(pos) => pos,
+        None => {
+
+            // 未登録の言語を追加
+            eitcur.lang_text.push(EitLangText {
+                lang: lang.to_string(),
+                title: String::new(),
+                marker_title: String::new(),
+                subtitle: String::new(),
+                desc: String::new(),
+                desc_items: vec![],
+            });
+
+            eitcur.lang_text.len() - 1
+
+        },
+    };
 
+    // リターン情報
+    &mut eitcur.lang_text[pos]
 
-    // push_cnt初期化
-    let mut push_cnt: i32 = -1;
+}
 
-    // データ挿入位置の確認
-    for loop_cnt in 0..eittop.len() {
-        if eittop[loop_cnt].start_time > eitb.start_time {
-            push_cnt = loop_cnt as i32;
-            break;
-        }
-    }
+//
+// 優先言語によるタイトル、説明の選択処理
+//
+pub fn lang_text_select(eitcur: &mut EitControl, lang_order: &[String]) {
 
-    // 最後に追加
-    if push_cnt == -1 {
-
-        // DateTime形式の時刻情報を作成
-        let dt: DateTime<Local> = match Local.with_ymd_and_hms(
-            eitb.yy as i32 + 1900, eitb.mm as u32, eitb.dd as u32,
-            eitb.hh as u32, eitb.hm as u32, eitb.ss as u32).single() {
-            Some(date_time) => date_time,
-            None => {
-                error!("eittop_data_update 日付変換エラー(最後に追加) 
-                    yy={} , mm={} , dd={} , hh={} , hm={} , ss={}",
-                    eitb.yy + 1900, eitb.mm, eitb.dd, eitb.hh, eitb.hm, eitb.ss);
-                return
-            },
-        };
+    // 優先言語順に検索し、該当が無い場合は最初に受信した言語を選択
+    let pos = lang_order.iter()
+        .find_map(|lang| eitcur.lang_text.iter().position(|lang_text| &lang_text.lang == lang))
+        .unwrap_or(0);
 
-        // データ更新
-        eittop.push(EitControl {
-            table_id: eith.table_id as i32,
-            servid: eith.service_id,
-            event_id: eitb.event_id,
-            version_number: eith.version_number,
-            section_number: eith.section_number,
-            last_section_number: eith.last_section_number,
-            segment_last_section_number: eith.segment_last_section_number,
-            running_status: eitb.running_status,
-            free_ca_mode: eitb.free_ca_mode,
-            content_type: 0,
-            content_subtype: 0,
-            genre2: 0,
-            sub_genre2: 0,
-            genre3: 0,
-            sub_genre3: 0,
-            episode_number: 0,
-            yy: eitb.yy,
-            mm: eitb.mm,
-            dd: eitb.dd,
-            hh: eitb.hh,
-            hm: eitb.hm,
-            ss: eitb.ss,
-            duration: eitb.duration,
-            start_time: dt.timestamp(),
-            title: sevtd.event_name.clone(),
-            subtitle: sevtd.text.clone(),
-            desc: String::new(),
-            desc_length: 0,
-            video_type: 0,
-            audio_type: 0,
-            multi_type: 0,
-            event_status: eitb.event_status,
-            sch_pnt: 0,
-            import_cnt: 0,
-            renew_cnt: 0,
-            tid: 0,
-            tid_status: 0,
-        });
-    }
-    // 途中に追加
-    else {
-
-        // DateTime形式の時刻情報を作成
-        let dt: DateTime<Local> = match Local.with_ymd_and_hms(
-            eitb.yy as i32 + 1900, eitb.mm as u32, eitb.dd as u32,
-            eitb.hh as u32, eitb.hm as u32, eitb.ss as u32).single() {
-            Some(date_time) => date_time,
-            None => {
-                error!("eittop_data_update 日付変換エラー(途中に追加) 
-                    yy={} , mm={} , dd={} , hh={} , hm={} , ss={}",
-                    eitb.yy + 1900, eitb.mm, eitb.dd, eitb.hh, eitb.hm, eitb.ss);
-                return
-            },
-        };
+    // 選択した言語の文字情報を設定
+    if let Some(lang_text) = eitcur.lang_text.get(pos) {
 
-        // データ更新
-        eittop.insert(push_cnt as usize, EitControl {
-            table_id: eith.table_id as i32,
-            servid: eith.service_id,
-            event_id: eitb.event_id,
-            version_number: eith.version_number,
-            section_number: eith.section_number,
-            last_section_number: eith.last_section_number,
-            segment_last_section_number: eith.segment_last_section_number,
-            running_status: eitb.running_status,
-            free_ca_mode: eitb.free_ca_mode,
-            content_type: 0,
-            content_subtype: 0,
-            genre2: 0,
-            sub_genre2: 0,
-            genre3: 0,
-            sub_genre3: 0,
-            episode_number: 0,
-            yy: eitb.yy,
-            mm: eitb.mm,
-            dd: eitb.dd,
-            hh: eitb.hh,
-            hm: eitb.hm,
-            ss: eitb.ss,
-            duration: eitb.duration,
-            start_time: dt.timestamp(),
-            title: sevtd.event_name.clone(),
-            subtitle: sevtd.text.clone(),
-            desc: String::new(),
-            desc_length: 0,
-            video_type: 0,
-            audio_type: 0,
-            multi_type: 0,
-            event_status: eitb.event_status,
-            sch_pnt: 0,
-            import_cnt: 0,
-            renew_cnt: 0,
-            tid: 0,
-            tid_status: 0,
-        });
+        eitcur.lang = lang_text.lang.clone();
+        eitcur.title = lang_text.title.clone();
+        eitcur.marker_title = lang_text.marker_title.clone();
+        eitcur.subtitle = lang_text.subtitle.clone();
+        eitcur.desc = lang_text.desc.clone();
+        eitcur.desc_items = lang_text.desc_items.clone();
+        eitcur.desc_length = eitcur.desc.len() as i32;
+
+    };
+
+}
+
+//
+// 短形式イベント記述子の文字情報更新処理
+//
+fn short_text_update(eitcur: &mut EitControl, sevtd: &SevtDesc, lang_order: &[String]) {
+
+    // 言語別文字情報の更新
+    let lang_text = lang_text_entry(eitcur, &sevtd.iso_639_language_code);
+    lang_text.title = sevtd.event_name.clone();
+    lang_text.marker_title = sevtd.marker_name.clone();
+    lang_text.subtitle = sevtd.text.clone();
+
+    // 優先言語の選択
+    lang_text_select(eitcur, lang_order);
+
+}
+
+//
+// 拡張形式イベント記述子の文字情報更新処理
+// (is_firstがtrueの場合はイベントの言語毎の最初の項目のため、受信済みの項目を破棄)
+//
+fn extended_text_update(eitcur: &mut EitControl, lang: &str, item: EitDescItem, is_first: bool, lang_order: &[String]) {
+
+    // 言語別文字情報の更新(詳細説明は「項目名<TAB>項目記述」を改行で連結)
+    let lang_text = lang_text_entry(eitcur, lang);
+    if is_first {
+
+        lang_text.desc_items.clear();
 
     };
+    lang_text.desc_items.push(item);
+    lang_text.desc = lang_text.desc_items.iter()
+        .map(|item| format!("{}\t{}", item.name, item.text))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    // 優先言語の選択
+    lang_text_select(eitcur, lang_order);
+
+}
 
+//
+// eittopへ追加処理
+//
+fn eittop_data_update(eittop: &mut EitStore,
+    eith: &EitHead, eitb: &EitBody, sevtd: &SevtDesc) {
+
+    // データ追加(開始時刻順の索引はイベントストアで管理)
+    eittop.upsert(EitControl {
+        table_id: eith.table_id as i32,
+        servid: eith.service_id,
+        event_id: eitb.event_id,
+        version_number: eith.version_number,
+        section_number: eith.section_number,
+        last_section_number: eith.last_section_number,
+        segment_last_section_number: eith.segment_last_section_number,
+        running_status: eitb.running_status,
+        free_ca_mode: eitb.free_ca_mode,
+        genres: vec![],
+        episode_number: 0,
+        yy: eitb.yy,
+        mm: eitb.mm,
+        dd: eitb.dd,
+        hh: eitb.hh,
+        hm: eitb.hm,
+        ss: eitb.ss,
+        duration: eitb.duration,
+        start_time: eitb.start_time,
+        title: sevtd.event_name.clone(),
+        marker_title: sevtd.marker_name.clone(),
+        subtitle: sevtd.text.clone(),
+        desc: String::new(),
+        desc_items: vec![],
+        desc_length: 0,
+        video_type: 0,
+        stream_content: 0,
+        audio_type: 0,
+        multi_type: 0,
+        audios: vec![],
+        series: None,
+        related_items: vec![],
+        event_status: eitb.event_status,
+        sch_pnt: 0,
+        import_cnt: 0,
+        renew_cnt: 0,
+        tid: 0,
+        tid_status: 0,
+        lang: sevtd.iso_639_language_code.clone(),
+        lang_text: vec![EitLangText {
+            lang: sevtd.iso_639_language_code.clone(),
+            title: sevtd.event_name.clone(),
+            marker_title: sevtd.marker_name.clone(),
+            subtitle: sevtd.text.clone(),
+            desc: String::new(),
+            desc_items: vec![],
+        }],
+        markers: BTreeSet::new(),
+        rating: 0,
+    });
 }
 //
 // EIT編集処理
@@ -373,7 +400,6 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
         let mut loop_len;
         let mut loop_blen;
         let mut loop_elen;
-        let mut eittop: Vec<EitControl>;
 
 
         // EITモードがtrueでEIT PFフラッグがfalseの場合はリターン
@@ -401,15 +427,29 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
 
                 svttop[cnt].svt_control_sub[0].service_id = eith.service_id;
 
-                // EIT PFフラグによりClone元を変更
+                // EIT SCHの受信セグメントを記録(イベントが無い場合はnullセグメント)
                 if eit_pf_flg != true {
 
-                    eittop = svttop[cnt].svt_control_sub[0].eitsch.clone();
+                    svttop[cnt].svt_control_sub[0].null_segment.record(EitNullSegment {
+                            table_id: eith.table_id as i32,
+                            service_id: eith.service_id,
+                            section_number: eith.section_number,
+                            version_number: eith.version_number,
+                        },
+                        eith.last_section_number, eith.last_table_id as i32,
+                        eith.section_length - (14 - 3 + 4) <= 0);
+
+                };
+
+                // EIT PFフラグにより更新するイベントストアを変更
+                let eittop: &mut EitStore = if eit_pf_flg != true {
+
+                    &mut svttop[cnt].svt_control_sub[0].sch_store
 
                 }
                 else {
 
-                    eittop = svttop[cnt].svt_control_sub[0].eit_pf.clone();
+                    &mut svttop[cnt].svt_control_sub[0].pf_store
 
                 };
 
@@ -435,6 +475,9 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
 
 
                     // 変数初期化
+                    let mut desc_reset_langs: BTreeSet<String> = BTreeSet::new();
+                    let mut audio_cnt = 0;
+                    let mut group_cnt = 0;
                     let mut save_eevtitem_item_length = 0;
                     let mut save_eevtitem_item: [u8; MAXSECLEN] = [0; MAXSECLEN];
                     let mut save_eevtitem = EevtdItem {
@@ -443,6 +486,7 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                         item_length: 0,
                         item: String::new(),
                         descriptor_number: 0,
+                        iso_639_language_code: String::new(),
                     };
 
                     // EITTボディー取り込み
@@ -466,6 +510,7 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                         eitb.event_status |= START_TIME_UNCERTAINTY;
                         eitb.yy = 138;
                         eitb.mm = eith.section_number + 1;
+                        eitb.start_time = START_TIME_UNDEFINED;
 
                     }
                     // オール0xff以外の処理
@@ -506,7 +551,7 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                         //};
 
                         // DateTime形式での日時、時刻情報の作成
-                        let dt: DateTime<Local> = match Local.with_ymd_and_hms(
+                        let dt: DateTime<FixedOffset> = match jst().with_ymd_and_hms(
                             eitb.yy as i32 + 1900, eitb.mm as u32, eitb.dd as u32,
                             eitb.hh as u32, eitb.hm as u32, eitb.ss as u32).single() {
                             Some(date_time) => date_time,
@@ -544,6 +589,14 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                     loop_blen = eitb.descriptors_loop_length;
                     loop_len -= loop_blen;
 
+                    // 格納済みイベントより古いバージョンの場合は記述子をスキップ
+                    if eittop.is_stale(eith.service_id, eitb.event_id, eith.table_id as i32, eith.version_number) {
+
+                        index += loop_blen as usize;
+                        continue;
+
+                    };
+
                     // loop_blenが0以上の処理
                     while loop_blen > 0 {
 
@@ -554,6 +607,7 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                             iso_639_language_code: String::new(),
                             event_name_length: 0,
                             event_name: String::new(),
+                            marker_name: String::new(),
                             text_length: 0,
                             text: String::new(),
                         };
@@ -581,6 +635,10 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                                 (sevtd.event_name_length, sevtd.event_name) = 
                                     arib_to_string(&buf[index + 6..index + 6 + work_event_name_length as usize], work_event_name_length);
 
+                                // 記号判定用のイベント名の文字コード変換
+                                sevtd.marker_name =
+                                    marker_title(&buf[index + 6..index + 6 + work_event_name_length as usize], work_event_name_length);
+
                             };
 
                             if work_text_length > 0 {
@@ -613,62 +671,44 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                         // lenが0より大きい（文字情報あり）の処理
                         if len > 0 {
 
-                            if eitb.event_status != EVENT_UNCERTAINTY {
-
-                                let mut seach_flg = false;
-
-                                // eittopがある分だけループ
-                                for cnt2 in 0..eittop.len() {
-
-                                    if eittop[cnt2].event_id == eitb.event_id && eittop[cnt2].servid == eith.service_id {
+                            // イベントストアに同じイベントがある場合の処理
+                            if let Some(mut eitcur) = eittop.remove(eith.service_id, eitb.event_id) {
+
+                                // 構造体の情報更新
+                                eitcur.table_id = eith.table_id as i32;
+                                eitcur.version_number = eith.version_number;
+                                eitcur.section_number = eith.section_number;
+                                eitcur.last_section_number = eith.last_section_number;
+                                eitcur.segment_last_section_number = eith.segment_last_section_number;
+                                eitcur.running_status = eitb.running_status;
+                                eitcur.yy = eitb.yy;
+                                eitcur.mm = eitb.mm;
+                                eitcur.dd = eitb.dd;
+                                eitcur.hh = eitb.hh;
+                                eitcur.hm = eitb.hm;
+                                eitcur.ss = eitb.ss;
+                                eitcur.duration = eitb.duration;
+                                eitcur.start_time = eitb.start_time;
+                                eitcur.event_status = eitb.event_status;
+
+                                // 言語別文字情報の更新
+                                short_text_update(&mut eitcur, &sevtd, &cmd_opt.lang_order);
+
+                                // EIT PFフラグがfalseの場合にimport_cntのカウントアップ
+                                if eit_pf_flg != true {
+                                    eitcur.import_cnt += 1;
+                                    eitcur.renew_cnt += 1;
+                                };
 
-                                        // DateTime形式の時刻情報を作成
-                                        let dt: DateTime<Local> = match Local.with_ymd_and_hms(
-                                            eitb.yy as i32 + 1900, eitb.mm as u32, eitb.dd as u32,
-                                            eitb.hh as u32, eitb.hm as u32, eitb.ss as u32).single() {
-                                            Some(date_time) => date_time,
-                                            None => {
-                                                error!("dump_eit 日付変換エラー(= event_id & service_id) 
-                                                       yy={} , mm={} , dd={} , hh={} , hm={} , ss={}",
-                                                    eitb.yy + 1900, eitb.mm, eitb.dd, eitb.hh, eitb.hm, eitb.ss);
-                                                return
-                                            },
-                                        };
+                                // イベントストアへ再格納(開始時刻の索引を更新)
+                                eittop.upsert(eitcur);
 
-                                        // 構造体の情報更新
-                                        eittop[cnt2].version_number = eith.version_number;
-                                        eittop[cnt2].section_number = eith.section_number;
-                                        eittop[cnt2].last_section_number = eith.last_section_number;
-                                        eittop[cnt2].segment_last_section_number = eith.segment_last_section_number;
-                                        eittop[cnt2].running_status = eitb.running_status;
-                                        eittop[cnt2].yy = eitb.yy;
-                                        eittop[cnt2].mm = eitb.mm;
-                                        eittop[cnt2].dd = eitb.dd;
-                                        eittop[cnt2].hh = eitb.hh;
-                                        eittop[cnt2].hm = eitb.hm;
-                                        eittop[cnt2].ss = eitb.ss;
-                                        eittop[cnt2].duration = eitb.duration;
-                                        eittop[cnt2].start_time = dt.timestamp();
-                                        eittop[cnt2].event_status = eitb.event_status;
-
-                                        // サーチフラグ on
-                                        seach_flg = true;
-
-                                        // EIT PFフラグがfalseの場合にimport_cntのカウントアップ
-                                        if eit_pf_flg != true {
-                                            eittop[cnt2].import_cnt += 1;
-                                            //eittop[cnt2].import_cnt = eittop[cnt2].import_cnt + 1;
-                                            eittop[cnt2].renew_cnt += 1;
-                                        };
-                                    };
-                                };
+                            }
+                            else {
 
-                                // 
-                                if seach_flg == false {
+                                // イベントストアへの追加処理呼出し
+                                eittop_data_update(eittop, &eith, &eitb, &sevtd);
 
-                                    // eittop配列への追加、挿入処理呼出し
-                                    eittop_data_update(&mut eittop, &eith, &eitb, &sevtd);
-                                };
                             };
                         }
                         else {
@@ -729,6 +769,7 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                                         item_length: 0,
                                         item: String::new(),
                                         descriptor_number: 0,
+                                        iso_639_language_code: eevthead.iso_639_language_code.clone(),
                                     };
 
                                     // レングス設定
@@ -790,22 +831,18 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                                             (save_eevtitem.item_length, save_eevtitem.item) =
                                                 arib_to_string(&save_eevtitem_item, save_eevtitem_item_length);
                                         
-                                            // eittop配列数分処理
-                                            for apent_cnt in 0..eittop.len() {
-
-                                                // イベントＩＤとサービスＩＤが同じ場合の処理（既情報のアップデート）
-                                                if eittop[apent_cnt].event_id == eitb.event_id && eittop[apent_cnt].servid == eith.service_id {
-
-                                                    // 格納エリアを初期化
-                                                    eittop[apent_cnt].desc = String::new();
+                                            // イベントＩＤとサービスＩＤが同じ場合の処理（既情報のアップデート）
+                                            if let Some(eitcur) = eittop.get_mut(eith.service_id, eitb.event_id) {
+
+                                                // 文字情報を格納
+                                                extended_text_update(eitcur, &save_eevtitem.iso_639_language_code,
+                                                    EitDescItem {
+                                                        name: save_eevtitem.item_description.clone(),
+                                                        text: save_eevtitem.item.clone(),
+                                                    },
+                                                    desc_reset_langs.insert(save_eevtitem.iso_639_language_code.clone()),
+                                                    &cmd_opt.lang_order);
 
-                                                    // 文字情報を格納
-                                                    eittop[apent_cnt].desc.push_str(&save_eevtitem.item_description);
-                                                    eittop[apent_cnt].desc.push_str("\t");
-                                                    eittop[apent_cnt].desc.push_str(&save_eevtitem.item);
-                                                    eittop[apent_cnt].desc_length = eittop[apent_cnt].desc.len() as i32;
-    
-                                                };
                                             };
 
                                             // 退避エリア変数の作成
@@ -849,383 +886,415 @@ pub fn dump_eit(cmd_opt: &CommanLineOpt, buf: &[u8], mut svttop: &mut Vec<SvtCon
                             // lenが0以下（文字情報なし）の処理
                             else {
 
-                                // eittopが未設定の処理
-                                if eittop.len() == 0 {
-
-                                    let _descriptor_tag = buf[index] as i32;
-                                    let descriptor_length = buf[index + 1] as i32;
-
-                                    // カウンター更新
-                                    len = descriptor_length as usize + 2;
-
-                                };
+                                // イベントストアに同じイベントがある場合の処理
+                                if let Some(eitcur) = eittop.get_mut(eith.service_id, eitb.event_id) {
 
-                                // eittop配列数分ループ
-                                for cnt2 in 0..eittop.len() {
+                                    // ディスクリプションタブ毎の処理
+                                    match buf[index] {
+                                        0x54 => {  // コンテンツ記述子
 
-                                    if eittop[cnt2].event_id == eitb.event_id && eittop[cnt2].servid == eith.service_id {
-
-                                        // ディスクリプションタブ毎の処理
-                                        match buf[index] {
-                                            0x54 => {  // コンテンツ記述子
+                                            // コンテンツ詳細の取得
+                                            let mut content_desc = ContentDesc {
+                                                descriptor_tag: buf[index] as i32,
+                                                descriptor_length: buf[index + 1] as i32,
+                                                content: [0; MAXSECLEN],
+                                            };
+                                            content_desc.content[..content_desc.descriptor_length as usize]
+                                                .copy_from_slice(&buf[index + 2..index + 2 + content_desc.descriptor_length as usize]);
+                                            len = content_desc.descriptor_length as usize + 2;
+
+                                            // コンテンツ詳細の全ジャンルを取得
+                                            eitcur.genres =
+                                                genre_parse(&content_desc.content[..content_desc.descriptor_length as usize]);
+                                        },
+                                        0xd5 => {  // シリーズ記述子
+                                        
+                                            // シリーズ詳細の取得
+                                            let mut series_desc = SeriesDesc {
+                                                descriptor_tag: buf[index] as i32,
+                                                descriptor_length: buf[index + 1] as i32,
+                                                series_id: ((buf[index + 2] as i32) << 8) + buf[index + 3] as i32,
+                                                repeat_label: (buf[index + 4] as i32 & 0xf0) >> 4,
+                                                program_pattern: (buf[index + 4] as i32 & 0x0e) >> 1,
+                                                expire_date_valid_flag: buf[index + 4] as i32 & 0x01,
+                                                expire_date: ((buf[index + 5] as i32) << 8) + buf[index + 6] as i32,
+                                                episode_number: ((buf[index + 7] as i32) << 4) +
+                                                    ((buf[index + 8] as i32 & 0xf0) >> 4),
+                                                last_episode_number: ((buf[index + 8] as i32 & 0x0f) << 8) + 
+                                                    buf[index + 9] as i32,
+                                                series_name_char: String::new(),
+                                            };
+                                            len = series_desc.descriptor_length as usize + 2;
 
-                                                // コンテンツ詳細の取得
-                                                let mut content_desc = ContentDesc {
-                                                    descriptor_tag: buf[index] as i32,
-                                                    descriptor_length: buf[index + 1] as i32,
-                                                    content: [0; MAXSECLEN],
-                                                };
-                                                content_desc.content[..content_desc.descriptor_length as usize]
-                                                    .copy_from_slice(&buf[index + 2..index + 2 + content_desc.descriptor_length as usize]);
-                                                len = content_desc.descriptor_length as usize + 2;
+                                            // シリーズ詳細長が9以上の処理
+                                            if series_desc.descriptor_length > 8 {
 
-                                                // コンテンツ詳細長が1以上の処理
-                                                if len > 0 {
+                                                (_, series_desc.series_name_char) = 
+                                                    arib_to_string(&buf[index + 10..index + 2 + series_desc.descriptor_length as usize],
+                                                        series_desc.descriptor_length - 8);
 
-                                                    // コンテンツタイプの取得
-                                                    eittop[cnt2].content_type = content_desc.content[0] as i32 >> 4;
-    
-                                                    // コンテンツタイプが14以外の処理
-                                                    if eittop[cnt2].content_type != 14 {
+                                            };
 
-                                                        eittop[cnt2].content_subtype = content_desc.content[0] as i32 & 0x0f;
+                                            // lenが1以上の処理
+                                            if len > 0 {
 
+                                                eitcur.episode_number = series_desc.episode_number;
+                                                eitcur.series = Some(EitSeries {
+                                                    series_id: series_desc.series_id,
+                                                    repeat_label: series_desc.repeat_label,
+                                                    program_pattern: series_desc.program_pattern,
+                                                    expire_date: if series_desc.expire_date_valid_flag == 1 {
+                                                        Some(mjd_to_timestamp(series_desc.expire_date))
                                                     }
-                                                    // コンテンツタイプが14の場合の処理
                                                     else {
+                                                        None
+                                                    },
+                                                    episode_number: series_desc.episode_number,
+                                                    last_episode_number: series_desc.last_episode_number,
+                                                    name: series_desc.series_name_char.clone(),
+                                                });
 
-                                                        // コンテンツサブタイプの取得(コンテンツ詳細によって変化)
-                                                        eittop[cnt2].content_subtype =
-                                                            if (content_desc.content[0] as u8 & 0x0f) == 0x01 {
-                                                                content_desc.content[1] as i32 + 0x40
-                                                            }
-                                                            else {
+                                            };
+                                        },
+                                        0x50 => {  // コンポーネント記述子
+
+                                            // コンポーネント情報の取得
+                                            let mut component_desc = ComponentDesc {
+                                                descriptor_tag: buf[index] as i32,
+                                                descriptor_length: buf[index + 1] as i32,
+                                                reserved_future_use: (buf[index + 2] as i32 & 0xf0) >> 4,
+                                                stream_content: buf[index + 2] as i32 & 0x0f,
+                                                component_type: buf[index + 3] as i32,
+                                                component_tag: buf[index + 4] as i32,
+                                                iso_639_language_code: match std::str::from_utf8(&buf[index + 5..index + 5 + 3]) {
+                                                    Ok(lang_code) => String::from(lang_code),
+                                                    Err(_) => String::from("jpn"),
+                                                },
+                                                text_char: String::new(),
+                                            };
+                                            len = component_desc.descriptor_length as usize + 2;
     
-                                                                content_desc.content[1] as i32
-
-                                                            };
-                                                    };
+                                            // コンポーネント情報が7以上の処理
+                                            if component_desc.descriptor_length > 6 {
 
-                                                    // コンテンツ詳細のディスクリプター長が4以上の処理
-                                                    if content_desc.descriptor_length >= 4 {
+                                                // 文字コード変換
+                                                (_, component_desc.text_char) =
+                                                    arib_to_string(&buf[index + 8..index + 8 + component_desc.descriptor_length as usize - 1],
+                                                        component_desc.descriptor_length - 6);
 
-                                                        // コンテンツgenre2の取得
-                                                        eittop[cnt2].genre2 = content_desc.content[2] as i32 >> 4;
+                                            }
 
-                                                        // コンテンツgenre2が14以外の処理
-                                                        if eittop[cnt2].genre2 != 14 {
+                                            // lenが1以上の処理
+                                            if len > 0 {
 
-                                                            // コンテンツサブgenre2の取得
-                                                            eittop[cnt2].sub_genre2 = content_desc.content[2] as i32 & 0x0f;
+                                                eitcur.video_type = component_desc.component_type;
+                                                eitcur.stream_content = component_desc.stream_content;
 
-                                                        }
-                                                        else {
-
-                                                            // コンテンツサブgenre2の取得(コンテンツ詳細によって変化)
-                                                            eittop[cnt2].sub_genre2 =
-                                                                if (content_desc.content[2] as u8 & 0x0f) == 0x01 {
-
-                                                                    content_desc.content[3] as i32 + 0x40
+                                            };
+                                        },
+                                        0xc4 => {  // オーディオコンポーネント記述子
+
+                                            // オーディオコンポーネント情報の取得
+                                            let mut audio_component_desc = AudioComponentDesc {
+                                                descriptor_tag: buf[index] as i32,
+                                                descriptor_length: buf[index + 1] as i32,
+                                                reserved_future_use_1: (buf[index + 2] as i32 & 0xf0) >> 4,
+                                                stream_content: buf[index + 2] as i32 & 0x0f,
+                                                component_type: buf[index + 3] as i32,
+                                                component_tag: buf[index + 4] as i32,
+                                                stream_type: buf[index + 5] as i32,
+                                                simulcast_group_tag: buf[index + 6] as i32,
+                                                es_multi_lingual_flag: (buf[index + 7] as i32 & 0x80) >> 7,
+                                                main_component_flag: (buf[index + 7] as i32 & 0x40) >> 6,
+                                                quality_indicator: (buf[index + 7] as i32 & 0x30) >> 4,
+                                                sampling_rate: (buf[index + 7] as i32 & 0x0e) >> 1,
+                                                reserved_future_use_2: buf[index + 7] as i32 & 0x01,
+                                                iso_639_language_code_1: match std::str::from_utf8(&buf[index + 8..index + 8 + 3]) {
+                                                    Ok(lang_code) => String::from(lang_code),
+                                                    Err(_) => String::from("jpn"),
+                                                },
+                                                iso_639_language_code_2: String::new(),
+                                                text_char: String::new(),
+                                            };
+                                            len = audio_component_desc.descriptor_length as usize + 2;
 
-                                                                }
-                                                                else {
+                                            // オーディオコンポーネントマルチランゲージフラグが1の処理
+                                            if audio_component_desc.es_multi_lingual_flag == 1 {
 
-                                                                    content_desc.content[3] as i32
+                                                // ランゲージコードの取得
+                                                audio_component_desc.iso_639_language_code_2 = match
+                                                    std::str::from_utf8(&buf[index + 11..index + 11 + 3]) {
+                                                        Ok(lang_code) => String::from(lang_code),
+                                                        Err(_) => String::from("jpn"),
+                                                };
 
-                                                                };
-                                                        };
+                                                // オーディオコンポーネント長が15以上の処理
+                                                if audio_component_desc.descriptor_length > 14 {
 
-                                                        // コンテンツ詳細のディスクリプター長が6以上の処理
-                                                        if content_desc.descriptor_length >= 6 {
+                                                    // 文字コード変換
+                                                    (_, audio_component_desc.text_char) =
+                                                        arib_to_string(&buf[index + 14..
+                                                            index + 14 + audio_component_desc.descriptor_length as usize - 1],
+                                                            audio_component_desc.descriptor_length - 12);
+    
+                                                };
+                                            }
+                                            // オーディオコンポーネントマルチランゲージフラグが1以外の処理
+                                            else {
 
-                                                            // コンテンツgenre3の取得
-                                                            eittop[cnt2].genre3 = content_desc.content[4] as i32 >> 4;
+                                                // オーディオコンポーネント長が12以上の処理
+                                                if audio_component_desc.descriptor_length > 11 {
 
-                                                            // コンテンツgenre3が14以外の処理
-                                                            if eittop[cnt2].genre3 != 14 {
+                                                    // 文字コード変換
+                                                    (audio_component_desc.descriptor_length, audio_component_desc.text_char) =
+                                                        arib_to_string(&buf[index + 11..
+                                                            index + 11 + audio_component_desc.descriptor_length as usize - 1],
+                                                            audio_component_desc.descriptor_length - 9);
 
-                                                                // コンテンツサブgenre3の取得
-                                                                eittop[cnt2].sub_genre3 = content_desc.content[4] as i32 & 0x0f;
+                                                    };
+                                            };
 
-                                                            }
-                                                            else {
+                                            // lenが1以上の処理
+                                            if len > 0 {
 
-                                                                // コンテンツサブgenre3の取得(コンテンツ詳細によって変化)
-                                                                eittop[cnt2].sub_genre3 =
-                                                                    if (content_desc.content[4] as u8 & 0x0f) == 0x01 {
+                                                eitcur.audio_type = audio_component_desc.component_type;
+                                                eitcur.multi_type = audio_component_desc.es_multi_lingual_flag;
 
-                                                                        content_desc.content[5] as i32 & 0x40
+                                                // イベントの最初の音声の場合は前のバージョンの音声を削除
+                                                if audio_cnt == 0 {
+                                                    eitcur.audios.clear();
+                                                };
+                                                audio_cnt += 1;
 
-                                                                    }
-                                                                    else {
+                                                let mut langs = vec![audio_component_desc.iso_639_language_code_1.clone()];
+                                                if audio_component_desc.es_multi_lingual_flag == 1 {
+                                                    langs.push(audio_component_desc.iso_639_language_code_2.clone());
+                                                };
+                                                eitcur.audios.push(EitAudio {
+                                                    component_type: audio_component_desc.component_type,
+                                                    component_tag: audio_component_desc.component_tag,
+                                                    is_main: audio_component_desc.main_component_flag == 1,
+                                                    sampling_rate: audio_component_desc.sampling_rate,
+                                                    langs,
+                                                });
     
-                                                                        content_desc.content[5] as i32
-
-                                                                    };
-                                                            };
-                                                        }
-                                                        else {
+                                            };
+                                        },
+                                        0xd6 => {  // イベントグループ記述子
+
+                                            // イベントグループ詳細の取得
+                                            let descriptor_length = buf[index + 1] as usize;
+                                            let group_type = (buf[index + 2] as i32 & 0xf0) >> 4;
+                                            let event_count = buf[index + 2] as usize & 0x0f;
+                                            len = descriptor_length + 2;
+
+                                            // イベントの最初のイベントグループの場合は前のバージョンの関連イベントを削除
+                                            if group_cnt == 0 {
+                                                eitcur.related_items.clear();
+                                            };
+                                            group_cnt += 1;
 
-                                                            // コンテンツgenre3、コンテンツサブgenre3の設定
-                                                            eittop[cnt2].genre3 = 16;
-                                                            eittop[cnt2].sub_genre3 = 16;
+                                            // 同一ネットワークのイベント(サービスID、イベントID)
+                                            let mut pos = index + 3;
+                                            for _ in 0..event_count {
 
-                                                        };
+                                                if pos + 4 > index + len {
+                                                    break;
+                                                };
+                                                eitcur.related_items.push(EitRelatedItem {
+                                                    group_type,
+                                                    network_id: None,
+                                                    service_id: ((buf[pos] as i32) << 8) + buf[pos + 1] as i32,
+                                                    event_id: ((buf[pos + 2] as i32) << 8) + buf[pos + 3] as i32,
+                                                });
+                                                pos += 4;
 
-                                                        // コンテンツタイプが14の場合の処理
-                                                        if eittop[cnt2].content_type == 14 {
+                                            };
 
-                                                            // コンテンツサブタイプの退避
-                                                            let sub_stock = eittop[cnt2].content_subtype;
+                                            // 他ネットワークへのリレー、移動の場合のイベント
+                                            // (オリジナルネットワークID、トランスポートストリームID、サービスID、イベントID)
+                                            if group_type == 4 || group_type == 5 {
 
-                                                            // コンテンツgenre2が14以外の処理
-                                                            if eittop[cnt2].genre2 != 14 {
+                                                while pos + 8 <= index + len {
 
-                                                                eittop[cnt2].content_type = eittop[cnt2].genre2;
-                                                                eittop[cnt2].content_subtype = eittop[cnt2].sub_genre2;
-                                                                eittop[cnt2].genre2 = 14;
-                                                                eittop[cnt2].sub_genre2 = sub_stock;
+                                                    eitcur.related_items.push(EitRelatedItem {
+                                                        group_type,
+                                                        network_id: Some(((buf[pos] as i32) << 8) + buf[pos + 1] as i32),
+                                                        service_id: ((buf[pos + 4] as i32) << 8) + buf[pos + 5] as i32,
+                                                        event_id: ((buf[pos + 6] as i32) << 8) + buf[pos + 7] as i32,
+                                                    });
+                                                    pos += 8;
 
-                                                            }
-                                                            // コンテンツgenre2が14、16以外の処理
-                                                            else if eittop[cnt2].genre3 != 14 && eittop[cnt2].genre3 != 16 {
+                                                };
+                                            };
+                                        },
+                                        0x55 => {  // パレンタルレート記述子
 
-                                                                eittop[cnt2].content_type = eittop[cnt2].genre3;
-                                                                eittop[cnt2].content_subtype = eittop[cnt2].sub_genre3;
-                                                                eittop[cnt2].genre3 = 14;
-                                                                eittop[cnt2].sub_genre3 = sub_stock;
+                                            // パレンタルレート詳細の取得(最初の国の視聴年齢)
+                                            let descriptor_length = buf[index + 1] as usize;
+                                            len = descriptor_length + 2;
 
-                                                            };
-                                                        };
-                                                    }
-                                                    else {
+                                            // パレンタルレート詳細長が4以上の処理
+                                            if descriptor_length >= 4 {
 
-                                                        eittop[cnt2].genre2     = 16;
-                                                        eittop[cnt2].sub_genre2 = 16;
-                                                        eittop[cnt2].genre3     = 16;
-                                                        eittop[cnt2].sub_genre3 = 16;
+                                                eitcur.rating = buf[index + 5] as i32;
 
-                                                    };
-                                                }
-                                            },
-                                            0xd5 => {  // シリーズ記述子
+                                            };
+                                        },
+                                        _ => {  // 上記以外
                                             
-                                                // シリーズ詳細の取得
-                                                let mut series_desc = SeriesDesc {
-                                                    descriptor_tag: buf[index] as i32,
-                                                    descriptor_length: buf[index + 1] as i32,
-                                                    series_id: ((buf[index + 2] as i32) << 8) + buf[index + 3] as i32,
-                                                    repeat_label: (buf[index + 4] as i32 & 0xf0) >> 4,
-                                                    program_pattern: (buf[index + 4] as i32 & 0x0e) >> 1,
-                                                    expire_date_valid_flag: buf[index + 4] as i32 & 0x01,
-                                                    expire_date: ((buf[index + 5] as i32) << 8) + buf[index + 6] as i32,
-                                                    episode_number: ((buf[index + 7] as i32) << 8) +
-                                                        ((buf[index + 8] as i32 & 0xf0) >> 4),
-                                                    last_episode_number: ((buf[index + 8] as i32 & 0x0f) << 8) + 
-                                                        buf[index + 9] as i32,
-                                                    series_name_char: String::new(),
-                                                };
-                                                len = series_desc.descriptor_length as usize + 2;
+                                            // データポインター移動
+                                            let _descriptor_tag = buf[index] as i32;
+                                            let descriptor_length = buf[index + 1] as i32;
 
-                                                // シリーズ詳細長が9以上の処理
-                                                if series_desc.descriptor_length > 8 {
+                                            // カウンター更新
+                                            len = descriptor_length as usize + 2;
 
-                                                    (_, series_desc.series_name_char) = 
-                                                        arib_to_string(&buf[index + 10..series_desc.descriptor_length as usize - 1],
-                                                            series_desc.descriptor_length - 10);
+                                        },
+                                    };
 
-                                                };
+                                }
+                                // イベントストアにイベントがない場合の処理
+                                else {
 
-                                                // lenが1以上の処理
-                                                if len > 0 {
+                                    // データポインター移動
+                                    let _descriptor_tag = buf[index] as i32;
+                                    let descriptor_length = buf[index + 1] as i32;
 
-                                                    eittop[cnt2].episode_number = series_desc.episode_number;
+                                    // カウンター更新
+                                    len = descriptor_length as usize + 2;
 
-                                                };
-                                            },
-                                            0x50 => {  // コンポーネント記述子
-
-                                                // コンポーネント情報の取得
-                                                let mut component_desc = ComponentDesc {
-                                                    descriptor_tag: buf[index] as i32,
-                                                    descriptor_length: buf[index + 1] as i32,
-                                                    reserved_future_use: (buf[index + 2] as i32 & 0xf0) >> 4,
-                                                    stream_content: buf[index + 2] as i32 & 0x0f,
-                                                    component_type: buf[index + 3] as i32,
-                                                    component_tag: buf[index + 4] as i32,
-                                                    iso_639_language_code: match std::str::from_utf8(&buf[index + 5..index + 5 + 3]) {
-                                                        Ok(lang_code) => String::from(lang_code),
-                                                        Err(_) => String::from("jpn"),
-                                                    },
-                                                    text_char: String::new(),
-                                                };
-                                                len = component_desc.descriptor_length as usize + 2;
-    
-                                                // コンポーネント情報が7以上の処理
-                                                if component_desc.descriptor_length > 6 {
+                                };
+                            };
+                        };
 
-                                                    // 文字コード変換
-                                                    (_, component_desc.text_char) =
-                                                        arib_to_string(&buf[index + 8..index + 8 + component_desc.descriptor_length as usize - 1],
-                                                            component_desc.descriptor_length - 6);
+                        // カウンター更新
+                        index += len;
+                        loop_blen -= len as i32;
 
-                                                }
+                    };
 
-                                                // lenが1以上の処理
-                                                if len > 0 {
+                    // セーブ情報の最終処理
+                    if save_eevtitem_item_length > 0 {
 
-                                                    eittop[cnt2].video_type = component_desc.component_type;
+                        // 文字コード変換
+                        (save_eevtitem.item_length, save_eevtitem.item) =
+                            arib_to_string(&save_eevtitem_item, save_eevtitem_item_length);
 
-                                                };
-                                            },
-                                            0xc4 => {  // オーディオコンポーネント記述子
-
-                                                // オーディオコンポーネント情報の取得
-                                                let mut audio_component_desc = AudioComponentDesc {
-                                                    descriptor_tag: buf[index] as i32,
-                                                    descriptor_length: buf[index + 1] as i32,
-                                                    reserved_future_use_1: (buf[index + 2] as i32 & 0xf0) >> 4,
-                                                    stream_content: buf[index + 2] as i32 & 0x0f,
-                                                    component_type: buf[index + 3] as i32,
-                                                    component_tag: buf[index + 4] as i32,
-                                                    stream_type: buf[index + 5] as i32,
-                                                    simulcast_group_tag: buf[index + 6] as i32,
-                                                    es_multi_lingual_flag: (buf[index + 7] as i32 & 0x80) >> 7,
-                                                    main_component_flag: (buf[index + 7] as i32 & 0x40) >> 6,
-                                                    quality_indicator: (buf[index + 7] as i32 & 0x30) >> 4,
-                                                    sampling_rate: (buf[index + 7] as i32 & 0x0e) >> 1,
-                                                    reserved_future_use_2: buf[index + 7] as i32 & 0x01,
-                                                    iso_639_language_code_1: match std::str::from_utf8(&buf[index + 8..index + 8 + 3]) {
-                                                        Ok(lang_code) => String::from(lang_code),
-                                                        Err(_) => String::from("jpn"),
-                                                    },
-                                                    iso_639_language_code_2: String::new(),
-                                                    text_char: String::new(),
-                                                };
-                                                len = audio_component_desc.descriptor_length as usize + 2;
+                        // イベントＩＤとサービスＩＤが同じ場合の処理（既情報のアップデート）
+                        if let Some(eitcur) = eittop.get_mut(eith.service_id, eitb.event_id) {
 
-                                                // オーディオコンポーネントマルチランゲージフラグが1の処理
-                                                if audio_component_desc.es_multi_lingual_flag == 1 {
+                            // データ更新
+                            extended_text_update(eitcur, &save_eevtitem.iso_639_language_code,
+                                EitDescItem {
+                                    name: save_eevtitem.item_description.clone(),
+                                    text: save_eevtitem.item.clone(),
+                                },
+                                desc_reset_langs.insert(save_eevtitem.iso_639_language_code.clone()),
+                                &cmd_opt.lang_order);
 
-                                                    // ランゲージコードの取得
-                                                    audio_component_desc.iso_639_language_code_2 = match
-                                                        std::str::from_utf8(&buf[index + 11..index + 11 + 3]) {
-                                                            Ok(lang_code) => String::from(lang_code),
-                                                            Err(_) => String::from("jpn"),
-                                                    };
-
-                                                    // オーディオコンポーネント長が15以上の処理
-                                                    if audio_component_desc.descriptor_length > 14 {
+                        };
+                    };
+                };
+            };
+        };
 
-                                                        // 文字コード変換
-                                                        (_, audio_component_desc.text_char) =
-                                                            arib_to_string(&buf[index + 14..
-                                                                index + 14 + audio_component_desc.descriptor_length as usize - 1],
-                                                                audio_component_desc.descriptor_length - 12);
-    
-                                                    };
-                                                }
-                                                // オーディオコンポーネントマルチランゲージフラグが1以外の処理
-                                                else {
+        table_id_index += eith.section_length as usize + 3;
 
-                                                    // オーディオコンポーネント長が12以上の処理
-                                                    if audio_component_desc.descriptor_length > 11 {
+    };
+}
 
-                                                        // 文字コード変換
-                                                        (audio_component_desc.descriptor_length, audio_component_desc.text_char) =
-                                                            arib_to_string(&buf[index + 11..
-                                                                index + 11 + audio_component_desc.descriptor_length as usize - 1],
-                                                                audio_component_desc.descriptor_length - 9);
+#[cfg(test)]
+mod tests {
 
-                                                        };
-                                                };
+    use super::*;
+    use crate::{command_line_parse};
+    use epgdump::encoder::{string_to_arib};
 
-                                                // lenが1以上の処理
-                                                if len > 0 {
+    // 短形式イベント記述子の作成処理
+    fn short_event(lang: &str, name: &str, text: &str) -> Vec<u8> {
 
-                                                    eittop[cnt2].audio_type = audio_component_desc.component_type;
-                                                    eittop[cnt2].multi_type = audio_component_desc.es_multi_lingual_flag;
-    
-                                                };
-                                            },
-                                            _ => {  // 上記以外
-                                                
-                                                // データポインター移動
-                                                let _descriptor_tag = buf[index] as i32;
-                                                let descriptor_length = buf[index + 1] as i32;
+        let name = string_to_arib(name);
+        let text = string_to_arib(text);
 
-                                                // カウンター更新
-                                                len = descriptor_length as usize + 2;
+        let mut body = lang.as_bytes().to_vec();
+        body.push(name.len() as u8);
+        body.extend(&name);
+        body.push(text.len() as u8);
+        body.extend(&text);
 
-                                            },
-                                        }; 
+        [vec![0x4d, body.len() as u8], body].concat()
 
-                                        break;
+    }
 
-                                    }
-                                    else if cnt2 == eittop.len() - 1 {
+    // 拡張形式イベント記述子の作成処理
+    fn extended_event(lang: &str, items: &[(&str, &str)]) -> Vec<u8> {
 
-                                        // データポインター移動
-                                        let _descriptor_tag = buf[index] as i32;
-                                        let descriptor_length = buf[index + 1] as i32;
-                                    
-                                        // カウンター更新
-                                        len = descriptor_length as usize + 2;
-                                        //loop_blen -= len as i32;
+        let mut item_bytes = vec![];
+        for (name, text) in items.iter() {
 
-                                    };
-                                };
-                            };
-                        };
+            let name = string_to_arib(name);
+            let text = string_to_arib(text);
+            item_bytes.push(name.len() as u8);
+            item_bytes.extend(&name);
+            item_bytes.push(text.len() as u8);
+            item_bytes.extend(&text);
 
-                        // カウンター更新
-                        index += len;
-                        loop_blen -= len as i32;
+        };
 
-                    };
+        let mut body = vec![0x00];
+        body.extend(lang.as_bytes());
+        body.push(item_bytes.len() as u8);
+        body.extend(&item_bytes);
+        body.push(0x00);
 
-                    // セーブ情報の最終処理
-                    if save_eevtitem_item_length > 0 {
+        [vec![0x4e, body.len() as u8], body].concat()
 
-                        // 文字コード変換
-                        (save_eevtitem.item_length, save_eevtitem.item) =
-                            arib_to_string(&save_eevtitem_item, save_eevtitem_item_length);
+    }
 
-                        // eittop配列数分処理
-                        for apent_cnt in 0..eittop.len() {
+    // EIT PF(自ストリーム)のセクション作成処理(2024-01-01 12:00から30分、CRCは未計算)
+    fn eit_section(descriptors: &[u8]) -> Vec<u8> {
 
-                            // イベントＩＤとサービスＩＤが同じ場合の処理（既情報のアップデート）
-                            if eittop[apent_cnt].event_id == eitb.event_id && eittop[apent_cnt].servid == eith.service_id {
+        let mut event = vec![0x00, 0x01, 0xeb, 0x96, 0x12, 0x00, 0x00, 0x00, 0x30, 0x00,
+            0x80 | (descriptors.len() >> 8) as u8, descriptors.len() as u8];
+        event.extend(descriptors);
 
-                                // 格納エリアの初期化
-                                eittop[apent_cnt].desc = String::new();
+        let section_length = 11 + event.len() + 4;
+        let mut section = vec![0x4e, 0xf0 | (section_length >> 8) as u8, section_length as u8,
+            0x00, 0x65, 0xc1, 0x00, 0x01, 0x40, 0x10, 0x00, 0x04, 0x01, 0x4e];
+        section.extend(&event);
+        section.extend([0x00; 4]);
+        section.extend([0xff; 16]);
 
-                                // データ更新
-                                eittop[apent_cnt].desc.push_str(&save_eevtitem.item_description);
-                                eittop[apent_cnt].desc.push_str("\t");
-                                eittop[apent_cnt].desc.push_str(&save_eevtitem.item);
-                                eittop[apent_cnt].desc_length = eittop[apent_cnt].desc.len() as i32;
+        section
 
-                            };
-                        };
-                    };
-                };
+    }
 
-                // EIT PFフラグによりClone先を変更
-                if eit_pf_flg != true {
+    #[test]
+    fn extended_text_repeated_two_languages() {
 
-                    svttop[cnt].svt_control_sub[0].eitsch = eittop.clone();
+        let cmd_opt = command_line_parse("epgdump", ["epgdump", "--BS", "-", "-"].iter().map(|arg| arg.to_string()).collect());
+        let mut svttop: Vec<SvtControlTop> = vec![];
 
-                }
-                else {
+        let descriptors = [
+            short_event("jpn", "番組", "説明"),
+            extended_event("jpn", &[("出演者", "山田"), ("内容", "本文")]),
+            short_event("eng", "Title", "Text"),
+            extended_event("eng", &[("Cast", "Smith")]),
+        ].concat();
+        let section = eit_section(&descriptors);
 
-                    svttop[cnt].svt_control_sub[0].eit_pf = eittop.clone();
+        // 同じセクションを2回受信
+        dump_eit(&cmd_opt, &section, &mut svttop);
+        dump_eit(&cmd_opt, &section, &mut svttop);
 
-                };
-            };
-        };
+        let eitcur = svttop[0].svt_control_sub[0].pf_store.get_mut(0x65, 1).unwrap();
+        let desc_names = |lang: &str| eitcur.lang_text.iter()
+            .find(|lang_text| lang_text.lang == lang).unwrap()
+            .desc_items.iter().map(|item| item.name.clone()).collect::<Vec<String>>();
 
-        table_id_index += eith.section_length as usize + 3;
+        assert_eq!(desc_names("jpn"), vec!["出演者", "内容"]);
+        assert_eq!(desc_names("eng"), vec!["Cast"]);
 
-    };
+    }
 }
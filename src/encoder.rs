@@ -0,0 +1,344 @@
+
+use encoding_rs::ISO_2022_JP;
+use log::{debug};
+use std::collections::HashMap;
+use std::sync::{OnceLock};
+
+use crate::arib::{put_symbols_char};
+use crate::symbol::{enclosed_symbol_code};
+
+// 制御コード
+const CODE_LS0: u8 = 0x0f;   // G0をGLに呼び出し
+const CODE_LS1: u8 = 0x0e;   // G1をGLに呼び出し
+const CODE_APR: u8 = 0x0d;   // 改行
+const CODE_ESC: u8 = 0x1b;   // エスケープ
+const CODE_SS3: u8 = 0x1d;   // G3を次の1文字のみGLに呼び出し
+const CODE_SP: u8 = 0x20;    // スペース
+const CODE_MSZ: u8 = 0x89;   // 中型サイズ(半角)
+const CODE_NSZ: u8 = 0x8a;   // 標準サイズ(全角)
+
+// G3へのグラフィックセット指示(2バイトGセット)
+const CODE_DESIGNATE_2BYTE_G3: [u8; 2] = [0x24, 0x2b];
+
+// グラフィックセットの終端符号
+const FINAL_ADDITIONAL_SYMBOLS: u8 = 0x3b;  // Additional symbols
+
+// 変換できない文字の代替文字(〓)
+const CODE_GETA: [u8; 2] = [0x22, 0x2e];
+
+// 追加記号の文字 -> 文字コード変換表(起動後の初回使用時に作成)
+static SYMBOL_CODE_TABLE: OnceLock<HashMap<char, u32>> = OnceLock::new();
+
+// ARIB 8単位符号の文字列エンコーダー構造体
+// (G0:漢字、G1:英数、G3:追加記号で符号化し、デコーダーの初期状態から開始)
+#[derive(Debug, Clone, Default)]
+pub struct AribEncoder {
+    locking_gl: usize,      // GLに呼び出したGセット(G0またはG1)
+    is_medium: bool,        // 中型サイズ(半角)の指定有無
+    g3_final: Option<u8>,   // G3に指示したグラフィックセットの終端符号(初期状態はカタカナ)
+}
+
+//
+// 追加記号の文字 -> 文字コード変換表の取得処理
+// (1文字の追加記号のみ、同じ文字が複数ある場合は先の文字コード)
+//
+fn symbol_code_table() -> &'static HashMap<char, u32> {
+
+    SYMBOL_CODE_TABLE.get_or_init(|| {
+
+        let mut table: HashMap<char, u32> = HashMap::new();
+
+        for c1 in 0x75..=0x7e {
+
+            for c2 in 0x21..=0x7e {
+
+                let mut chars = put_symbols_char((c1 << 8) + c2).chars();
+                if let (Some(c), None) = (chars.next(), chars.next()) {
+
+                    table.entry(c).or_insert((c1 << 8) + c2);
+
+                };
+            };
+        };
+
+        table
+
+    })
+
+}
+
+//
+// 漢字(JIS X 0208)の文字コード取得処理
+//
+fn kanji_code(c: char) -> Option<[u8; 2]> {
+
+    let mut work = [0u8; 4];
+    let (work_code, _, had_errors) = ISO_2022_JP.encode(c.encode_utf8(&mut work));
+
+    // 「ESC $ B 第1バイト 第2バイト ESC ( B」のみ対象
+    match &work_code[..] {
+        [0x1b, 0x24, 0x42, c1, c2, 0x1b, 0x28, 0x42] if !had_errors => Some([*c1, *c2]),
+        _ => None,
+    }
+
+}
+
+impl AribEncoder {
+
+    //
+    // エンコーダーの作成処理
+    //
+    pub fn new() -> Self {
+
+        AribEncoder::default()
+
+    }
+
+    //
+    // 文字サイズの指定処理
+    //
+    fn set_size(&mut self, is_medium: bool, ret_data: &mut Vec<u8>) {
+
+        if self.is_medium != is_medium {
+
+            ret_data.push(if is_medium { CODE_MSZ } else { CODE_NSZ });
+            self.is_medium = is_medium;
+
+        };
+
+    }
+
+    //
+    // GLへのGセット呼び出し処理(G0またはG1)
+    //
+    fn set_gl(&mut self, g_num: usize, ret_data: &mut Vec<u8>) {
+
+        if self.locking_gl != g_num {
+
+            ret_data.push(if g_num == 0 { CODE_LS0 } else { CODE_LS1 });
+            self.locking_gl = g_num;
+
+        };
+
+    }
+
+    //
+    // G3へのグラフィックセット指示処理(2バイトGセット)
+    //
+    fn set_g3(&mut self, final_code: u8, ret_data: &mut Vec<u8>) {
+
+        if self.g3_final != Some(final_code) {
+
+            ret_data.push(CODE_ESC);
+            ret_data.extend_from_slice(&CODE_DESIGNATE_2BYTE_G3);
+            ret_data.push(final_code);
+            self.g3_final = Some(final_code);
+
+        };
+
+    }
+
+    //
+    // 1文字の符号化処理(リターン:変換有無)
+    //
+    fn encode_char(&mut self, c: char, ret_data: &mut Vec<u8>) -> bool {
+
+        // 改行
+        if c == '\n' {
+
+            ret_data.push(CODE_APR);
+
+        }
+        // 半角スペース
+        else if c == ' ' {
+
+            self.set_size(true, ret_data);
+            ret_data.push(CODE_SP);
+
+        }
+        // 英数(半角)
+        else if c.is_ascii_graphic() {
+
+            self.set_size(true, ret_data);
+            self.set_gl(1, ret_data);
+            ret_data.push(c as u8);
+
+        }
+        // 漢字(全角)
+        else if let Some(code) = kanji_code(c) {
+
+            self.set_size(false, ret_data);
+            self.set_gl(0, ret_data);
+            ret_data.extend_from_slice(&code);
+
+        }
+        // 追加記号(Unicode 5.2の囲み文字または1文字の追加記号)
+        else if let Some(code) = enclosed_symbol_code(c.encode_utf8(&mut [0u8; 4]))
+            .or_else(|| symbol_code_table().get(&c).copied()) {
+
+            self.set_size(false, ret_data);
+            self.set_g3(FINAL_ADDITIONAL_SYMBOLS, ret_data);
+            ret_data.push(CODE_SS3);
+            ret_data.extend_from_slice(&[(code >> 8) as u8, code as u8]);
+
+        }
+        // 変換できない文字
+        else {
+
+            return false;
+
+        };
+
+        true
+
+    }
+
+    //
+    // 文字列 -> ARIB 8単位符号への変換処理(変換できない文字は「〓」)
+    //
+    pub fn encode(&mut self, text: &str) -> Vec<u8> {
+
+        let mut ret_data: Vec<u8> = vec![];
+
+        // デコーダーの初期状態から開始
+        *self = AribEncoder::default();
+
+        // 1文字づつ変換
+        for c in text.chars() {
+
+            if !self.encode_char(c, &mut ret_data) {
+
+                debug!("encode error char=U+{:04X}", c as u32);
+                self.set_size(false, &mut ret_data);
+                self.set_gl(0, &mut ret_data);
+                ret_data.extend_from_slice(&CODE_GETA);
+
+            };
+        };
+
+        ret_data
+
+    }
+}
+
+//
+// 文字列 -> ARIB 8単位符号への変換処理
+//
+pub fn string_to_arib(text: &str) -> Vec<u8> {
+
+    AribEncoder::new().encode(text)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use proptest::prelude::*;
+    use std::sync::{Arc};
+
+    use crate::arib::{AribDecoder, TextDecoder};
+    use crate::symbol::{SymbolProfile, SymbolTable};
+
+    //
+    // 符号化できる文字の一覧作成処理(英数、JIS X 0208、1文字の追加記号)
+    //
+    fn repertoire() -> Vec<char> {
+
+        let mut chars: Vec<char> = (0x20u8..=0x7e).map(char::from).collect();
+
+        for c1 in 0x21..=0x7e {
+
+            for c2 in 0x21..=0x7e {
+
+                let work_code = [0x1b, 0x24, 0x42, c1, c2, 0x1b, 0x28, 0x42];
+                let (text, had_errors) = ISO_2022_JP.decode_without_bom_handling(&work_code);
+                let mut text_chars = text.chars();
+                if let (Some(c), None, false) = (text_chars.next(), text_chars.next(), had_errors) {
+
+                    chars.push(c);
+
+                };
+            };
+        };
+
+        chars.extend(symbol_code_table().keys());
+        chars.sort();
+        chars.dedup();
+
+        chars
+
+    }
+
+    //
+    // Unicode 5.2の囲み文字の一覧作成処理
+    //
+    fn enclosed_repertoire() -> Vec<char> {
+
+        (0x2600..=0x32ff).chain(0x1f100..=0x1f2ff)
+            .filter_map(char::from_u32)
+            .filter(|c| enclosed_symbol_code(c.encode_utf8(&mut [0u8; 4])).is_some())
+            .collect()
+
+    }
+
+    fn decode(data: &[u8]) -> String {
+
+        AribDecoder::new().decode(data, data.len() as i32).1
+
+    }
+
+    #[test]
+    fn encode_alphanumeric() {
+
+        assert_eq!(string_to_arib("AB 1"), vec![CODE_MSZ, CODE_LS1, 0x41, 0x42, CODE_SP, 0x31]);
+
+    }
+
+    #[test]
+    fn encode_kanji_after_alphanumeric() {
+
+        assert_eq!(string_to_arib("A字"), vec![CODE_MSZ, CODE_LS1, 0x41, CODE_NSZ, CODE_LS0, 0x3b, 0x7a]);
+
+    }
+
+    #[test]
+    fn encode_additional_symbols() {
+
+        // G3への指示は1回のみ
+        assert_eq!(string_to_arib("🈑㐂"),
+            vec![CODE_ESC, 0x24, 0x2b, FINAL_ADDITIONAL_SYMBOLS, CODE_SS3, 0x7a, 0x56, CODE_SS3, 0x75, 0x21]);
+        assert_eq!(decode(&string_to_arib("🈑㐂")), "[字]㐂");
+
+    }
+
+    #[test]
+    fn encode_unknown_char() {
+
+        assert_eq!(decode(&string_to_arib("a\u{e000}b")), "a〓b");
+
+    }
+
+    proptest! {
+
+        #[test]
+        fn round_trip(text in prop::collection::vec(prop::sample::select(repertoire()), 0..64)
+            .prop_map(|chars| chars.into_iter().collect::<String>())) {
+
+            prop_assert_eq!(decode(&string_to_arib(&text)), text);
+
+        }
+
+        #[test]
+        fn round_trip_unicode_symbols(text in prop::collection::vec(
+            prop::sample::select([repertoire(), enclosed_repertoire()].concat()), 0..64)
+            .prop_map(|chars| chars.into_iter().collect::<String>())) {
+
+            let data = string_to_arib(&text);
+            let mut decoder = AribDecoder::new().with_symbols(Arc::new(SymbolTable::new(SymbolProfile::Unicode)));
+
+            prop_assert_eq!(decoder.decode(&data, data.len() as i32).1, text);
+
+        }
+    }
+}
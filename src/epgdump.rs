@@ -1,11 +1,10 @@
 extern crate getopts;
 
-use chrono::{DateTime, Local, TimeZone};
-use chrono::prelude::{Datelike, Timelike};
+use chrono::{Local};
 use colored::*;
 use std::io::{BufRead};
 use env_logger::{Builder, Env, Target};
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::path::Path;
 use getopts::Options;
 use log::{debug, warn};
@@ -14,16 +13,49 @@ use std::env;
 use std::io::{BufReader, Write};
 use std::process;
 
-mod arib;
+mod delimited;
 mod eit;
+mod gap;
+mod genre;
+mod ics;
+mod json;
+mod marker;
+mod mirakurun;
+mod ndjson;
+mod overlap;
 mod sdt;
+mod sqlite;
+mod segment;
+mod store;
+mod tot;
 mod ts;
+mod xmltv;
 
+// 文字列変換はライブラリ(src/lib.rs)のモジュールを使用
+use epgdump::{arib, drcs, symbol, width};
+
+use crate::arib::{newline_init};
+use crate::delimited::{Column, DEFAULT_COLUMNS, Delimiter, column_list_parse, dump_delimited};
+use crate::drcs::{drcs_table_init, drcs_table_read};
+use crate::symbol::{SymbolProfile, symbol_table_init, symbol_table_read};
+use crate::width::{TextWidth, text_width_init};
+use crate::xmltv::{DescLayout, XmlMode, dump_xmltv};
 use crate::eit::{CERTAINTY, START_TIME_UNCERTAINTY, DURATION_UNCERTAINTY};
 use crate::eit::{dump_eit};
+use crate::gap::{GapFill, gap_genre_parse};
+use crate::genre::{GenreLang, genre_compat, genre_is_rest, genre_names};
+use crate::ics::{IcsFilter, dump_ics};
+use crate::json::{dump_json};
+use crate::mirakurun::{JsonMode, dump_mirakurun};
+use crate::ndjson::{NdjsonWriter};
+use crate::marker::{TitleMarker, marker_parse};
+use crate::overlap::{OverlapPolicy, overlap_repair};
 use crate::sdt::{dump_sdt};
+use crate::sqlite::{dump_sqlite};
+use crate::store::{EitStore};
+use crate::tot::{TimeFormat, TotState, dump_tot};
 use crate::ts::{MAXSECBUF, read_ts, SecCache, SvtControl, SvtControlTop,
-                EitControl, TsPacket, TSPAYLOADMAX};
+                EitControl, EitGenre, EitNullSegmentTop, TsPacket, TSPAYLOADMAX};
 
 // 定数設定
 pub const PROGRAM:  &str = env!("CARGO_PKG_NAME");   // パッケージ名
@@ -48,6 +80,18 @@ struct CommanLineOpt {
     sdt_mode: bool,
     eit_mode: bool,
     is_xml: bool,
+    xml_mode: XmlMode,
+    desc_layout: DescLayout,
+    is_json: bool,
+    json_mode: JsonMode,
+    is_ndjson: bool,
+    is_sqlite: bool,
+    delimiter: Option<Delimiter>,
+    columns: Vec<Column>,
+    is_bom: bool,
+    is_ics: bool,
+    ics_filter: IcsFilter,
+    is_genre_name: bool,
     is_sid: bool,
     select_sid: i32,
     _is_cut: bool,
@@ -55,6 +99,19 @@ struct CommanLineOpt {
     is_bs: bool,
     is_cs: bool,
     //is_time: bool,
+    lang_order: Vec<String>,
+    time_format: TimeFormat,
+    overlap_policy: OverlapPolicy,
+    gap_fill: GapFill,
+    drcs_map: Option<String>,
+    drcs_pattern: Vec<String>,
+    symbol_profile: SymbolProfile,
+    symbol_map: Option<String>,
+    text_width: TextWidth,
+    is_strip_markers: bool,
+    is_report: bool,
+    is_newline: bool,
+    is_flatten_newline: bool,
     id: String,
     infile: String,
     outfile: String,
@@ -62,10 +119,32 @@ struct CommanLineOpt {
 
 pub(crate) fn command_line_check(program: &str) -> CommanLineOpt {
 
+    // 実行時に与えられた引数で解析
+    command_line_parse(program, env::args().collect())
+
+}
+
+//
+// コマンドライン引数の解析処理
+//
+pub(crate) fn command_line_parse(program: &str, mut args: Vec<String>) -> CommanLineOpt {
+
     //let mut _is_logo: bool = false;
     let mut sdt_mode: bool = false;
     let mut eit_mode: bool = false;
     let mut is_xml: bool = false;
+    let mut xml_mode: XmlMode = XmlMode::Una;
+    let mut desc_layout: DescLayout = DescLayout::Append;
+    let mut is_json: bool = false;
+    let mut json_mode: JsonMode = JsonMode::Legacy;
+    let mut is_ndjson: bool = false;
+    let mut is_sqlite: bool = false;
+    let mut delimiter: Option<Delimiter> = None;
+    let mut columns: Vec<Column> = DEFAULT_COLUMNS.to_vec();
+    let mut is_bom: bool = false;
+    let mut is_ics: bool = false;
+    let mut ics_filter: IcsFilter = IcsFilter::default();
+    let mut is_genre_name: bool = false;
     let mut is_sid: bool = false;
     let mut select_sid: i32 = 0;
     let mut is_cut: bool = false;
@@ -73,14 +152,24 @@ pub(crate) fn command_line_check(program: &str) -> CommanLineOpt {
     let mut is_bs: bool = false;
     let mut is_cs: bool = false;
     //let mut _is_time: bool = false;
+    let mut lang_order: Vec<String> = vec!["jpn".to_string()];
+    let mut time_format: TimeFormat = TimeFormat::Legacy;
+    let mut overlap_policy: OverlapPolicy = OverlapPolicy::None;
+    let mut gap_fill: GapFill = GapFill::default();
+    let mut drcs_map: Option<String> = None;
+    let mut drcs_pattern: Vec<String> = vec![];
+    let mut symbol_profile: SymbolProfile = SymbolProfile::Bracket;
+    let mut symbol_map: Option<String> = None;
+    let mut text_width: TextWidth = TextWidth::Raw;
+    let mut is_strip_markers: bool = false;
+    let mut is_report: bool = false;
+    let mut is_newline: bool = false;
+    let mut is_flatten_newline: bool = false;
     let mut id: String = "".to_string();
     let infile: String;
     let outfile: String;
 
 
-    // 実行時に与えられた引数をargs: Vec<String>に格納する
-    let mut args: Vec<String> = env::args().collect();
-
     // epgdump互換引数の変換
     for cnt in 0..args.len() {
 
@@ -113,6 +202,36 @@ pub(crate) fn command_line_check(program: &str) -> CommanLineOpt {
     opts.optopt("c","cut","-cut,BS/CS不要チャンネル除外モード。nには不要チャンネルsidをcsv形式で指定","n1,n2,...");
     opts.optflag("","all","-all,全サービスを出力対象とする。");
     opts.optflag("","xml","-xml,XMLフォーマットで出力する。");
+    opts.optopt("","xml-mode","XMLの出力形式を指定(--xmlを含む、既定値:una)。una:従来形式 strict:xmltv.dtdに準拠したXMLTV","una|strict");
+    opts.optopt("","desc-layout","XMLの説明(<desc>)の出力形式を指定(既定値:append)。short:説明のみ append:説明の後に詳細説明を追加 separate:詳細説明の項目毎に<desc>を出力","short|append|separate");
+    opts.optflag("","json","JSONフォーマットで出力する。");
+    opts.optopt("","json-mode","JSONの出力形式を指定(--jsonを含む、既定値:legacy)。legacy:従来形式 mirakurun:Mirakurunの/api/services、/api/programs形式","legacy|mirakurun");
+    opts.optflag("","sqlite","outfileのSQLiteデータベースに出力する。既存のデータベースはイベントのバージョンが新しい場合に置き換え");
+    opts.optopt("","delimited","CSV/TSVフォーマットで出力する。csv:カンマ区切り tsv:タブ区切り","csv|tsv");
+    opts.optopt("","columns","CSV/TSVの出力項目をcsv形式で指定(既定値:channel,start,end,title,subtitle,genre,episode,flags)。その他にservice,event_id,duration,descを指定可能","c1,c2,...");
+    opts.optflag("","bom","CSV/TSVの先頭にBOM(UTF-8)を出力する(Excel向け)。");
+    opts.optflag("","ics","iCalendar(.ics)フォーマットで出力する(日時はJST、UIDはネットワークID、サービスID、イベントIDで作成)。");
+    opts.optopt("","ics-series","iCalendarに出力する番組のシリーズIDをcsv形式で指定(既定値:全番組)","n1,n2,...");
+    opts.optopt("","ics-title","iCalendarに出力する番組のタイトルに含む文字列を指定","text");
+    opts.optflag("","ndjson","NDJSON(1行1レコードのJSON)で出力する。標準入力、パイプの場合は受信したイベントを逐次出力");
+    opts.optflag("","genre-names","JSONのジャンルに名称(日本語、英語)を付加して出力する。");
+    opts.optopt("","lang","タイトル、説明の優先言語をcsv形式で指定(既定値:jpn)。その他の言語はXML/JSONにlang属性付きで出力","jpn,eng");
+    opts.optopt("","time-format","日時の出力形式を指定(既定値:legacy)。legacy:放送時刻 iso8601:オフセット付きISO-8601 utc:UTC","legacy|iso8601|utc");
+    opts.optopt("","overlap","番組重複の補正方針を指定(既定値:none)。trust-pf:EIT PFを優先 newest:新しいバージョンを優先 truncate:前の番組を切り詰め","none|trust-pf|newest|truncate");
+    opts.optflag("","no-gap-fill","番組間の空き時間に放送休止データを挿入せず、ジャンル未定義の番組のジャンルも補正しない。");
+    opts.optopt("","gap-title","放送休止データのタイトルを指定(既定値:放送休止)","title");
+    opts.optopt("","gap-genre","放送休止データ、ジャンル未定義の番組のジャンルをcontent_nibble_1,content_nibble_2[,user_nibble_1,user_nibble_2]で指定(既定値:14,0,3,15)","n1,n2[,u1,u2]");
+    opts.optopt("","gap-min","放送休止データを挿入する最小の空き時間(秒)を指定(既定値:1)","sec");
+    opts.optflag("","mark-synthetic","放送休止データをXMLはsynthetic属性(XMLTVは<keyword>synthetic</keyword>)、JSONはsyntheticで明示する。");
+    opts.optflag("","newline","説明の改行(APR)を改行、APFをスペースとして出力する(既定値:改行しない)。");
+    opts.optflag("","flatten-newline","XML/JSON/CSV/TSVで改行をスペースに置き換えて1行で出力する。");
+    opts.optflag("","strip-markers","タイトルから[新]、[再]、[字]などの記号を削除する(記号はXMLは<new/>などの要素、JSONはmarkersで出力)。");
+    opts.optopt("","drcs-map","DRCS(外字)のパターンのハッシュ(MD5)と置き換え文字列の対応表ファイルを指定(既定値:/etc/epgdump/drcs_map.conf)","file");
+    opts.optmulti("","drcs-pattern","DRCS(外字)のパターン(DRCSデータ構造)ファイルを指定。対応表に無いDRCSは「〓」で出力","file");
+    opts.optopt("","symbols","追加記号(囲み文字)の出力形式を指定(既定値:bracket)。bracket:[字] unicode:🈑 none:出力しない","bracket|unicode|none");
+    opts.optopt("","symbol-map","追加記号の文字コード(16進数)と置き換え文字列の対応表ファイルを指定(出力形式より優先)","file");
+    opts.optflag("","report","EIT SCHの受信漏れ期間、番組重複の補正内容を標準エラー出力に出力する。");
+    opts.optopt("","width","タイトル、説明、サービス名の文字幅の正規化方式を指定(既定値:raw)。raw:放送のまま fold:英数字は半角、カナは全角 nfkc:Unicode正規化(NFKC)","raw|fold|nfkc");
     opts.optflag("h","help","このヘルプを表示");
     opts.optflag("v","version","バージョンを表示する。");
 
@@ -174,6 +293,251 @@ pub(crate) fn command_line_check(program: &str) -> CommanLineOpt {
         is_xml = true;
     }
 
+    // XML出力形式の設定
+    if matches.opt_present("xml-mode") {
+        is_xml = true;
+        xml_mode = match &*matches.opt_str("xml-mode").unwrap() {
+            "una" => XmlMode::Una,
+            "strict" => XmlMode::Strict,
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // XMLの説明の出力形式の設定
+    if matches.opt_present("desc-layout") {
+        desc_layout = match &*matches.opt_str("desc-layout").unwrap() {
+            "short" => DescLayout::Short,
+            "append" => DescLayout::Append,
+            "separate" => DescLayout::Separate,
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // JSONフォーマット出力の設定
+    if matches.opt_present("json") {
+        is_json = true;
+    }
+
+    // NDJSONフォーマット出力の設定
+    if matches.opt_present("ndjson") {
+        is_ndjson = true;
+    }
+
+    // SQLite出力の設定
+    if matches.opt_present("sqlite") {
+        is_sqlite = true;
+    }
+
+    // CSV/TSVフォーマット出力の設定
+    if matches.opt_present("delimited") {
+        delimiter = match &*matches.opt_str("delimited").unwrap() {
+            "csv" => Some(Delimiter::Csv),
+            "tsv" => Some(Delimiter::Tsv),
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // CSV/TSVの出力項目の設定
+    if matches.opt_present("columns") {
+        columns = match column_list_parse(&matches.opt_str("columns").unwrap()) {
+            Some(columns) => columns,
+            None => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // CSV/TSVのBOM出力の設定
+    if matches.opt_present("bom") {
+        is_bom = true;
+    }
+
+    // iCalendarフォーマット出力の設定
+    if matches.opt_present("ics") {
+        is_ics = true;
+    }
+
+    // iCalendarに出力するシリーズIDの設定
+    if matches.opt_present("ics-series") {
+        ics_filter.series_ids = match matches.opt_str("ics-series").unwrap()
+            .split(',')
+            .map(|series_id| series_id.trim().parse::<i32>())
+            .collect() {
+            Ok(series_ids) => series_ids,
+            Err(_e) => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // iCalendarに出力するタイトルの設定
+    if matches.opt_present("ics-title") {
+        ics_filter.title = matches.opt_str("ics-title");
+    }
+
+    // JSONの出力形式の設定
+    if matches.opt_present("json-mode") {
+        is_json = true;
+        json_mode = match &*matches.opt_str("json-mode").unwrap() {
+            "legacy" => JsonMode::Legacy,
+            "mirakurun" => JsonMode::Mirakurun,
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // ジャンル名称出力の設定
+    if matches.opt_present("genre-names") {
+        is_genre_name = true;
+    }
+
+    // 優先言語の設定
+    if matches.opt_present("lang") {
+        lang_order = matches.opt_str("lang").unwrap()
+            .split(',')
+            .map(|lang| lang.trim().to_string())
+            .filter(|lang| !lang.is_empty())
+            .collect();
+    }
+
+    // 日時出力形式の設定
+    if matches.opt_present("time-format") {
+        time_format = match &*matches.opt_str("time-format").unwrap() {
+            "legacy" => TimeFormat::Legacy,
+            "iso8601" => TimeFormat::Iso8601,
+            "utc" => TimeFormat::Utc,
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // 番組重複補正方針の設定
+    if matches.opt_present("overlap") {
+        overlap_policy = match &*matches.opt_str("overlap").unwrap() {
+            "none" => OverlapPolicy::None,
+            "trust-pf" => OverlapPolicy::TrustPf,
+            "newest" => OverlapPolicy::Newest,
+            "truncate" => OverlapPolicy::Truncate,
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // 放送休止データ挿入の設定
+    if matches.opt_present("no-gap-fill") {
+        gap_fill.is_fill = false;
+    }
+
+    // 放送休止データのタイトルの設定
+    if matches.opt_present("gap-title") {
+        gap_fill.title = matches.opt_str("gap-title").unwrap();
+    }
+
+    // 放送休止データのジャンルの設定
+    if matches.opt_present("gap-genre") {
+        gap_fill.genre = match gap_genre_parse(&matches.opt_str("gap-genre").unwrap()) {
+            Some(genre) => genre,
+            None => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // 放送休止データの最小の空き時間の設定
+    if matches.opt_present("gap-min") {
+        gap_fill.min_gap = match matches.opt_str("gap-min").unwrap().parse::<i64>() {
+            Ok(min_gap) => min_gap,
+            Err(_e) => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // 放送休止データ明示の設定
+    if matches.opt_present("mark-synthetic") {
+        gap_fill.is_mark = true;
+    }
+
+    // 改行出力の設定
+    if matches.opt_present("newline") {
+        is_newline = true;
+    }
+
+    // XML/JSONの改行置き換えの設定
+    if matches.opt_present("flatten-newline") {
+        is_flatten_newline = true;
+    }
+
+    // タイトルの記号削除の設定
+    if matches.opt_present("strip-markers") {
+        is_strip_markers = true;
+    }
+
+    // 受信漏れ、番組重複補正レポートの設定
+    if matches.opt_present("report") {
+        is_report = true;
+    }
+
+    // DRCS対応表ファイルの設定
+    if matches.opt_present("drcs-map") {
+        drcs_map = matches.opt_str("drcs-map");
+    }
+
+    // DRCSパターンファイルの設定
+    if matches.opt_present("drcs-pattern") {
+        drcs_pattern = matches.opt_strs("drcs-pattern");
+    }
+
+    // 追加記号の出力形式の設定
+    if matches.opt_present("symbols") {
+        symbol_profile = match &*matches.opt_str("symbols").unwrap() {
+            "bracket" => SymbolProfile::Bracket,
+            "unicode" => SymbolProfile::Unicode,
+            "none" => SymbolProfile::None,
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
+    // 追加記号対応表ファイルの設定
+    if matches.opt_present("symbol-map") {
+        symbol_map = matches.opt_str("symbol-map");
+    }
+
+    // 文字幅の正規化方式の設定
+    if matches.opt_present("width") {
+        text_width = match &*matches.opt_str("width").unwrap() {
+            "raw" => TextWidth::Raw,
+            "fold" => TextWidth::Fold,
+            "nfkc" => TextWidth::Nfkc,
+            _ => {
+                show_usage(program, &opts);
+                process::exit(0);
+            },
+        };
+    }
+
     // BS/CS単チャンネル出力モードの設定
     if matches.opt_present("sid") {
         is_sid = true;
@@ -224,12 +588,30 @@ pub(crate) fn command_line_check(program: &str) -> CommanLineOpt {
 
     };
 
+    // SQLite出力は標準出力に出力できない
+    if is_sqlite && outfile == "-" {
+        show_usage(program, &opts);
+        process::exit(0);
+    }
+
     // リターン情報
     CommanLineOpt {
         //is_logo: _is_logo,
         sdt_mode: sdt_mode,
         eit_mode: eit_mode,
         is_xml: is_xml,
+        xml_mode,
+        desc_layout,
+        is_json,
+        json_mode,
+        is_ndjson,
+        is_sqlite,
+        delimiter,
+        columns,
+        is_bom,
+        is_ics,
+        ics_filter,
+        is_genre_name,
         is_sid: is_sid,
         select_sid: select_sid,
         _is_cut: is_cut,
@@ -237,6 +619,19 @@ pub(crate) fn command_line_check(program: &str) -> CommanLineOpt {
         is_bs: is_bs,
         is_cs: is_cs,
         //is_time: _is_time,
+        lang_order,
+        time_format,
+        overlap_policy,
+        gap_fill,
+        drcs_map,
+        drcs_pattern,
+        symbol_profile,
+        symbol_map,
+        text_width,
+        is_strip_markers,
+        is_report,
+        is_newline,
+        is_flatten_newline,
         id: id,
         infile: infile,
         outfile: outfile,
@@ -361,12 +756,27 @@ fn main() {
         ch_type = 0;
     };
 
+    // DRCS変換表の読込み
+    drcs_table_init(drcs_table_read(opt.drcs_map.as_deref(), &opt.drcs_pattern));
+
+    // 追加記号変換表の読込み
+    symbol_table_init(symbol_table_read(opt.symbol_profile, opt.symbol_map.as_deref()));
+
+    // 文字幅の正規化方式の設定
+    text_width_init(opt.text_width);
+
+    // 改行出力の設定
+    newline_init(opt.is_newline);
+
     // tsid_list構造体の作成と初期化
     let mut tsid_list: Vec<TsidList> = vec![];
 
     // tsid_listデータの読み込み
     tsid_node_slot_list_read(&mut tsid_list);
 
+    // TDT/TOTの受信状態の初期化
+    let mut tot = TotState::default();
+
     // 構造体(SecCache, TS_Packet)の初期化
     let mut secs: [SecCache; SECCOUNT] = [
         SecCache {
@@ -413,6 +823,9 @@ fn main() {
         eitsch: vec![],
         eit_pf: vec![],
         prev_sch: vec![],
+        sch_store: EitStore::default(),
+        pf_store: EitStore::default(),
+        null_segment: EitNullSegmentTop::default(),
         import_cnt: 0,
         import_stat: 0,
         logo_download_data_id: 0,
@@ -437,6 +850,9 @@ fn main() {
             eitsch: vec![],
             eit_pf: vec![],
             prev_sch: vec![],
+            sch_store: EitStore::default(),
+            pf_store: EitStore::default(),
+            null_segment: EitNullSegmentTop::default(),
             import_cnt: 0,
             import_stat: 0,
             logo_download_data_id: 0,
@@ -459,11 +875,24 @@ fn main() {
         "-" => {
             File::create("/dev/stdout").unwrap()
         },
+        // SQLiteの場合は既存のデータベースを更新するため切り詰めない
+        _ if opt.is_sqlite => {
+            OpenOptions::new().write(true).create(true).truncate(false).open(&opt.outfile).unwrap()
+        },
         _ => {
             File::create(&opt.outfile).unwrap()
         },
     };
 
+    // NDJSON出力の作成(標準入力、パイプの場合は受信中に逐次出力)
+    let mut ndjson = if opt.is_ndjson {
+        let is_stream = opt.infile == "-" || !infile.metadata().is_ok_and(|metadata| metadata.is_file());
+        Some(NdjsonWriter::new(outfile.try_clone().unwrap(), is_stream))
+    }
+    else {
+        None
+    };
+
     // リードバッファの作成
     let mut readbuff_file = BufReader::with_capacity(CAP, &infile);
 
@@ -472,48 +901,62 @@ fn main() {
     // 処理対象を設定
     secs[1].pid = 0x11; // SDT
     secs[2].pid = 0x12; // H-EIT
-    secs_count = 3;
+    secs[3].pid = 0x14; // TOT
+    secs_count = 4;
 
     // std取得呼び出し
-    get_sdt(&opt, &mut readbuff_file, &mut svttop, &mut secs, secs_count);
-    
-    // 不要なsevice_idの削除
-    for cnt in (0..svttop.len()).rev() {
+    get_sdt(&opt, &mut readbuff_file, &mut svttop, &mut secs, secs_count, &mut tot, &mut ndjson);
 
-        if svttop[cnt].svt_control_sub[0].import_stat <= 0 {
+    // 出力対象のサービス、番組配列の作成処理呼び出し
+    service_list_update(&opt, &tot, &mut svttop);
 
-            svttop.remove(cnt);
+    // EIT SCHの受信漏れレポート
+    if !opt.eit_mode {
 
-        };
+        for svt in svttop.iter() {
 
+            svt.svt_control_sub[0].null_segment.hole_report(&tot, opt.is_report);
+
+        };
     };
 
-    // 削除対象とするtransport_stream_idのワーク変数
-    let mut transport_stream_id = 0;
+    // SQLiteデータベースの作成処理
+    if opt.is_sqlite {
 
-    // 余計なeit_pfの削除
-    for cnt in 0..svttop.len() {
+        // SQLiteデータベース作成処理呼び出し
+        dump_sqlite(&opt, &mut svttop);
 
-        // 2つ目以降のeit_pfを削除
-        if transport_stream_id != svttop[cnt].svt_control_sub[0].transport_stream_id {
+    }
+    // iCalendarファイルの作成処理
+    else if opt.is_ics {
 
-            // 削除対象のtransport_stream_idを設定
-            transport_stream_id = svttop[cnt].svt_control_sub[0].transport_stream_id;
+        // iCalendar作成処理呼び出し
+        dump_ics(&opt, &tot, &mut outfile, &mut svttop);
 
-        }
-        else {
+    }
+    // CSV/TSVファイルの作成処理
+    else if let Some(delimiter) = opt.delimiter {
 
-            // eit_pfの削除
-            svttop[cnt].svt_control_sub[0].eit_pf = vec![];
+        // CSV/TSV作成処理呼び出し
+        dump_delimited(&opt, &tot, &mut outfile, delimiter, &mut svttop);
 
-        };
+    }
+    // NDJSONファイルの作成処理
+    else if let Some(ndjson) = ndjson.as_mut() {
 
-        debug!("svttop={},service_id={},service_type={},servicename={},import_cnt={},import_stat={}", cnt, svttop[cnt].svt_control_sub[0].service_id, svttop[cnt].svt_control_sub[0].service_type, svttop[cnt].svt_control_sub[0].servicename, svttop[cnt].svt_control_sub[0].import_cnt, svttop[cnt].svt_control_sub[0].import_stat);
+        // NDJSON出力の終了処理呼び出し
+        ndjson.finish(&opt, &tot, &mut svttop);
 
-    };
+    }
+    // XMLTVファイルの作成処理
+    else if opt.is_xml && opt.xml_mode == XmlMode::Strict {
+
+        // XMLTV作成処理呼び出し
+        dump_xmltv(&opt, &tot, &mut outfile, &mut svttop);
 
+    }
     // XMLファイルの作成処理
-    if opt.is_xml == true {
+    else if opt.is_xml == true {
 
         // ヘッダー出力
         writeln!(outfile,"<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
@@ -540,13 +983,26 @@ fn main() {
         for cnt in 0..svttop.len() {
 
             // XML詳細作成処理呼び出し
-            dump_xml(&opt, &mut outfile, &mut svttop[cnt].svt_control_sub[0]);
+            dump_xml(&opt, &tot, &mut outfile, &mut svttop[cnt].svt_control_sub[0]);
 
         };
 
         // フッター出力
         writeln!(outfile,"</tv>").unwrap();
 
+    }
+    // JSONファイルの作成処理
+    else if opt.is_json && opt.json_mode == JsonMode::Mirakurun {
+
+        // Mirakurun形式のJSON作成処理呼び出し
+        dump_mirakurun(&opt, &mut outfile, &mut svttop);
+
+    }
+    else if opt.is_json {
+
+        // JSON作成処理呼び出し
+        dump_json(&opt, &tot, &mut outfile, &mut svttop);
+
     }
     // svttop配列がある場合のserial出力処理
     else if svttop.len() > 0 {
@@ -620,7 +1076,7 @@ fn main() {
             if svttop[cnt].svt_control_sub[0].import_stat == 2 {
 
                 // シリアル出力処理呼び出し
-                dump_serial(&opt, &mut outfile, &mut svttop[cnt].svt_control_sub[0])
+                dump_serial(&opt, &tot, &mut outfile, &mut svttop[cnt].svt_control_sub[0])
 
             };
 
@@ -633,7 +1089,7 @@ fn main() {
 // データ構造体作成処理
 //
 fn get_sdt( cmd_opt: &CommanLineOpt, mut readbuff_file: &mut BufReader<&File>,
-    svttop: &mut Vec<SvtControlTop>, mut secs: &mut [SecCache], count: usize) -> () {
+    svttop: &mut Vec<SvtControlTop>, mut secs: &mut [SecCache], count: usize, tot: &mut TotState, ndjson: &mut Option<NdjsonWriter>) -> () {
 
     // ループ
     loop {
@@ -658,11 +1114,19 @@ fn get_sdt( cmd_opt: &CommanLineOpt, mut readbuff_file: &mut BufReader<&File>,
                         // EIT構造体の作成処理呼び出し
                         dump_eit(&cmd_opt, &bsecs.buf, svttop);
 
+                        // NDJSONの逐次出力処理呼び出し
+                        if let Some(ndjson) = ndjson.as_mut() {
+                            ndjson.stream(cmd_opt, tot, svttop);
+                        };
+
                     },
-                    /*
                     0x14 => {  // TOT
 
+                        // TOTの解析処理呼び出し
+                        dump_tot(tot, &bsecs.buf);
+
                     },
+                    /*
                     // SDTT
                     0x24 => {  // SDTT
                      
@@ -687,12 +1151,11 @@ fn get_sdt( cmd_opt: &CommanLineOpt, mut readbuff_file: &mut BufReader<&File>,
 //
 // 放送休止データ挿入処理(EIT PF)
 //
-fn insert_rest_pf(svtcur: &mut SvtControl) -> () {
+fn insert_rest_pf(gap_fill: &GapFill, svtcur: &mut SvtControl) -> () {
 
     // 変数作成
     let mut start_time: i64;
     let mut end_time: i64;
-    let mut end_time_dt: DateTime<Local>;
 
     // eit_pf配列が未作成の場合はリターン
     if svtcur.eit_pf.len() <= 0 {
@@ -704,16 +1167,8 @@ fn insert_rest_pf(svtcur: &mut SvtControl) -> () {
     // イベントステータスがCERTAINTYの場合の処理
     if svtcur.eit_pf[0].event_status == CERTAINTY {
 
-        // end_time用のDateTime形式の開始日時情報作成
-        let dt: DateTime<Local> = Local.with_ymd_and_hms(
-            svtcur.eit_pf[0].yy as i32 + 1900, svtcur.eit_pf[0].mm as u32, svtcur.eit_pf[0].dd as u32,
-            svtcur.eit_pf[0].hh as u32, svtcur.eit_pf[0].hm as u32, svtcur.eit_pf[0].ss as u32).unwrap();
-
         // end_time用のシリアル形式の終了日時情報作成
-        end_time = dt.timestamp() + svtcur.eit_pf[0].duration as i64;
-
-        // end_time用のDateTime形式の終了日時情報作成
-        end_time_dt = Local.timestamp_opt(end_time,0).unwrap();
+        end_time = svtcur.eit_pf[0].start_time + svtcur.eit_pf[0].duration as i64;
 
         // eit_pfループカウンター作成
         let mut cnt2 = 1;
@@ -728,55 +1183,12 @@ fn insert_rest_pf(svtcur: &mut SvtControl) -> () {
 
             }
 
-            // starttime用のDateTime形式の開始日時情報作成
-            let dt: DateTime<Local> = Local.with_ymd_and_hms(
-                svtcur.eit_pf[cnt2].yy as i32 + 1900, svtcur.eit_pf[cnt2].mm as u32, svtcur.eit_pf[cnt2].dd as u32,
-                svtcur.eit_pf[cnt2].hh as u32, svtcur.eit_pf[cnt2].hm as u32, svtcur.eit_pf[cnt2].ss as u32).unwrap();
-
             // start_time用のシリアル形式の開始日時情報作成
-            start_time = dt.timestamp();
-
-            // 終了時間が開始時間より小さい場合は放送休止データを挿入
-            if end_time != start_time {
-                svtcur.eit_pf.insert(cnt2, EitControl {
-                    table_id: svtcur.eit_pf[cnt2].table_id,
-                    servid: svtcur.eit_pf[cnt2].servid,
-                    event_id: -1,
-                    version_number: 0,
-                    section_number: 0,
-                    last_section_number: 0,
-                    segment_last_section_number: 0,
-                    running_status: 0,
-                    free_ca_mode: 0,
-                    content_type: 14,
-                    content_subtype: 0x3f,
-                    genre2: 16,
-                    sub_genre2: 16,
-                    genre3: 16,
-                    sub_genre3: 16,
-                    episode_number: 0,
-                    yy: end_time_dt.year() as i32 - 1900,
-                    mm: end_time_dt.month() as i32,
-                    dd: end_time_dt.day() as i32,
-                    hh: end_time_dt.hour() as i32,
-                    hm: end_time_dt.minute() as i32,
-                    ss: end_time_dt.second() as i32,
-                    duration: (start_time - end_time) as i32,
-                    start_time: start_time + svtcur.eit_pf[cnt2 - 1].duration as i64,
-                    title: String::from("放送休止"),
-                    subtitle: String::new(),
-                    desc: String::new(),
-                    desc_length: 0,
-                    video_type: 0,
-                    audio_type: 0,
-                    multi_type: 0,
-                    event_status: 0,
-                    sch_pnt: -1,
-                    import_cnt: 0,
-                    renew_cnt: 0,
-                    tid: 0,
-                    tid_status: 0,
-                });
+            start_time = svtcur.eit_pf[cnt2].start_time;
+
+            // 終了時間が開始時間より小さい場合は放送休止データを挿入(補完対象の空き時間のみ)
+            if gap_fill.is_gap(end_time, start_time) {
+                svtcur.eit_pf.insert(cnt2, gap_fill.rest_event(&svtcur.eit_pf[cnt2], end_time, start_time, -1));
 
                 // 放送休止追加後にカウンターアップ
                 cnt2 += 1;
@@ -793,9 +1205,6 @@ fn insert_rest_pf(svtcur: &mut SvtControl) -> () {
             // 終了日時を更新
             end_time = start_time + svtcur.eit_pf[cnt2].duration as i64;
 
-            // DateTime形式の終了日時情報作成
-            end_time_dt = Local.timestamp_opt(end_time,0).unwrap();
-
             // カウンターアップ
             cnt2 += 1;
 
@@ -806,24 +1215,14 @@ fn insert_rest_pf(svtcur: &mut SvtControl) -> () {
 //
 // 放送休止データ挿入処理(EIT SCH)
 //
-fn insert_rest_sch(svtcur: &mut SvtControl) -> () {
+fn insert_rest_sch(gap_fill: &GapFill, tot: &TotState, svtcur: &mut SvtControl) -> () {
 
     // 変数作成
     let mut start_time: i64;
-    let mut end_time: i64;
-    let mut end_time_dt: DateTime<Local>;
-    let cnt = 0;
-
-    // DateTime形式の開始日時情報作成
-    let dt: DateTime<Local> = Local.with_ymd_and_hms(
-        svtcur.eitsch[cnt].yy as i32 + 1900, svtcur.eitsch[cnt].mm as u32, svtcur.eitsch[cnt].dd as u32,
-        svtcur.eitsch[cnt].hh as u32, svtcur.eitsch[cnt].hm as u32, svtcur.eitsch[cnt].ss as u32).unwrap();
-
-    // シリアル形式の終了日時情報作成
-    end_time = dt.timestamp() + svtcur.eitsch[cnt].duration as i64;
+    let mut end_time: Option<i64>;
 
-    // DateTime形式の終了日時情報作成
-    end_time_dt = Local.timestamp_opt(end_time,0).unwrap();
+    // 終了日時作成(開始日時、継続時間が未定義の場合はNone)
+    end_time = svtcur.eitsch[0].end();
 
     // eitschループカウンター作成
     let mut cnt2 = 0;
@@ -831,67 +1230,33 @@ fn insert_rest_sch(svtcur: &mut SvtControl) -> () {
     // eitsch配列分ループ
     while cnt2 < svtcur.eitsch.len() {
 
-        // DateTime形式の開始日時情報作成
-        let dt: DateTime<Local> = Local.with_ymd_and_hms(
-            svtcur.eitsch[cnt2].yy as i32 + 1900, svtcur.eitsch[cnt2].mm as u32, svtcur.eitsch[cnt2].dd as u32,
-            svtcur.eitsch[cnt2].hh as u32, svtcur.eitsch[cnt2].hm as u32, svtcur.eitsch[cnt2].ss as u32).unwrap();
+        // 開始日時が未定義の場合は終了日時を未定義にして次を処理
+        start_time = match svtcur.eitsch[cnt2].start() {
+            Some(start_time) => start_time,
+            None => {
 
-        // シリアル形式の開始日時情報作成
-        start_time = dt.timestamp();
-            
-        // 終了時間が開始時間より小さい場合は放送休止データを挿入
-        if end_time < start_time {
-
-            svtcur.eitsch.insert(cnt2, EitControl {
-                table_id: svtcur.eitsch[cnt2].table_id,
-                servid: svtcur.eitsch[cnt2].servid,
-                event_id: -1,
-                version_number: 0,
-                section_number: 0,
-                last_section_number: 0,
-                segment_last_section_number: 0,
-                running_status: 0,
-                free_ca_mode: 0,
-                content_type: 14,
-                content_subtype: 0x3f,
-                genre2: 16,
-                sub_genre2: 16,
-                genre3: 16,
-                sub_genre3: 16,
-                episode_number: 0,
-                yy: end_time_dt.year() as i32 - 1900,
-                mm: end_time_dt.month() as i32,
-                dd: end_time_dt.day() as i32,
-                hh: end_time_dt.hour() as i32,
-                hm: end_time_dt.minute() as i32,
-                ss: end_time_dt.second() as i32,
-                duration: (start_time - end_time) as i32,
-                start_time: end_time as i64,
-                title: String::from("放送休止"),
-                subtitle: String::new(),
-                desc: String::new(),
-                desc_length: 0,
-                video_type: 0,
-                audio_type: 0,
-                multi_type: 0,
-                event_status: 0,
-                sch_pnt: 0,
-                import_cnt: 0,
-                renew_cnt: 0,
-                tid: 0,
-                tid_status: 0,
-            });
+                end_time = None;
+                cnt2 += 1;
+
+                continue;
+
+            },
+        };
+
+        // 終了時間が開始時間より小さい場合は放送休止データを挿入(補完対象の空き時間のみ)
+        // (受信漏れのセグメントを含む空き時間は対象外)
+        if let Some(end_time) = end_time.filter(|end_time|
+            gap_fill.is_gap(*end_time, start_time) && svtcur.null_segment.is_received(*end_time, start_time, tot.current_time)) {
+
+            svtcur.eitsch.insert(cnt2, gap_fill.rest_event(&svtcur.eitsch[cnt2], end_time, start_time, 0));
 
             // 放送休止追加後にカウンターアップ
             cnt2 += 1;
 
         };
 
-        // 終了日時を更新
-        end_time = start_time + svtcur.eitsch[cnt2].duration as i64;
-
-        // DateTime形式の終了日時情報作成
-        end_time_dt = Local.timestamp_opt(end_time,0).unwrap();
+        // 終了日時を更新(継続時間が未定義の場合はNone)
+        end_time = svtcur.eitsch[cnt2].end();
 
         // カウンターアップ
         cnt2 += 1;
@@ -900,24 +1265,25 @@ fn insert_rest_sch(svtcur: &mut SvtControl) -> () {
 
 //
 // ジャンル未定義補正処理
+// (放送休止データを補完しない場合は補正しない、ジャンルは放送休止データのジャンルで補正)
 //
-fn rest_repair(eitcur: &mut EitControl) -> () {
+fn rest_repair(gap_fill: &GapFill, eitcur: &mut EitControl) -> () {
+
+    if !gap_fill.is_fill {
+
+        return;
 
-    if eitcur.content_type == 0 && eitcur.content_subtype == 0 && eitcur.genre2 == 0 &&
-        eitcur.sub_genre2 == 0 && eitcur.genre3 == 0 && eitcur.sub_genre3 == 0 {
+    }
+
+    if eitcur.genres.is_empty() {
 
-        eitcur.content_type    = 14;
-        eitcur.content_subtype = 0x3f;
-        eitcur.genre2          = 16;
-        eitcur.sub_genre2      = 16;
-        eitcur.genre3          = 16;
-        eitcur.sub_genre3      = 16;
+        eitcur.genres.push(gap_fill.genre);
 
     }
 
-    if eitcur.content_type == 14 && eitcur.content_subtype == 0x3f && eitcur.title == "" {
+    if (genre_is_rest(&eitcur.genres) || eitcur.genres.first() == Some(&gap_fill.genre)) && eitcur.title == "" {
 
-        eitcur.title = "放送休止".to_string();
+        eitcur.title = gap_fill.title.clone();
 
     }
 
@@ -926,10 +1292,10 @@ fn rest_repair(eitcur: &mut EitControl) -> () {
 //
 // シリアルデータの作成処理
 //
-fn line_serial(line_cnt: i32, array_cnt: i32, mut eitcur: &mut EitControl, ch_disc: &String) -> String {
+fn line_serial(cmd_opt: &CommanLineOpt, tot: &TotState, line_cnt: i32, array_cnt: i32, mut eitcur: &mut EitControl, ch_disc: &String) -> String {
 
     // ジャンル未定義補正処理呼び出し
-    rest_repair(&mut eitcur);
+    rest_repair(&cmd_opt.gap_fill, &mut eitcur);
     
     // 処理用タイトル作成
     let title = &eitcur.title;
@@ -941,41 +1307,32 @@ fn line_serial(line_cnt: i32, array_cnt: i32, mut eitcur: &mut EitControl, ch_di
     }
     subtitle.push_str(&eitcur.subtitle);
 
-    // 処理用開始時間作成
-    let start_time = eitcur.start_time;
-    
-    // 処理用終了時間作成
-    let end_time = start_time + eitcur.duration as i64;
-
-    // 処理用終了時間(キャラクタ)作成
-    let cendtime = Local.timestamp_opt(end_time,0).unwrap().format("%Y-%m-%d %H:%M:%S");
-
-    // 処理用開始時間(キャラクタ)作成
-    let cstarttime = format!("{:4}-{:02}-{:02} {:02}:{:02}:{:02}", eitcur.yy + 1900, eitcur.mm, eitcur.dd, eitcur.hh, eitcur.hm, eitcur.ss);
-
-    // 処理用コンテンツタイプ作成
-    let content_type = match eitcur.content_type {
-        16 => { 0 },
-        _ => { eitcur.content_type + 1 },
+    // 処理用開始時間(シリアル)作成(未定義の場合はnull)
+    let cstarttime = match eitcur.start() {
+        Some(start_time) => {
+            let cstarttime = tot.format_time(start_time, cmd_opt.time_format);
+            format!("s:{}:\"{}\";", cstarttime.len(), cstarttime)
+        },
+        None => "N;".to_string(),
     };
 
-    // 処理用genre2作成
-    let genre2 = match eitcur.genre2 {
-        16 => { 0 },
-        _ => { eitcur.genre2 + 1 },
+    // 処理用終了時間(シリアル)作成(未定義の場合はnull)
+    let cendtime = match eitcur.end() {
+        Some(end_time) => {
+            let cendtime = tot.format_time(end_time, cmd_opt.time_format);
+            format!("s:{}:\"{}\";", cendtime.len(), cendtime)
+        },
+        None => "N;".to_string(),
     };
 
-    // 処理用genrer3作成
-    let genre3 = match eitcur.genre3 {
-        16 => { 0 },
-        _ => { eitcur.genre3 + 1 },
-    };
+    // 処理用ジャンル(旧形式互換)作成
+    let genre = genre_compat(&eitcur.genres);
     
     // リターン文字作成
     let ret_str = format!(
         "i:{};a:{}:{}\
-        s:9:\"starttime\";s:19:\"{}\";\
-        s:7:\"endtime\";s:19:\"{}\";\
+        s:9:\"starttime\";{}\
+        s:7:\"endtime\";{}\
         s:12:\"channel_disc\";s:{}:\"{}\";\
         s:3:\"eid\";i:{};\
         s:5:\"title\";s:{}:\"{}\";\
@@ -991,9 +1348,9 @@ fn line_serial(line_cnt: i32, array_cnt: i32, mut eitcur: &mut EitControl, ch_di
         eitcur.event_id,
         title.len(),title,
         subtitle.len(), subtitle,
-        content_type, eitcur.content_subtype,
-        genre2, eitcur.sub_genre2,
-        genre3, eitcur.sub_genre3,
+        genre[0], genre[1],
+        genre[2], genre[3],
+        genre[4], genre[5],
         eitcur.video_type, eitcur.audio_type, eitcur.multi_type
         );
 
@@ -1071,18 +1428,180 @@ fn sch_pnt_update( svtcur: &mut SvtControl) -> () {
 
 }
 
+//
+// 出力対象のサービス、番組配列の作成処理
+// (取得対象外のサービスの削除、番組重複の補正、同じtransport_stream_idの2つ目以降のEIT PFの削除、タイトルの記号の解析)
+//
+fn service_list_update(cmd_opt: &CommanLineOpt, tot: &TotState, svttop: &mut Vec<SvtControlTop>) {
+
+    // イベントストアから開始時刻順の番組配列を作成
+    for svt in svttop.iter_mut() {
+
+        let svtcur = &mut svt.svt_control_sub[0];
+        svtcur.eitsch = svtcur.sch_store.to_vec();
+        svtcur.eit_pf = svtcur.pf_store.to_vec();
+
+    };
+
+    // 不要なsevice_idの削除
+    for cnt in (0..svttop.len()).rev() {
+
+        if svttop[cnt].svt_control_sub[0].import_stat <= 0 {
+
+            svttop.remove(cnt);
+
+        };
+
+    };
+
+    // 番組重複の補正処理(EIT PFの削除前に、全サービスのEIT PFで補正)
+    for svt in svttop.iter_mut() {
+
+        overlap_repair(&mut svt.svt_control_sub[0], cmd_opt.overlap_policy, tot, cmd_opt.is_report);
+
+    };
+
+    // 余計なeit_pfの削除
+    let pf_flags = pf_service_flags(svttop);
+    for cnt in 0..svttop.len() {
+
+        // 2つ目以降のeit_pfを削除
+        if !pf_flags[cnt] {
+
+            // eit_pfの削除
+            svttop[cnt].svt_control_sub[0].eit_pf = vec![];
+
+        };
+
+        debug!("svttop={},service_id={},service_type={},servicename={},import_cnt={},import_stat={}", cnt, svttop[cnt].svt_control_sub[0].service_id, svttop[cnt].svt_control_sub[0].service_type, svttop[cnt].svt_control_sub[0].servicename, svttop[cnt].svt_control_sub[0].import_cnt, svttop[cnt].svt_control_sub[0].import_stat);
+
+    };
+
+    // タイトルの記号の解析処理
+    for svt in svttop.iter_mut() {
+
+        marker_parse(&mut svt.svt_control_sub[0], cmd_opt.is_strip_markers);
+
+    };
+
+}
+
+//
+// EIT PFの出力対象サービスの判定処理
+// (取得対象外のサービスはfalse、同じtransport_stream_idの2つ目以降のサービスはEIT PFを出力しないためfalse)
+//
+pub(crate) fn pf_service_flags(svttop: &[SvtControlTop]) -> Vec<bool> {
+
+    // 削除対象とするtransport_stream_idのワーク変数
+    let mut transport_stream_id = 0;
+
+    svttop.iter()
+        .map(|svt| {
+
+            let svtcur = &svt.svt_control_sub[0];
+
+            // 取得対象外のサービス
+            if svtcur.import_stat <= 0 {
+
+                return false;
+
+            };
+
+            // transport_stream_idの最初のサービス
+            if transport_stream_id != svtcur.transport_stream_id {
+
+                transport_stream_id = svtcur.transport_stream_id;
+
+                return true;
+
+            };
+
+            false
+
+        })
+        .collect()
+
+}
+
+//
+// 出力前の番組データ補正処理
+// (放送休止データの挿入、sch_pnt補正、ジャンル未定義補正)
+//
+pub(crate) fn programme_repair(cmd_opt: &CommanLineOpt, tot: &TotState, svtcur: &mut SvtControl) {
+
+    // 放送休止補正処理(EIT PF)
+    insert_rest_pf(&cmd_opt.gap_fill, svtcur);
+
+    // EITモードフラグがfalseでeitschにデータある場合の処理
+    if !cmd_opt.eit_mode && !svtcur.eitsch.is_empty() {
+
+        // 放送休止補正処理(EIT SCH)
+        insert_rest_sch(&cmd_opt.gap_fill, tot, svtcur);
+
+        // sch_pnt補正処理呼出し
+        sch_pnt_update(svtcur);
+
+    };
+
+    // ジャンル未定義補正処理呼び出し
+    for eitcur in svtcur.eit_pf.iter_mut().chain(svtcur.eitsch.iter_mut()) {
+
+        rest_repair(&cmd_opt.gap_fill, eitcur);
+
+    };
+
+}
+
+//
+// 出力対象の番組一覧作成処理
+// (EIT SCHに無いEIT PFの番組を補って開始時刻順に並べ替え、EITモードフラグがtrueの場合はEIT PFのみ)
+// (放送休止データはイベントIDが共通のため、同じ開始時刻のデータが無い場合に追加)
+//
+pub(crate) fn programme_list<'a>(cmd_opt: &CommanLineOpt, svtcur: &'a SvtControl) -> Vec<&'a EitControl> {
+
+    let mut programmes: Vec<&EitControl> = vec![];
+    if !cmd_opt.eit_mode {
+
+        programmes.extend(svtcur.eitsch.iter());
+
+    };
+    for eitcur in svtcur.eit_pf.iter() {
+
+        let is_found = if eitcur.is_synthetic() {
+
+            programmes.iter().any(|programme| programme.is_synthetic() && programme.start() == eitcur.start())
+
+        }
+        else {
+
+            programmes.iter().any(|programme| programme.event_id == eitcur.event_id)
+
+        };
+
+        if !is_found {
+
+            programmes.push(eitcur);
+
+        };
+    };
+    programmes.sort_by_key(|programme| programme.start());
+
+    programmes
+
+}
+
 //
 // シリアル出力処理
 //
-fn dump_serial( cmd_opt: &CommanLineOpt, outfile: &mut File, mut svtcur: &mut SvtControl) -> () {
+fn dump_serial( cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, mut svtcur: &mut SvtControl) -> () {
 
     // 放送休止補正処理(EIT PF)
-    insert_rest_pf(&mut svtcur);
+    insert_rest_pf(&cmd_opt.gap_fill, &mut svtcur);
 
     if cmd_opt.eit_mode == false && svtcur.eitsch.len() > 0 {
 
         // 放送休止補正処理(EIT SCH)
-        insert_rest_sch(&mut svtcur);
+        insert_rest_sch(&cmd_opt.gap_fill, tot, &mut svtcur);
 
         // sch_pnt補正処理呼出し
         sch_pnt_update(&mut svtcur);
@@ -1110,7 +1629,7 @@ fn dump_serial( cmd_opt: &CommanLineOpt, outfile: &mut File, mut svtcur: &mut Sv
             for cnt in 0..svtcur.eit_pf.len() {
 
                 write!(outfile, "{}",
-                    line_serial(cnt as i32, 17, &mut svtcur.eit_pf[cnt], &svtcur.ontv)).unwrap();
+                    line_serial(cmd_opt, tot, cnt as i32, 17, &mut svtcur.eit_pf[cnt], &svtcur.ontv)).unwrap();
                 write!(outfile, "s:6:\"status\";i:{};s:7:\"sch_pnt\";i:{};{}",
                     svtcur.eit_pf[cnt].event_status, svtcur.eit_pf[cnt].sch_pnt, "}").unwrap();
 
@@ -1129,7 +1648,7 @@ fn dump_serial( cmd_opt: &CommanLineOpt, outfile: &mut File, mut svtcur: &mut Sv
             for cnt in 0..svtcur.eitsch.len() {
 
                 write!(outfile, "{}{}",
-                    line_serial(cnt as i32, 15, &mut svtcur.eitsch[cnt], &svtcur.ontv), "}").unwrap();
+                    line_serial(cmd_opt, tot, cnt as i32, 15, &mut svtcur.eitsch[cnt], &svtcur.ontv), "}").unwrap();
 
             }
 
@@ -1156,19 +1675,134 @@ fn xml_special_chars(xml: String) -> String {
     ret_string
 }
 
+//
+// 改行の置き換え処理(is_flattenがtrueの場合はスペースに置き換え)
+//
+pub(crate) fn newline_flatten(text: &str, is_flatten: bool) -> String {
+
+    if is_flatten {
+
+        text.replace('\n', " ")
+
+    }
+    else {
+
+        text.to_string()
+
+    }
+}
+
+//
+// ISO 639-2言語コード -> XML言語コード変換処理
+//
+fn xml_lang_code(lang: &str) -> String {
+
+    let ret_string = match lang {
+        "jpn" => "ja",
+        "eng" => "en",
+        "kor" => "ko",
+        "zho" | "chi" => "zh",
+        "fra" | "fre" => "fr",
+        "deu" | "ger" => "de",
+        "spa" => "es",
+        "ita" => "it",
+        "por" => "pt",
+        "rus" => "ru",
+        _ => lang,
+    };
+
+    // リターン情報
+    ret_string.to_string()
+}
+
+//
+// 優先言語以外の文字情報のxml出力処理
+//
+fn xml_other_lang(cmd_opt: &CommanLineOpt, outfile: &mut File, eitcur: &EitControl, tag: &str) {
+
+    // 言語別文字情報分ループ
+    for lang_text in &eitcur.lang_text {
+
+        // 優先言語はスキップ
+        if lang_text.lang == eitcur.lang {
+
+            continue;
+
+        };
+
+        // タグにより出力する文字情報を選択(説明は出力形式により詳細説明を含む)
+        let texts = match tag {
+            "title" => vec![lang_text.title.clone()],
+            _ => cmd_opt.desc_layout.desc_texts(&lang_text.subtitle, &lang_text.desc_items),
+        };
+
+        // 文字情報がある場合に出力
+        for text in texts.iter().filter(|text| !text.is_empty()) {
+
+            writeln!(outfile, "    <{} lang=\"{}\">{}</{}>",
+                tag, xml_lang_code(&lang_text.lang),
+                xml_special_chars(newline_flatten(text, cmd_opt.is_flatten_newline)), tag).unwrap();
+
+        };
+    };
+}
+
+//
+// ジャンル名称のxml出力処理
+//
+fn xml_category(outfile: &mut File, genres: &[EitGenre]) {
+
+    // 言語毎にジャンル名称を出力
+    for (lang, xml_lang) in [(GenreLang::Ja, "ja"), (GenreLang::En, "en")] {
+        for name in genre_names(genres, lang) {
+
+            writeln!(outfile, "    <category lang=\"{}\">{}</category>", xml_lang, xml_special_chars(name)).unwrap();
+
+        };
+    };
+}
+
+//
+// タイトルの記号のxml出力処理(xmltv.dtdの出力順、二か国語放送は最後)
+//
+fn xml_markers(outfile: &mut File, eitcur: &EitControl) {
+
+    for element in eitcur.xmltv_markers() {
+
+        writeln!(outfile, "    {}", element).unwrap();
+
+    };
+
+    // 二か国語放送
+    if eitcur.markers.contains(&TitleMarker::Bilingual) {
+
+        writeln!(outfile, "    <audio><stereo>bilingual</stereo></audio>").unwrap();
+
+    };
+}
+
+//
+// 補完番組属性の作成処理
+//
+fn xml_synthetic(gap_fill: &GapFill, eitcur: &EitControl) -> &'static str {
+
+    if gap_fill.is_mark && eitcur.is_synthetic() { " synthetic=\"1\"" } else { "" }
+
+}
+
 //
 // xmlデータ作成処理
 //
-fn dump_xml( cmd_opt: &CommanLineOpt, outfile: &mut File, mut svtcur: &mut SvtControl) -> () {
+fn dump_xml( cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, mut svtcur: &mut SvtControl) -> () {
 
     // 放送休止補正処理(EIT PF)
-    insert_rest_pf(&mut svtcur);
+    insert_rest_pf(&cmd_opt.gap_fill, &mut svtcur);
 
     // EITモードフラグがfalseでeitschにデータある場合の処理
     if cmd_opt.eit_mode == false && svtcur.eitsch.len() > 0 {
 
         // 放送休止補正処理(EIT SCH)
-        insert_rest_sch(&mut svtcur);
+        insert_rest_sch(&cmd_opt.gap_fill, tot, &mut svtcur);
 
         // sch_pnt補正処理呼出し
         sch_pnt_update(&mut svtcur);
@@ -1180,63 +1814,51 @@ fn dump_xml( cmd_opt: &CommanLineOpt, outfile: &mut File, mut svtcur: &mut SvtCo
             for cnt in 0..svtcur.eit_pf.len() {
 
                 // ジャンル未定義補正処理呼び出し
-                rest_repair(&mut svtcur.eit_pf[cnt]);
+                rest_repair(&cmd_opt.gap_fill, &mut svtcur.eit_pf[cnt]);
 
                 // 処理用タイトル作成
-                let mut title = svtcur.eit_pf[cnt].title.clone();
+                let mut title = newline_flatten(&svtcur.eit_pf[cnt].title, cmd_opt.is_flatten_newline);
                 title = xml_special_chars(title);
 
-                // 処理用サブタイトル作成
-                let mut subtitle = svtcur.eit_pf[cnt].subtitle.clone();
-                subtitle = xml_special_chars(subtitle);
+                // 処理用説明作成(出力形式により詳細説明を含む)
+                let descs: Vec<String> = cmd_opt.desc_layout.desc_texts(&svtcur.eit_pf[cnt].subtitle, &svtcur.eit_pf[cnt].desc_items)
+                    .iter()
+                    .map(|desc| xml_special_chars(newline_flatten(desc, cmd_opt.is_flatten_newline)))
+                    .collect();
 
                 // 処理用タグ作成
                 let tag = "programme_pf".to_string();
 
-                // 処理用開始日時作成
-                let start_time = svtcur.eit_pf[cnt].start_time;
-
-                // 処理用終了日時作成
-                let end_time = start_time + svtcur.eit_pf[cnt].duration as i64;
+                // 処理用開始日時属性作成(未定義の場合は省略)
+                let cstarttime = svtcur.eit_pf[cnt].start()
+                    .map(|start_time| format!(" start=\"{}\"", tot.format_time(start_time, cmd_opt.time_format)))
+                    .unwrap_or_default();
 
-                // 処理用終了日時(キャラクタ)作成
-                let cendtime = Local.timestamp_opt(end_time,0).unwrap().format("%Y-%m-%d %H:%M:%S");
+                // 処理用終了日時属性作成(未定義の場合は省略)
+                let cendtime = svtcur.eit_pf[cnt].end()
+                    .map(|end_time| format!(" stop=\"{}\"", tot.format_time(end_time, cmd_opt.time_format)))
+                    .unwrap_or_default();
 
-                // 処理用開始日時(キャラクタ)作成
-                let cstarttime = format!("{:4}-{:02}-{:02} {:02}:{:02}:{:02}",
-                    svtcur.eit_pf[cnt].yy + 1900, svtcur.eit_pf[cnt].mm, svtcur.eit_pf[cnt].dd,
-                    svtcur.eit_pf[cnt].hh, svtcur.eit_pf[cnt].hm, svtcur.eit_pf[cnt].ss);
-
-                // 処理用コンテンツタイプ作成
-                let content_type = match svtcur.eit_pf[cnt].content_type {
-                    16 => { 0 },
-                    _ => { svtcur.eit_pf[cnt].content_type + 1 },
-                };
+                // 処理用ジャンル(旧形式互換)作成
+                let genre = genre_compat(&svtcur.eit_pf[cnt].genres);
 
-                // 処理用genre2作成
-                let genre2 = match svtcur.eit_pf[cnt].genre2 {
-                    16 => { 0 },
-                    _ => { svtcur.eit_pf[cnt].genre2 + 1 },
-                };
-
-                // 処理用genre3作成
-                let genre3 = match svtcur.eit_pf[cnt].genre3 {
-                    16 => { 0 },
-                    _ => { svtcur.eit_pf[cnt].genre3 + 1 },
-                };
-    
                 // 出力処理
-                writeln!(outfile ,"  <{} start=\"{}\" stop=\"{}\" channel=\"{}\" eid=\"{}\">",
-                    tag, cstarttime, cendtime, svtcur.ontv, svtcur.eit_pf[cnt].event_id).unwrap();
+                writeln!(outfile ,"  <{}{}{} channel=\"{}\" eid=\"{}\"{}>",
+                    tag, cstarttime, cendtime, svtcur.ontv, svtcur.eit_pf[cnt].event_id,
+                    xml_synthetic(&cmd_opt.gap_fill, &svtcur.eit_pf[cnt])).unwrap();
                 writeln!(outfile, "    <title>{}</title>", title).unwrap();
-                writeln!(outfile, "    <desc>{}</desc>", subtitle ).unwrap();
+                xml_other_lang(cmd_opt, outfile, &svtcur.eit_pf[cnt], "title");
+                for desc in descs.iter() {
+                    writeln!(outfile, "    <desc>{}</desc>", desc).unwrap();
+                };
+                xml_other_lang(cmd_opt, outfile, &svtcur.eit_pf[cnt], "desc");
                 writeln!(outfile, "    <genres>{}:{}:{}:{}:{}:{}</genres>",
-                    content_type, svtcur.eit_pf[cnt].content_subtype,
-                    genre2, svtcur.eit_pf[cnt].sub_genre2,
-                    genre3, svtcur.eit_pf[cnt].sub_genre3).unwrap();
+                    genre[0], genre[1], genre[2], genre[3], genre[4], genre[5]).unwrap();
+                xml_category(outfile, &svtcur.eit_pf[cnt].genres);
                 writeln!(outfile, "    <video_audio>{}:{}:{}</video_audio>",
                     svtcur.eit_pf[cnt].video_type, svtcur.eit_pf[cnt].audio_type,
                     svtcur.eit_pf[cnt].multi_type).unwrap();
+                xml_markers(outfile, &svtcur.eit_pf[cnt]);
                 writeln!(outfile, "    <status>{}</status>",
                     svtcur.eit_pf[cnt].event_status).unwrap();
                 writeln!(outfile, "    <sch_pnt>{}</sch_pnt>",
@@ -1253,63 +1875,51 @@ fn dump_xml( cmd_opt: &CommanLineOpt, outfile: &mut File, mut svtcur: &mut SvtCo
             for cnt in 0..svtcur.eitsch.len() {
 
                 // ジャンル未定義補正処理呼び出し
-                rest_repair(&mut svtcur.eitsch[cnt]);
+                rest_repair(&cmd_opt.gap_fill, &mut svtcur.eitsch[cnt]);
 
                 // 処理用タイトル作成
-                let mut title = svtcur.eitsch[cnt].title.clone();
+                let mut title = newline_flatten(&svtcur.eitsch[cnt].title, cmd_opt.is_flatten_newline);
                 title = xml_special_chars(title);
 
-                // 処理用サブタイトル作成
-                let mut subtitle = svtcur.eitsch[cnt].subtitle.clone();
-                subtitle = xml_special_chars(subtitle);
+                // 処理用説明作成(出力形式により詳細説明を含む)
+                let descs: Vec<String> = cmd_opt.desc_layout.desc_texts(&svtcur.eitsch[cnt].subtitle, &svtcur.eitsch[cnt].desc_items)
+                    .iter()
+                    .map(|desc| xml_special_chars(newline_flatten(desc, cmd_opt.is_flatten_newline)))
+                    .collect();
 
                 // 処理用タグ作成
                 let tag = "programme".to_string();
 
-                // 処理用開始日時作成
-                let start_time = svtcur.eitsch[cnt].start_time;
-
-                // 処理用終了日時作成
-                let end_time = start_time + svtcur.eitsch[cnt].duration as i64;
+                // 処理用開始日時属性作成(未定義の場合は省略)
+                let cstarttime = svtcur.eitsch[cnt].start()
+                    .map(|start_time| format!(" start=\"{}\"", tot.format_time(start_time, cmd_opt.time_format)))
+                    .unwrap_or_default();
 
-                // 処理用終了日時(キャラクタ)作成
-                let cendtime = Local.timestamp_opt(end_time,0).unwrap().format("%Y-%m-%d %H:%M:%S");
+                // 処理用終了日時属性作成(未定義の場合は省略)
+                let cendtime = svtcur.eitsch[cnt].end()
+                    .map(|end_time| format!(" stop=\"{}\"", tot.format_time(end_time, cmd_opt.time_format)))
+                    .unwrap_or_default();
 
-                // 処理用開始日時(キャラクタ)作成
-                let cstarttime = format!("{:4}-{:02}-{:02} {:02}:{:02}:{:02}",
-                    svtcur.eitsch[cnt].yy + 1900, svtcur.eitsch[cnt].mm, svtcur.eitsch[cnt].dd,
-                    svtcur.eitsch[cnt].hh, svtcur.eitsch[cnt].hm, svtcur.eitsch[cnt].ss);
-
-                // 処理用コンテンツタイプ作成
-                let content_type = match svtcur.eitsch[cnt].content_type {
-                    16 => { 0 },
-                    _ => { svtcur.eitsch[cnt].content_type + 1 },
-                };
-                
-                // 処理用genre2作成
-                let genre2 = match svtcur.eitsch[cnt].genre2 {
-                    16 => { 0 },
-                    _ => { svtcur.eitsch[cnt].genre2 + 1 },
-                };
-
-                // 処理用genre3作成
-                let genre3 = match svtcur.eitsch[cnt].genre3 {
-                    16 => { 0 },
-                    _ => { svtcur.eitsch[cnt].genre3 + 1 },
-                };
+                // 処理用ジャンル(旧形式互換)作成
+                let genre = genre_compat(&svtcur.eitsch[cnt].genres);
 
                 // 出力処理
-                writeln!(outfile ,"  <{} start=\"{}\" stop=\"{}\" channel=\"{}\" eid=\"{}\">",
-                    tag, cstarttime, cendtime, svtcur.ontv, svtcur.eitsch[cnt].event_id).unwrap();
+                writeln!(outfile ,"  <{}{}{} channel=\"{}\" eid=\"{}\"{}>",
+                    tag, cstarttime, cendtime, svtcur.ontv, svtcur.eitsch[cnt].event_id,
+                    xml_synthetic(&cmd_opt.gap_fill, &svtcur.eitsch[cnt])).unwrap();
                 writeln!(outfile, "    <title>{}</title>", title).unwrap();
-                writeln!(outfile, "    <desc>{}</desc>", subtitle ).unwrap();
+                xml_other_lang(cmd_opt, outfile, &svtcur.eitsch[cnt], "title");
+                for desc in descs.iter() {
+                    writeln!(outfile, "    <desc>{}</desc>", desc).unwrap();
+                };
+                xml_other_lang(cmd_opt, outfile, &svtcur.eitsch[cnt], "desc");
                 writeln!(outfile, "    <genres>{}:{}:{}:{}:{}:{}</genres>",
-                    content_type, svtcur.eitsch[cnt].content_subtype,
-                    genre2, svtcur.eitsch[cnt].sub_genre2,
-                    genre3, svtcur.eitsch[cnt].sub_genre3).unwrap();
+                    genre[0], genre[1], genre[2], genre[3], genre[4], genre[5]).unwrap();
+                xml_category(outfile, &svtcur.eitsch[cnt].genres);
                 writeln!(outfile, "    <video_audio>{}:{}:{}</video_audio>",
                     svtcur.eitsch[cnt].video_type, svtcur.eitsch[cnt].audio_type,
                     svtcur.eitsch[cnt].multi_type).unwrap();
+                xml_markers(outfile, &svtcur.eitsch[cnt]);
                 //writeln!(outfile, "    <status>{}</status>", svtcur.eitsch[cnt].event_status);
                 //writeln!(outfile, "    <sch_pnt>{}</sch_pnt>", svtcur.eitsch[cnt].sch_pnt);
                 writeln!(outfile, "  </{}>", tag).unwrap();
@@ -1324,3 +1934,95 @@ fn dump_xml( cmd_opt: &CommanLineOpt, outfile: &mut File, mut svtcur: &mut SvtCo
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ts::tests::{event, service};
+
+    // テスト用のサービス作成処理(EIT PF、EIT SCHはイベントストアに格納)
+    fn store_service(service_id: i32, transport_stream_id: u32, pf: Vec<EitControl>, sch: Vec<EitControl>) -> SvtControlTop {
+
+        let mut svtcur = service(vec![], vec![]);
+        svtcur.service_id = service_id;
+        svtcur.transport_stream_id = transport_stream_id;
+        svtcur.import_stat = 1;
+
+        for eitcur in pf {
+
+            svtcur.pf_store.upsert(EitControl { table_id: 0x4e, servid: service_id, ..eitcur });
+
+        };
+        for eitcur in sch {
+
+            svtcur.sch_store.upsert(EitControl { servid: service_id, ..eitcur });
+
+        };
+
+        SvtControlTop {
+            service_id,
+            svt_control_sub: vec![svtcur],
+        }
+
+    }
+
+    #[test]
+    fn trust_pf_before_pf_clear() {
+
+        let cmd_opt = command_line_parse("epgdump",
+            ["epgdump", "--BS", "--overlap", "trust-pf", "-", "-"].iter().map(|arg| arg.to_string()).collect());
+
+        // 同じtransport_stream_idの2サービス(EIT SCHは延長前の時刻)
+        let base = 1_700_000_000;
+        let mut svttop: Vec<SvtControlTop> = [101, 102].iter()
+            .map(|service_id| store_service(*service_id, 0x4010,
+                vec![event(1, base, 3600, 0)],
+                vec![event(1, base, 1800, 0), event(2, base + 1800, 1800, 0)]))
+            .collect();
+
+        service_list_update(&cmd_opt, &TotState::default(), &mut svttop);
+
+        // 2つ目のサービスもEIT PFで補正した後にEIT PFを削除
+        for svt in svttop.iter() {
+
+            let svtcur = &svt.svt_control_sub[0];
+            let sch: Vec<(i32, Option<i64>, Option<i64>)> = svtcur.eitsch.iter()
+                .map(|eitcur| (eitcur.event_id, eitcur.start(), eitcur.end()))
+                .collect();
+            assert_eq!(sch, vec![(1, Some(base), Some(base + 3600))], "service_id={}", svt.service_id);
+
+        };
+        assert_eq!(svttop[0].svt_control_sub[0].eit_pf.len(), 1);
+        assert!(svttop[1].svt_control_sub[0].eit_pf.is_empty());
+
+    }
+
+    #[test]
+    fn rest_repair_gap_fill() {
+
+        let gap_fill = |args: &[&str]| command_line_parse("epgdump",
+            ["epgdump", "--BS"].iter().chain(args.iter()).chain(["-", "-"].iter()).map(|arg| arg.to_string()).collect()).gap_fill;
+
+        // 放送休止データを補完しない場合はジャンル未定義のまま
+        let mut eitcur = event(1, 1_700_000_000, 1800, 0);
+        rest_repair(&gap_fill(&["--no-gap-fill"]), &mut eitcur);
+        assert!(eitcur.genres.is_empty());
+        assert_eq!(eitcur.title, "");
+
+        // 放送休止データのジャンル、タイトルで補正
+        let mut eitcur = event(1, 1_700_000_000, 1800, 0);
+        rest_repair(&gap_fill(&["--gap-genre", "15,15", "--gap-title", "休止"]), &mut eitcur);
+        assert_eq!(eitcur.genres.len(), 1);
+        assert_eq!((eitcur.genres[0].content_nibble_level_1, eitcur.genres[0].content_nibble_level_2), (15, 15));
+        assert_eq!(eitcur.title, "休止");
+
+        // タイトルのある番組はタイトルを変更しない
+        let mut eitcur = event(1, 1_700_000_000, 1800, 0);
+        eitcur.title = "番組".to_string();
+        rest_repair(&gap_fill(&[]), &mut eitcur);
+        assert_eq!(eitcur.genres, vec![GapFill::default().genre]);
+        assert_eq!(eitcur.title, "番組");
+
+    }
+}
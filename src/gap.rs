@@ -0,0 +1,140 @@
+
+use chrono::{TimeZone};
+use chrono::prelude::{Datelike, Timelike};
+use std::collections::{BTreeSet};
+
+use crate::genre::{GENRE_REST};
+use crate::tot::{jst};
+use crate::ts::{EitControl, EitGenre};
+
+// 補完番組のイベントID
+pub const EVENT_ID_SYNTHETIC: i32 = -1;
+
+// 補完番組の既定タイトル
+pub const GAP_TITLE: &str = "放送休止";
+
+// 放送休止(番組間の空き時間)の補完設定
+#[derive(Debug, Clone)]
+pub struct GapFill {
+    pub is_fill: bool,         // 空き時間の補完有無
+    pub title: String,         // 補完番組のタイトル
+    pub genre: EitGenre,       // 補完番組のジャンル
+    pub min_gap: i64,          // 補完する最小の空き時間(秒)
+    pub is_mark: bool,         // 補完番組をXML/JSONで明示
+}
+
+impl Default for GapFill {
+
+    fn default() -> Self {
+
+        GapFill {
+            is_fill: true,
+            title: GAP_TITLE.to_string(),
+            genre: GENRE_REST,
+            min_gap: 1,
+            is_mark: false,
+        }
+
+    }
+}
+
+impl GapFill {
+
+    //
+    // 補完対象の空き時間判定処理
+    //
+    pub fn is_gap(&self, end_time: i64, start_time: i64) -> bool {
+
+        self.is_fill && start_time - end_time >= self.min_gap.max(1)
+
+    }
+
+    //
+    // 補完番組の作成処理
+    //
+    pub fn rest_event(&self, next: &EitControl, end_time: i64, start_time: i64, sch_pnt: i32) -> EitControl {
+
+        // DateTime形式の開始日時情報作成
+        let end_time_dt = jst().timestamp_opt(end_time, 0).unwrap();
+
+        EitControl {
+            table_id: next.table_id,
+            servid: next.servid,
+            event_id: EVENT_ID_SYNTHETIC,
+            version_number: 0,
+            section_number: 0,
+            last_section_number: 0,
+            segment_last_section_number: 0,
+            running_status: 0,
+            free_ca_mode: 0,
+            genres: vec![self.genre],
+            episode_number: 0,
+            yy: end_time_dt.year() - 1900,
+            mm: end_time_dt.month() as i32,
+            dd: end_time_dt.day() as i32,
+            hh: end_time_dt.hour() as i32,
+            hm: end_time_dt.minute() as i32,
+            ss: end_time_dt.second() as i32,
+            duration: (start_time - end_time) as i32,
+            start_time: end_time,
+            title: self.title.clone(),
+            marker_title: String::new(),
+            subtitle: String::new(),
+            desc: String::new(),
+            desc_items: vec![],
+            desc_length: 0,
+            video_type: 0,
+            stream_content: 0,
+            audio_type: 0,
+            multi_type: 0,
+            audios: vec![],
+            series: None,
+            related_items: vec![],
+            event_status: 0,
+            sch_pnt,
+            import_cnt: 0,
+            renew_cnt: 0,
+            tid: 0,
+            tid_status: 0,
+            lang: String::new(),
+            lang_text: vec![],
+            markers: BTreeSet::new(),
+            rating: 0,
+        }
+
+    }
+}
+
+impl EitControl {
+
+    //
+    // 補完番組の判定処理
+    //
+    pub fn is_synthetic(&self) -> bool {
+
+        self.event_id == EVENT_ID_SYNTHETIC
+
+    }
+}
+
+//
+// 補完番組のジャンル解析処理(content_nibble_1,content_nibble_2[,user_nibble_1,user_nibble_2])
+//
+pub fn gap_genre_parse(text: &str) -> Option<EitGenre> {
+
+    // 0〜15の数値に変換
+    let nibbles: Vec<i32> = text.split(',')
+        .map(|nibble| nibble.trim().parse::<i32>().ok().filter(|nibble| (0..16).contains(nibble)))
+        .collect::<Option<Vec<i32>>>()?;
+
+    match nibbles[..] {
+        [lv1, lv2] => Some(EitGenre {
+            content_nibble_level_1: lv1, content_nibble_level_2: lv2, user_nibble_1: 0, user_nibble_2: 0,
+        }),
+        [lv1, lv2, user1, user2] => Some(EitGenre {
+            content_nibble_level_1: lv1, content_nibble_level_2: lv2, user_nibble_1: user1, user_nibble_2: user2,
+        }),
+        _ => None,
+    }
+
+}
@@ -0,0 +1,373 @@
+
+use crate::ts::{EitGenre};
+
+// ジャンル拡張(content_nibble_level_1)
+pub const GENRE_EXTENSION: i32 = 0x0e;
+
+// ジャンル拡張の種別(content_nibble_level_2)
+pub const GENRE_EXT_PROGRAM_INFO: i32 = 0x00;  // BS/地上デジタル放送用番組付属情報
+pub const GENRE_EXT_CS: i32 = 0x01;            // 広帯域CSデジタル放送用拡張
+
+// 放送休止のジャンル(旧形式の 14/0x3f に相当)
+pub const GENRE_REST: EitGenre = EitGenre {
+    content_nibble_level_1: GENRE_EXTENSION,
+    content_nibble_level_2: GENRE_EXT_PROGRAM_INFO,
+    user_nibble_1: 0x03,
+    user_nibble_2: 0x0f,
+};
+
+// ジャンル種別
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GenreKind {
+    Standard(i32, i32),      // 標準ジャンル(大分類、中分類)
+    ProgramInfo(i32),        // 番組付属情報(番組特性コード)
+    CsExtension(i32, i32),   // CS拡張ジャンル(大分類、中分類)
+    Reserved,                // 未定義の拡張
+}
+
+impl EitGenre {
+
+    //
+    // ユーザーバイトの取得処理
+    //
+    pub fn user_byte(&self) -> i32 {
+
+        (self.user_nibble_1 << 4) + self.user_nibble_2
+
+    }
+
+    //
+    // ジャンル種別の判定処理
+    //
+    pub fn kind(&self) -> GenreKind {
+
+        // ジャンル拡張以外は標準ジャンル
+        if self.content_nibble_level_1 != GENRE_EXTENSION {
+
+            return GenreKind::Standard(self.content_nibble_level_1, self.content_nibble_level_2);
+
+        };
+
+        // ジャンル拡張はcontent_nibble_level_2で種別を判定し、ユーザーニブルを解釈
+        match self.content_nibble_level_2 {
+            GENRE_EXT_PROGRAM_INFO => GenreKind::ProgramInfo(self.user_byte()),
+            GENRE_EXT_CS => GenreKind::CsExtension(self.user_nibble_1, self.user_nibble_2),
+            _ => GenreKind::Reserved,
+        }
+
+    }
+}
+
+//
+// コンテント記述子のジャンル一覧作成処理
+//
+pub fn genre_parse(content: &[u8]) -> Vec<EitGenre> {
+
+    // 2バイト毎にジャンルを取得
+    content.chunks_exact(2)
+        .map(|pair| EitGenre {
+            content_nibble_level_1: (pair[0] >> 4) as i32,
+            content_nibble_level_2: (pair[0] & 0x0f) as i32,
+            user_nibble_1: (pair[1] >> 4) as i32,
+            user_nibble_2: (pair[1] & 0x0f) as i32,
+        })
+        .collect()
+
+}
+
+//
+// 放送休止ジャンルの判定処理
+//
+pub fn genre_is_rest(genres: &[EitGenre]) -> bool {
+
+    genres.first() == Some(&GENRE_REST)
+
+}
+
+//
+// 旧形式(3組のジャンル)互換情報の作成処理
+// (リターン:category、sub_genre、genre2、sub_genre2、genre3、sub_genre3 の出力値)
+//
+pub fn genre_compat(genres: &[EitGenre]) -> [i32; 6] {
+
+    // ジャンル無しの場合は全て0
+    if genres.is_empty() {
+
+        return [0; 6];
+
+    };
+
+    // 先頭3組の大分類、中分類を作成(ジャンル拡張の中分類はユーザーバイト、CS拡張は+0x40)
+    // 存在しないジャンルは16
+    let mut compat = [16; 6];
+    for (cnt, genre) in genres.iter().take(3).enumerate() {
+
+        compat[cnt * 2] = genre.content_nibble_level_1;
+        compat[cnt * 2 + 1] = match genre.kind() {
+            GenreKind::Standard(_, lv2) => lv2,
+            GenreKind::CsExtension(..) => genre.user_byte() + 0x40,
+            _ => genre.user_byte(),
+        };
+
+    };
+
+    // 先頭がジャンル拡張の場合は後続の標準ジャンルと入れ替え
+    if genres.len() > 1 && compat[0] == GENRE_EXTENSION {
+
+        if compat[2] != GENRE_EXTENSION {
+
+            compat.swap(0, 2);
+            compat.swap(1, 3);
+
+        }
+        else if compat[4] != GENRE_EXTENSION && compat[4] != 16 {
+
+            compat.swap(0, 4);
+            compat.swap(1, 5);
+
+        };
+    };
+
+    // 大分類を出力値へ変換(16は0、それ以外は+1)
+    for cnt in [0, 2, 4] {
+
+        compat[cnt] = if compat[cnt] == 16 { 0 } else { compat[cnt] + 1 };
+
+    };
+
+    // リターン情報
+    compat
+
+}
+
+// ジャンル大分類名称テーブル(ARIB STD-B10 付録H)
+const GENRE_LV1_NAME_TABLE: [(i32, &str, &str); 13] = [
+    (0x0, "ニュース／報道",          "News/Reports"),
+    (0x1, "スポーツ",                "Sports"),
+    (0x2, "情報／ワイドショー",      "Information/Tabloid shows"),
+    (0x3, "ドラマ",                  "Dramas"),
+    (0x4, "音楽",                    "Music"),
+    (0x5, "バラエティ",              "Variety shows"),
+    (0x6, "映画",                    "Movies"),
+    (0x7, "アニメ／特撮",            "Animation/Special effects"),
+    (0x8, "ドキュメンタリー／教養",  "Documentary/Culture"),
+    (0x9, "劇場／公演",              "Theatre/Public performance"),
+    (0xa, "趣味／教育",              "Hobby/Education"),
+    (0xb, "福祉",                    "Welfare"),
+    (0xf, "その他",                  "Others"),
+];
+
+// ジャンル中分類名称テーブル(ARIB STD-B10 付録H)
+const GENRE_LV2_NAME_TABLE: [(i32, i32, &str, &str); 104] = [
+    (0x0, 0x0, "定時・総合",                      "Regular/General"),
+    (0x0, 0x1, "天気",                            "Weather report"),
+    (0x0, 0x2, "特集・ドキュメント",              "Special/Documentary"),
+    (0x0, 0x3, "政治・国会",                      "Politics/National assembly"),
+    (0x0, 0x4, "経済・市況",                      "Economics/Market report"),
+    (0x0, 0x5, "海外・国際",                      "Overseas/International report"),
+    (0x0, 0x6, "解説",                            "News analysis"),
+    (0x0, 0x7, "討論・会談",                      "Discussion/Conference"),
+    (0x0, 0x8, "報道特番",                        "Special report"),
+    (0x0, 0x9, "ローカル・地域",                  "Local program"),
+    (0x0, 0xa, "交通",                            "Traffic report"),
+    (0x0, 0xf, "その他",                          "Others"),
+    (0x1, 0x0, "スポーツニュース",                "Sports news"),
+    (0x1, 0x1, "野球",                            "Baseball"),
+    (0x1, 0x2, "サッカー",                        "Soccer"),
+    (0x1, 0x3, "ゴルフ",                          "Golf"),
+    (0x1, 0x4, "その他の球技",                    "Other ball games"),
+    (0x1, 0x5, "相撲・格闘技",                    "Sumo/Combative sports"),
+    (0x1, 0x6, "オリンピック・国際大会",          "Olympics/International games"),
+    (0x1, 0x7, "マラソン・陸上・水泳",            "Marathon/Athletic sports/Swimming"),
+    (0x1, 0x8, "モータースポーツ",                "Motor sports"),
+    (0x1, 0x9, "マリン・ウィンタースポーツ",      "Marine sports/Winter sports"),
+    (0x1, 0xa, "競馬・公営競技",                  "Horse racing/Public racing"),
+    (0x1, 0xf, "その他",                          "Others"),
+    (0x2, 0x0, "芸能・ワイドショー",              "Gossip/Tabloid shows"),
+    (0x2, 0x1, "ファッション",                    "Fashion"),
+    (0x2, 0x2, "暮らし・住まい",                  "Living/Home"),
+    (0x2, 0x3, "健康・医療",                      "Health/Medical care"),
+    (0x2, 0x4, "ショッピング・通販",              "Shopping/Mail-order business"),
+    (0x2, 0x5, "グルメ・料理",                    "Gourmet/Cooking"),
+    (0x2, 0x6, "イベント",                        "Events"),
+    (0x2, 0x7, "番組紹介・お知らせ",              "Program guide/Information"),
+    (0x2, 0xf, "その他",                          "Others"),
+    (0x3, 0x0, "国内ドラマ",                      "Japanese dramas"),
+    (0x3, 0x1, "海外ドラマ",                      "Overseas dramas"),
+    (0x3, 0x2, "時代劇",                          "Period dramas"),
+    (0x3, 0xf, "その他",                          "Others"),
+    (0x4, 0x0, "国内ロック・ポップス",            "Japanese rock/Pop music"),
+    (0x4, 0x1, "海外ロック・ポップス",            "Overseas rock/Pop music"),
+    (0x4, 0x2, "クラシック・オペラ",              "Classical/Opera"),
+    (0x4, 0x3, "ジャズ・フュージョン",            "Jazz/Fusion"),
+    (0x4, 0x4, "歌謡曲・演歌",                    "Popular songs/Japanese popular songs (enka)"),
+    (0x4, 0x5, "ライブ・コンサート",              "Live concerts"),
+    (0x4, 0x6, "ランキング・リクエスト",          "Ranking/Request music"),
+    (0x4, 0x7, "カラオケ・のど自慢",              "Karaoke/Amateur singing contests"),
+    (0x4, 0x8, "民謡・邦楽",                      "Japanese ballads/Japanese traditional music"),
+    (0x4, 0x9, "童謡・キッズ",                    "Children's songs"),
+    (0x4, 0xa, "民族音楽・ワールドミュージック",  "Folk music/World music"),
+    (0x4, 0xf, "その他",                          "Others"),
+    (0x5, 0x0, "クイズ",                          "Quiz"),
+    (0x5, 0x1, "ゲーム",                          "Game"),
+    (0x5, 0x2, "トークバラエティ",                "Talk variety"),
+    (0x5, 0x3, "お笑い・コメディ",                "Comedy program"),
+    (0x5, 0x4, "音楽バラエティ",                  "Music variety"),
+    (0x5, 0x5, "旅バラエティ",                    "Tour variety"),
+    (0x5, 0x6, "料理バラエティ",                  "Cooking variety"),
+    (0x5, 0xf, "その他",                          "Others"),
+    (0x6, 0x0, "洋画",                            "Overseas movies"),
+    (0x6, 0x1, "邦画",                            "Japanese movies"),
+    (0x6, 0x2, "アニメ",                          "Animation"),
+    (0x6, 0xf, "その他",                          "Others"),
+    (0x7, 0x0, "国内アニメ",                      "Japanese animation"),
+    (0x7, 0x1, "海外アニメ",                      "Overseas animation"),
+    (0x7, 0x2, "特撮",                            "Special effects"),
+    (0x7, 0xf, "その他",                          "Others"),
+    (0x8, 0x0, "社会・時事",                      "Social/Current events"),
+    (0x8, 0x1, "歴史・紀行",                      "History/Travel record"),
+    (0x8, 0x2, "自然・動物・環境",                "Nature/Animal/Environment"),
+    (0x8, 0x3, "宇宙・科学・医学",                "Space/Science/Medical science"),
+    (0x8, 0x4, "カルチャー・伝統文化",            "Culture/Traditional culture"),
+    (0x8, 0x5, "文学・文芸",                      "Literature/Literary art"),
+    (0x8, 0x6, "スポーツ",                        "Sports"),
+    (0x8, 0x7, "ドキュメンタリー全般",            "Total documentary"),
+    (0x8, 0x8, "インタビュー・討論",              "Interview/Discussion"),
+    (0x8, 0xf, "その他",                          "Others"),
+    (0x9, 0x0, "現代劇・新劇",                    "Modern drama/Western-style drama"),
+    (0x9, 0x1, "ミュージカル",                    "Musical"),
+    (0x9, 0x2, "ダンス・バレエ",                  "Dance/Ballet"),
+    (0x9, 0x3, "落語・演芸",                      "Comic story/Entertainment"),
+    (0x9, 0x4, "歌舞伎・古典",                    "Kabuki/Classical drama"),
+    (0x9, 0xf, "その他",                          "Others"),
+    (0xa, 0x0, "旅・釣り・アウトドア",            "Trip/Fishing/Outdoor entertainment"),
+    (0xa, 0x1, "園芸・ペット・手芸",              "Gardening/Pet/Handicrafts"),
+    (0xa, 0x2, "音楽・美術・工芸",                "Music/Art/Industrial art"),
+    (0xa, 0x3, "囲碁・将棋",                      "Japanese chess (shogi) and go"),
+    (0xa, 0x4, "麻雀・パチンコ",                  "Mah-jong/Pinball games (pachinko)"),
+    (0xa, 0x5, "車・オートバイ",                  "Cars/Motorbikes"),
+    (0xa, 0x6, "コンピュータ・ＴＶゲーム",        "Computer/TV games"),
+    (0xa, 0x7, "会話・語学",                      "Conversation/Languages"),
+    (0xa, 0x8, "幼児・小学生",                    "Little children/Schoolchildren"),
+    (0xa, 0x9, "中学生・高校生",                  "Junior high school/High school students"),
+    (0xa, 0xa, "大学生・受験",                    "University students/Examinations"),
+    (0xa, 0xb, "生涯教育・資格",                  "Lifelong education/Qualifications"),
+    (0xa, 0xc, "教育問題",                        "Educational problems"),
+    (0xa, 0xf, "その他",                          "Others"),
+    (0xb, 0x0, "高齢者",                          "Old aged persons"),
+    (0xb, 0x1, "障害者",                          "Handicapped persons"),
+    (0xb, 0x2, "社会福祉",                        "Social welfare"),
+    (0xb, 0x3, "ボランティア",                    "Volunteers"),
+    (0xb, 0x4, "手話",                            "Sign language"),
+    (0xb, 0x5, "文字（字幕）",                    "Text (subtitles)"),
+    (0xb, 0x6, "音声解説",                        "Explanation on sound multiplex broadcast"),
+    (0xb, 0xf, "その他",                          "Others"),
+    (0xf, 0xf, "その他",                          "Others"),
+];
+
+// CS拡張ジャンル名称テーブル(大分類、中分類はユーザーニブル)
+const GENRE_CS_NAME_TABLE: [(i32, i32, &str, &str); 35] = [
+    (0x0, 0x0, "スポーツ(CS) - テニス",                    "Sports (CS) - Tennis"),
+    (0x0, 0x1, "スポーツ(CS) - バスケットボール",          "Sports (CS) - Basketball"),
+    (0x0, 0x2, "スポーツ(CS) - ラグビー",                  "Sports (CS) - Rugby"),
+    (0x0, 0x3, "スポーツ(CS) - アメリカンフットボール",    "Sports (CS) - American football"),
+    (0x0, 0x4, "スポーツ(CS) - ボクシング",                "Sports (CS) - Boxing"),
+    (0x0, 0x5, "スポーツ(CS) - プロレス",                  "Sports (CS) - Professional wrestling"),
+    (0x0, 0xf, "スポーツ(CS) - その他",                    "Sports (CS) - Others"),
+    (0x1, 0x0, "洋画(CS) - アクション",                    "Overseas movies (CS) - Action"),
+    (0x1, 0x1, "洋画(CS) - SF／ファンタジー",              "Overseas movies (CS) - SF/Fantasy"),
+    (0x1, 0x2, "洋画(CS) - コメディー",                    "Overseas movies (CS) - Comedy"),
+    (0x1, 0x3, "洋画(CS) - サスペンス／ミステリー",        "Overseas movies (CS) - Suspense/Mystery"),
+    (0x1, 0x4, "洋画(CS) - 恋愛／ロマンス",                "Overseas movies (CS) - Romance"),
+    (0x1, 0x5, "洋画(CS) - ホラー／スリラー",              "Overseas movies (CS) - Horror/Thriller"),
+    (0x1, 0x6, "洋画(CS) - ウエスタン",                    "Overseas movies (CS) - Western"),
+    (0x1, 0x7, "洋画(CS) - ドラマ／社会派ドラマ",          "Overseas movies (CS) - Drama/Social drama"),
+    (0x1, 0x8, "洋画(CS) - アニメーション",                "Overseas movies (CS) - Animation"),
+    (0x1, 0x9, "洋画(CS) - ドキュメンタリー",              "Overseas movies (CS) - Documentary"),
+    (0x1, 0xa, "洋画(CS) - アドベンチャー／冒険",          "Overseas movies (CS) - Adventure"),
+    (0x1, 0xb, "洋画(CS) - ミュージカル／音楽映画",        "Overseas movies (CS) - Musical/Music movie"),
+    (0x1, 0xc, "洋画(CS) - ホームドラマ",                  "Overseas movies (CS) - Home drama"),
+    (0x1, 0xf, "洋画(CS) - その他",                        "Overseas movies (CS) - Others"),
+    (0x2, 0x0, "邦画(CS) - アクション",                    "Japanese movies (CS) - Action"),
+    (0x2, 0x1, "邦画(CS) - SF／ファンタジー",              "Japanese movies (CS) - SF/Fantasy"),
+    (0x2, 0x2, "邦画(CS) - お笑い／コメディー",            "Japanese movies (CS) - Comedy"),
+    (0x2, 0x3, "邦画(CS) - サスペンス／ミステリー",        "Japanese movies (CS) - Suspense/Mystery"),
+    (0x2, 0x4, "邦画(CS) - 恋愛／ロマンス",                "Japanese movies (CS) - Romance"),
+    (0x2, 0x5, "邦画(CS) - ホラー／スリラー",              "Japanese movies (CS) - Horror/Thriller"),
+    (0x2, 0x6, "邦画(CS) - 青春／学園／アイドル",          "Japanese movies (CS) - Youth/School/Idol"),
+    (0x2, 0x7, "邦画(CS) - 任侠／時代劇",                  "Japanese movies (CS) - Yakuza/Period drama"),
+    (0x2, 0x8, "邦画(CS) - アニメーション",                "Japanese movies (CS) - Animation"),
+    (0x2, 0x9, "邦画(CS) - ドキュメンタリー",              "Japanese movies (CS) - Documentary"),
+    (0x2, 0xa, "邦画(CS) - アドベンチャー／冒険",          "Japanese movies (CS) - Adventure"),
+    (0x2, 0xb, "邦画(CS) - ミュージカル／音楽映画",        "Japanese movies (CS) - Musical/Music movie"),
+    (0x2, 0xc, "邦画(CS) - ホームドラマ",                  "Japanese movies (CS) - Home drama"),
+    (0x2, 0xf, "邦画(CS) - その他",                        "Japanese movies (CS) - Others"),
+];
+
+// ジャンル名称の言語
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GenreLang {
+    Ja,
+    En,
+}
+
+//
+// ジャンル名称の取得処理
+// (標準ジャンルは「大分類 - 中分類」、CS拡張は拡張ジャンル名称、名称が無い場合はNone)
+//
+pub fn genre_name(genre: &EitGenre, lang: GenreLang) -> Option<String> {
+
+    // 言語別名称の選択
+    let select = |ja: &str, en: &str| if lang == GenreLang::Ja { ja.to_string() } else { en.to_string() };
+
+    match genre.kind() {
+        GenreKind::Standard(lv1, lv2) => {
+
+            // 大分類名称の取得
+            let (_, lv1_ja, lv1_en) = GENRE_LV1_NAME_TABLE.iter().find(|name| name.0 == lv1)?;
+
+            // 中分類名称の取得(中分類名称が無い場合は大分類名称のみ)
+            match GENRE_LV2_NAME_TABLE.iter().find(|name| name.0 == lv1 && name.1 == lv2) {
+                Some((_, _, lv2_ja, lv2_en)) =>
+                    Some(format!("{} - {}", select(lv1_ja, lv1_en), select(lv2_ja, lv2_en))),
+                None => Some(select(lv1_ja, lv1_en)),
+            }
+
+        },
+        GenreKind::CsExtension(lv1, lv2) => {
+
+            // CS拡張ジャンル名称の取得
+            GENRE_CS_NAME_TABLE.iter()
+                .find(|name| name.0 == lv1 && name.1 == lv2)
+                .map(|(_, _, ja, en)| select(ja, en))
+
+        },
+        _ => None,
+    }
+
+}
+
+//
+// ジャンル名称一覧の作成処理(重複は除外)
+//
+pub fn genre_names(genres: &[EitGenre], lang: GenreLang) -> Vec<String> {
+
+    let mut names: Vec<String> = vec![];
+
+    // ジャンル数分ループ
+    for genre in genres {
+
+        if let Some(name) = genre_name(genre, lang) {
+
+            if !names.contains(&name) {
+
+                names.push(name);
+
+            };
+        };
+    };
+
+    // リターン情報
+    names
+
+}
@@ -0,0 +1,243 @@
+use chrono::{TimeZone, Utc};
+use std::fs::File;
+use std::io::Write;
+
+use crate::{CommanLineOpt, programme_list, sch_pnt_update};
+use crate::genre::{GenreLang, genre_names};
+use crate::tot::{TotState, jst};
+use crate::ts::{EitControl, SvtControl, SvtControlTop};
+use crate::xmltv::{DescLayout};
+
+// iCalendarの1行の最大長(オクテット、改行を除く)
+const ICS_LINE_MAX: usize = 75;
+
+// iCalendarのタイムゾーン(JST固定)
+const ICS_TZID: &str = "Asia/Tokyo";
+
+// iCalendarの出力対象の番組の条件
+#[derive(Debug, Clone, Default)]
+pub struct IcsFilter {
+    pub series_ids: Vec<i32>,    // シリーズID(指定なしの場合は全番組)
+    pub title: Option<String>,   // タイトルに含む文字列
+}
+
+impl IcsFilter {
+
+    //
+    // 出力対象の判定処理
+    //
+    fn is_match(&self, eitcur: &EitControl) -> bool {
+
+        let is_series = self.series_ids.is_empty() ||
+            eitcur.series.as_ref().is_some_and(|series| self.series_ids.contains(&series.series_id));
+        let is_title = self.title.as_ref().is_none_or(|title| eitcur.title.contains(title.as_str()));
+
+        is_series && is_title
+
+    }
+}
+
+//
+// iCalendarの文字列エスケープ処理(\、;、,、改行)
+//
+fn ics_text(text: &str) -> String {
+
+    let mut ret_string = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '\\' => ret_string.push_str("\\\\"),
+            ';'  => ret_string.push_str("\\;"),
+            ','  => ret_string.push_str("\\,"),
+            '\n' => ret_string.push_str("\\n"),
+            '\r' => {},
+            _ => ret_string.push(ch),
+        };
+    };
+
+    ret_string
+
+}
+
+//
+// iCalendarの日時(JST)作成処理
+//
+fn ics_time(timestamp: i64) -> String {
+
+    jst().timestamp_opt(timestamp, 0).unwrap().format("%Y%m%dT%H%M%S").to_string()
+
+}
+
+//
+// iCalendarの1行の折り返し処理(75オクテットで折り返し、改行はCRLF、マルチバイト文字は分割しない)
+//
+fn fold_line(line: &str) -> String {
+
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+
+    for ch in line.chars() {
+
+        // 折り返し行は先頭の空白を含めて75オクテット
+        if octets + ch.len_utf8() > ICS_LINE_MAX {
+
+            folded.push_str("\r\n ");
+            octets = 1;
+
+        };
+        folded.push(ch);
+        octets += ch.len_utf8();
+
+    };
+
+    folded.push_str("\r\n");
+
+    folded
+
+}
+
+//
+// iCalendarの1行の出力処理
+//
+fn write_line(outfile: &mut File, line: &str) {
+
+    write!(outfile, "{}", fold_line(line)).unwrap();
+
+}
+
+//
+// 番組データのVEVENT出力処理(開始時刻未定義の番組は出力しない)
+// (UIDはネットワークID、サービスID、イベントIDで作成し、再出力時に同じ予定を置き換え)
+// (SEQUENCEはEITのバージョン番号、5bitのため31の次は0に戻る)
+//
+fn ics_event(outfile: &mut File, svtcur: &SvtControl, eitcur: &EitControl, dtstamp: &str) {
+
+    let start_time = match eitcur.start() {
+        Some(start_time) => start_time,
+        None => return,
+    };
+
+    // 説明(短形式イベント記述子の説明、詳細説明の項目を空行で区切る)
+    let mut description = DescLayout::Separate.desc_texts(&eitcur.subtitle, &eitcur.desc_items);
+    description.retain(|text| !text.is_empty());
+
+    write_line(outfile, "BEGIN:VEVENT");
+    write_line(outfile, &format!("UID:{}-{}-{}@epgdump", svtcur.original_network_id, svtcur.service_id, eitcur.event_id));
+    write_line(outfile, &format!("DTSTAMP:{}", dtstamp));
+    write_line(outfile, &format!("SEQUENCE:{}", eitcur.version_number));
+    write_line(outfile, &format!("DTSTART;TZID={}:{}", ICS_TZID, ics_time(start_time)));
+    if let Some(end_time) = eitcur.end() {
+        write_line(outfile, &format!("DTEND;TZID={}:{}", ICS_TZID, ics_time(end_time)));
+    };
+    write_line(outfile, &format!("SUMMARY:{}", ics_text(&eitcur.title)));
+    if !description.is_empty() {
+        write_line(outfile, &format!("DESCRIPTION:{}", ics_text(&description.join("\n\n"))));
+    };
+    write_line(outfile, &format!("LOCATION:{}", ics_text(&svtcur.servicename)));
+
+    // ジャンル
+    let genres: Vec<String> = genre_names(&eitcur.genres, GenreLang::Ja).iter().map(|name| ics_text(name)).collect();
+    if !genres.is_empty() {
+        write_line(outfile, &format!("CATEGORIES:{}", genres.join(",")));
+    };
+    write_line(outfile, "END:VEVENT");
+
+}
+
+//
+// サービス毎の番組データのVEVENT出力処理
+// (放送休止データは挿入せず、EIT SCHにEIT PFの番組を補って開始時刻順に出力)
+//
+fn ics_service(cmd_opt: &CommanLineOpt, outfile: &mut File, svtcur: &mut SvtControl, dtstamp: &str) {
+
+    // EITモードフラグがfalseでeitschにデータある場合の処理
+    if !cmd_opt.eit_mode && !svtcur.eitsch.is_empty() {
+
+        // sch_pnt補正処理呼出し
+        sch_pnt_update(svtcur);
+
+    };
+
+    // 出力対象の番組作成(EIT SCHに無いEIT PFの番組を追加)
+    for eitcur in programme_list(cmd_opt, svtcur).iter().filter(|eitcur| !eitcur.is_synthetic() && cmd_opt.ics_filter.is_match(eitcur)) {
+
+        ics_event(outfile, svtcur, eitcur, dtstamp);
+
+    };
+}
+
+//
+// iCalendarデータ作成処理
+//
+pub fn dump_ics(cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, svttop: &mut [SvtControlTop]) {
+
+    // 作成日時(TOTの現在時刻、未受信の場合はシステム時刻)
+    let dtstamp = Utc.timestamp_opt(tot.current_time.unwrap_or(Utc::now().timestamp()), 0).unwrap()
+        .format("%Y%m%dT%H%M%SZ").to_string();
+
+    // ヘッダー出力
+    write_line(outfile, "BEGIN:VCALENDAR");
+    write_line(outfile, "VERSION:2.0");
+    write_line(outfile, "PRODID:-//epgdump//EPG//JA");
+    write_line(outfile, "CALSCALE:GREGORIAN");
+
+    // タイムゾーン出力(JSTは夏時間なし)
+    write_line(outfile, "BEGIN:VTIMEZONE");
+    write_line(outfile, &format!("TZID:{}", ICS_TZID));
+    write_line(outfile, "BEGIN:STANDARD");
+    write_line(outfile, "DTSTART:19700101T000000");
+    write_line(outfile, "TZOFFSETFROM:+0900");
+    write_line(outfile, "TZOFFSETTO:+0900");
+    write_line(outfile, "TZNAME:JST");
+    write_line(outfile, "END:STANDARD");
+    write_line(outfile, "END:VTIMEZONE");
+
+    // 番組出力
+    for svt in svttop.iter_mut() {
+
+        ics_service(cmd_opt, outfile, &mut svt.svt_control_sub[0], &dtstamp);
+
+    };
+
+    // フッター出力
+    write_line(outfile, "END:VCALENDAR");
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn fold_line_octets() {
+
+        // 75オクテット以下は折り返さない
+        let line = "a".repeat(75);
+        assert_eq!(fold_line(&line), format!("{}\r\n", line));
+
+        // 2行目以降は先頭の空白を含めて75オクテット
+        let line = format!("SUMMARY:{}", "a".repeat(150));
+        let folded = fold_line(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= ICS_LINE_MAX));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+
+    }
+
+    #[test]
+    fn fold_line_multibyte() {
+
+        // 3オクテットの文字は分割せずに折り返し
+        let line = format!("SUMMARY:{}", "番組".repeat(30));
+        let folded = fold_line(&line);
+        let parts: Vec<&str> = folded.trim_end_matches("\r\n").split("\r\n").collect();
+
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() <= ICS_LINE_MAX));
+        assert_eq!(parts[0].len(), 8 + 3 * 22);
+        assert!(parts[1..].iter().all(|part| part.starts_with(' ')));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+
+    }
+
+}
@@ -0,0 +1,193 @@
+
+use std::fs::File;
+use std::io::Write;
+
+use crate::{CommanLineOpt, newline_flatten, programme_repair};
+use crate::genre::{GenreKind, GenreLang, genre_name};
+use crate::tot::{TotState};
+use crate::ts::{EitControl, SvtControl, SvtControlTop};
+
+//
+// jsonキャラクタ変換処理
+//
+pub fn json_special_chars(text: &str) -> String {
+
+    let mut ret_string = String::with_capacity(text.len() + 2);
+
+    // 1文字づつエスケープ
+    for ch in text.chars() {
+        match ch {
+            '"'  => ret_string.push_str("\\\""),
+            '\\' => ret_string.push_str("\\\\"),
+            '\n' => ret_string.push_str("\\n"),
+            '\r' => ret_string.push_str("\\r"),
+            '\t' => ret_string.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => ret_string.push_str(&format!("\\u{:04x}", ch as u32)),
+            _ => ret_string.push(ch),
+        };
+    };
+
+    // リターン情報
+    ret_string
+}
+
+//
+// チャンネルデータのjson作成処理(record_typeはNDJSONのレコード種別、従来形式はNone)
+//
+pub fn json_channel(svtcur: &SvtControl, record_type: Option<&str>) -> String {
+
+    // NDJSONの場合はレコード種別を付加
+    let record = match record_type {
+        Some(record_type) => format!("\"type\":\"{}\",", record_type),
+        None => String::new(),
+    };
+
+    format!("{{{}\"id\":\"{}\",\"display-name\":\"{}\",\"ts\":{},\"on\":{},\"sv\":{},\"st\":{}}}",
+        record, json_special_chars(&svtcur.ontv), json_special_chars(&svtcur.servicename),
+        svtcur.transport_stream_id, svtcur.original_network_id, svtcur.service_id, svtcur.service_type)
+
+}
+
+//
+// 番組データのjson作成処理(record_typeはNDJSONのレコード種別、従来形式はNone)
+//
+pub fn json_programme(cmd_opt: &CommanLineOpt, tot: &TotState, eitcur: &EitControl, ontv: &str, pf_flg: bool, record_type: Option<&str>) -> String {
+
+    // NDJSONの場合はレコード種別、バージョンを付加
+    let record = match record_type {
+        Some(record_type) => format!("\"type\":\"{}\",\"version\":{},", record_type, eitcur.version_number),
+        None => String::new(),
+    };
+
+    // 処理用日時作成(未定義の場合はnull)
+    let json_time = |time: Option<i64>| match time {
+        Some(time) => format!("\"{}\"", tot.format_time(time, cmd_opt.time_format)),
+        None => "null".to_string(),
+    };
+    let cstarttime = json_time(eitcur.start());
+    let cendtime = json_time(eitcur.end());
+
+    // 処理用ジャンル作成(ジャンル拡張は種別、拡張ジャンルを付加)
+    let genres: Vec<String> = eitcur.genres.iter()
+        .map(|genre| {
+            let mut ext = match genre.kind() {
+                GenreKind::Standard(..) => String::new(),
+                GenreKind::ProgramInfo(info) => format!(",\"ext\":\"program_info\",\"info\":{}", info),
+                GenreKind::CsExtension(lv1, lv2) => format!(",\"ext\":\"cs\",\"lv1\":{},\"lv2\":{}", lv1, lv2),
+                GenreKind::Reserved => ",\"ext\":\"reserved\"".to_string(),
+            };
+
+            // ジャンル名称出力の場合は名称を付加
+            if cmd_opt.is_genre_name {
+                if let (Some(name_ja), Some(name_en)) = (genre_name(genre, GenreLang::Ja), genre_name(genre, GenreLang::En)) {
+                    ext.push_str(&format!(",\"name_ja\":\"{}\",\"name_en\":\"{}\"",
+                        json_special_chars(&name_ja), json_special_chars(&name_en)));
+                };
+            };
+
+            format!("{{\"content_nibble_1\":{},\"content_nibble_2\":{},\"user_nibble_1\":{},\"user_nibble_2\":{}{}}}",
+                genre.content_nibble_level_1, genre.content_nibble_level_2,
+                genre.user_nibble_1, genre.user_nibble_2, ext)
+        })
+        .collect();
+
+    // 文字情報のjson変換(改行の置き換えを含む)
+    let json_text = |text: &str| json_special_chars(&newline_flatten(text, cmd_opt.is_flatten_newline));
+
+    // 優先言語以外の文字情報作成
+    let lang_text: Vec<String> = eitcur.lang_text.iter()
+        .filter(|lang_text| lang_text.lang != eitcur.lang)
+        .map(|lang_text| format!("{{\"lang\":\"{}\",\"title\":\"{}\",\"desc\":\"{}\",\"extended\":\"{}\"}}",
+            json_special_chars(&lang_text.lang), json_text(&lang_text.title),
+            json_text(&lang_text.subtitle), json_text(&lang_text.desc)))
+        .collect();
+
+    // タイトルの記号作成
+    let markers: Vec<String> = eitcur.markers.iter()
+        .map(|marker| format!("\"{}\"", marker.name()))
+        .collect();
+
+    // 補完番組明示の場合はsyntheticを付加
+    let synthetic = if cmd_opt.gap_fill.is_mark {
+        format!(",\"synthetic\":{}", eitcur.is_synthetic())
+    }
+    else {
+        String::new()
+    };
+
+    // リターン情報
+    format!("{{{}\"channel\":\"{}\",\"eid\":{},\"pf\":{}{},\"start\":{},\"stop\":{},\
+        \"lang\":\"{}\",\"title\":\"{}\",\"desc\":\"{}\",\"extended\":\"{}\",\
+        \"genres\":[{}],\"markers\":[{}],\
+        \"video_type\":{},\"audio_type\":{},\"multi_type\":{},\
+        \"lang_text\":[{}]}}",
+        record, json_special_chars(ontv), eitcur.event_id, pf_flg, synthetic, cstarttime, cendtime,
+        json_special_chars(&eitcur.lang), json_text(&eitcur.title),
+        json_text(&eitcur.subtitle), json_text(&eitcur.desc),
+        genres.join(","), markers.join(","),
+        eitcur.video_type, eitcur.audio_type, eitcur.multi_type,
+        lang_text.join(","))
+
+}
+
+//
+// サービス毎の番組データ作成処理
+//
+fn json_service(cmd_opt: &CommanLineOpt, tot: &TotState, svtcur: &mut SvtControl, programmes: &mut Vec<String>) {
+
+    // 番組データ補正処理
+    programme_repair(cmd_opt, tot, svtcur);
+
+    // eit_pf配列分ループ
+    for eitcur in svtcur.eit_pf.iter() {
+
+        programmes.push(json_programme(cmd_opt, tot, eitcur, &svtcur.ontv, true, None));
+
+    };
+
+    // EITモードフラグがfalseの場合にeitsch配列分ループ
+    if !cmd_opt.eit_mode {
+        for eitcur in svtcur.eitsch.iter() {
+
+            programmes.push(json_programme(cmd_opt, tot, eitcur, &svtcur.ontv, false, None));
+
+        };
+    };
+}
+
+//
+// jsonデータ作成処理(--json、--json-mode legacyの従来形式、ジャンル名称は--genre-names)
+//
+pub fn dump_json(cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, svttop: &mut [SvtControlTop]) {
+
+    let mut channels: Vec<String> = vec![];
+    let mut programmes: Vec<String> = vec![];
+
+    // svttop配列分ループ
+    for svt in svttop.iter_mut() {
+
+        let svtcur = &mut svt.svt_control_sub[0];
+
+        // チャンネル情報作成
+        channels.push(json_channel(svtcur, None));
+
+        // 番組情報作成
+        json_service(cmd_opt, tot, svtcur, &mut programmes);
+
+    };
+
+    // 出力処理
+    writeln!(outfile, "{{").unwrap();
+    writeln!(outfile, "  \"channels\": [").unwrap();
+    if !channels.is_empty() {
+        writeln!(outfile, "    {}", channels.join(",\n    ")).unwrap();
+    };
+    writeln!(outfile, "  ],").unwrap();
+    writeln!(outfile, "  \"programmes\": [").unwrap();
+    if !programmes.is_empty() {
+        writeln!(outfile, "    {}", programmes.join(",\n    ")).unwrap();
+    };
+    writeln!(outfile, "  ]").unwrap();
+    writeln!(outfile, "}}").unwrap();
+
+}
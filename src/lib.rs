@@ -0,0 +1,11 @@
+// ARIB STD-B24の8単位符号の文字列変換ライブラリ(epgdumpと共通)
+// (arib:復号 encoder:符号化 drcs:DRCS(外字) symbol:追加記号 width:文字幅の正規化)
+
+pub mod arib;
+pub mod drcs;
+pub mod encoder;
+pub mod symbol;
+pub mod width;
+
+pub use crate::arib::{AribDecoder, DecodeError, DecodeErrorKind, TextDecoder, arib_to_string, arib_to_string_checked};
+pub use crate::encoder::{AribEncoder, string_to_arib};
@@ -0,0 +1,149 @@
+
+use log::{debug};
+use std::sync::{Arc};
+
+use crate::arib::{AribDecoder, TextDecoder};
+use crate::symbol::{SymbolProfile, SymbolTable};
+use crate::ts::{EitControl, SvtControl};
+use crate::width::{TextWidth};
+
+// タイトルの記号(番組の属性)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TitleMarker {
+    New,        // [新] 新番組
+    Premiere,   // [初] 初回放送
+    Rerun,      // [再] 再放送
+    Final,      // [終] 最終回
+    Live,       // [生] 生放送
+    Caption,    // [字] 字幕放送
+    Bilingual,  // [二] 二か国語放送
+    Data,       // [デ] データ放送
+    Free,       // [無料] 無料放送
+}
+
+// タイトルの記号の表記(括弧付きの文字列、Unicode 5.2の囲み文字)
+const TITLE_MARKER_TABLE: [(TitleMarker, &[&str]); 9] = [
+    (TitleMarker::New,       &["[新]", "\u{1f21f}"]),
+    (TitleMarker::Premiere,  &["[初]", "\u{1f220}"]),
+    (TitleMarker::Rerun,     &["[再]", "\u{1f21e}"]),
+    (TitleMarker::Final,     &["[終]", "\u{1f221}"]),
+    (TitleMarker::Live,      &["[生]", "\u{1f222}"]),
+    (TitleMarker::Caption,   &["[字]", "\u{1f211}"]),
+    (TitleMarker::Bilingual, &["[二]", "\u{1f214}"]),
+    (TitleMarker::Data,      &["[デ]", "\u{1f213}"]),
+    (TitleMarker::Free,      &["[無料]", "[無][料]", "\u{1f21a}\u{1f21b}"]),
+];
+
+// XMLTVの番組属性の要素(xmltv.dtdの出力順、二か国語放送は音声の要素で出力)
+const XMLTV_MARKER_TABLE: [(TitleMarker, &str); 5] = [
+    (TitleMarker::Rerun,    "<previously-shown/>"),
+    (TitleMarker::Premiere, "<premiere/>"),
+    (TitleMarker::Final,    "<last-chance/>"),
+    (TitleMarker::New,      "<new/>"),
+    (TitleMarker::Caption,  "<subtitles type=\"teletext\"/>"),
+];
+
+impl TitleMarker {
+
+    //
+    // 記号名の取得処理
+    //
+    pub fn name(&self) -> &'static str {
+
+        match self {
+            TitleMarker::New => "new",
+            TitleMarker::Premiere => "premiere",
+            TitleMarker::Rerun => "rerun",
+            TitleMarker::Final => "final",
+            TitleMarker::Live => "live",
+            TitleMarker::Caption => "caption",
+            TitleMarker::Bilingual => "bilingual",
+            TitleMarker::Data => "data",
+            TitleMarker::Free => "free",
+        }
+
+    }
+}
+
+//
+// 記号判定用のタイトルの文字コード変換処理
+// (追加記号は出力形式、対応表によらずbracket形式、文字幅は放送のままで変換)
+//
+pub fn marker_title(data: &[u8], length: i32) -> String {
+
+    AribDecoder::new()
+        .with_symbols(Arc::new(SymbolTable::new(SymbolProfile::Bracket)))
+        .with_width(TextWidth::Raw)
+        .decode(data, length).1
+
+}
+
+impl EitControl {
+
+    //
+    // タイトルの記号のXMLTVの要素の取得処理(xmltv.dtdの出力順)
+    //
+    pub fn xmltv_markers(&self) -> Vec<&'static str> {
+
+        XMLTV_MARKER_TABLE.iter()
+            .filter(|(marker, _)| self.markers.contains(marker))
+            .map(|(_, element)| *element)
+            .collect()
+
+    }
+
+    //
+    // タイトルの記号の解析処理(is_stripがtrueの場合はタイトルから記号を削除)
+    // (出力用のタイトルと記号判定用のタイトルの両方から判定し、追加記号の出力形式、DRCSの対応表によらず記号を取得)
+    //
+    pub fn marker_parse(&mut self, is_strip: bool) {
+
+        self.markers.clear();
+
+        for (marker, texts) in TITLE_MARKER_TABLE.iter() {
+
+            for text in texts.iter() {
+
+                if self.marker_title.contains(text) {
+
+                    self.markers.insert(*marker);
+
+                };
+
+                if self.title.contains(text) {
+
+                    self.markers.insert(*marker);
+
+                    if is_strip {
+
+                        self.title = self.title.replace(text, "");
+
+                    };
+                };
+            };
+        };
+
+        // 記号を削除した場合は前後の空白を削除
+        if is_strip && !self.markers.is_empty() {
+
+            self.title = self.title.trim_matches([' ', '　']).to_string();
+
+        };
+
+    }
+}
+
+//
+// サービスの全番組のタイトルの記号の解析処理
+//
+pub fn marker_parse(svtcur: &mut SvtControl, is_strip: bool) {
+
+    for eitcur in svtcur.eit_pf.iter_mut().chain(svtcur.eitsch.iter_mut()) {
+
+        eitcur.marker_parse(is_strip);
+
+    };
+
+    debug!("marker_parse service_id={} is_strip={}", svtcur.service_id, is_strip);
+
+}
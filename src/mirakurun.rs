@@ -0,0 +1,313 @@
+use std::fs::File;
+use std::io::Write;
+
+use crate::{CommanLineOpt, newline_flatten, programme_list, sch_pnt_update};
+use crate::json::{json_special_chars};
+use crate::ts::{EitAudio, EitControl, EitDescItem, EitRelatedItem, EitSeries, SvtControl, SvtControlTop};
+
+// JSONの出力形式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JsonMode {
+    Legacy,     // 従来形式(channels、programmes)
+    Mirakurun,  // Mirakurunの/api/services、/api/programs形式(services、programs)
+}
+
+// Mirakurunの音声の言語コード(対応表に無い言語はetc)
+const MIRAKURUN_LANG_CODES: [&str; 9] = ["jpn", "eng", "deu", "fra", "ita", "rus", "zho", "kor", "spa"];
+
+//
+// MirakurunのID作成処理(ネットワークID、サービスID、イベントIDを10進数5桁毎に連結)
+//
+fn mirakurun_id(ids: &[i32]) -> i64 {
+
+    ids.iter().fold(0, |id, part| id * 100000 + *part as i64)
+
+}
+
+//
+// 映像の符号化方式の取得処理(コンポーネント内容)
+//
+fn video_type(stream_content: i32) -> Option<&'static str> {
+
+    match stream_content {
+        0x01 => Some("mpeg2"),
+        0x05 => Some("h.264"),
+        0x09 => Some("h.265"),
+        _ => None,
+    }
+
+}
+
+//
+// 映像の解像度の取得処理(コンポーネント種別の上位4ビット)
+//
+fn video_resolution(component_type: i32) -> Option<&'static str> {
+
+    match (component_type & 0xf0) >> 4 {
+        0x00 => Some("480i"),
+        0x08 => Some("4320p"),
+        0x09 => Some("2160p"),
+        0x0a => Some("480p"),
+        0x0b => Some("1080i"),
+        0x0c => Some("720p"),
+        0x0d => Some("240p"),
+        0x0e => Some("1080p"),
+        0x0f => Some("180p"),
+        _ => None,
+    }
+
+}
+
+//
+// 音声のサンプリング周波数の取得処理(予約の場合は-1)
+//
+fn sampling_rate(sampling_rate: i32) -> i32 {
+
+    match sampling_rate {
+        1 => 16000,
+        2 => 22050,
+        3 => 24000,
+        5 => 32000,
+        6 => 44100,
+        7 => 48000,
+        _ => -1,
+    }
+
+}
+
+//
+// 関連イベントの種別の取得処理(イベントグループ記述子のグループ種別)
+//
+fn related_item_type(group_type: i32) -> Option<&'static str> {
+
+    match group_type {
+        1 => Some("shared"),
+        2 | 4 => Some("relay"),
+        3 | 5 => Some("movement"),
+        _ => None,
+    }
+
+}
+
+//
+// 詳細説明のjson作成処理(項目名をキーとするオブジェクト)
+//
+fn json_extended(items: &[EitDescItem], json_text: &dyn Fn(&str) -> String) -> String {
+
+    let mut extended: Vec<(String, String)> = vec![];
+
+    for item in items.iter() {
+
+        // 項目名が無い項目は前の項目に連結
+        if item.name.is_empty() {
+            if let Some((_, text)) = extended.last_mut() {
+
+                text.push_str(&item.text);
+                continue;
+
+            };
+        };
+
+        // 同じ項目名の項目は改行で連結
+        match extended.iter_mut().find(|(name, _)| *name == item.name) {
+            Some((_, text)) => text.push_str(&format!("\n{}", item.text)),
+            None => extended.push((item.name.clone(), item.text.clone())),
+        };
+    };
+
+    extended.iter()
+        .map(|(name, text)| format!("\"{}\":\"{}\"", json_text(name), json_text(text)))
+        .collect::<Vec<String>>()
+        .join(",")
+
+}
+
+//
+// 音声のjson作成処理
+//
+fn json_audio(audio: &EitAudio) -> String {
+
+    let langs: Vec<String> = audio.langs.iter()
+        .map(|lang| {
+            let lang = lang.to_lowercase();
+            format!("\"{}\"", if MIRAKURUN_LANG_CODES.contains(&lang.as_str()) { lang.as_str() } else { "etc" })
+        })
+        .collect();
+
+    format!("{{\"componentType\":{},\"componentTag\":{},\"isMain\":{},\"samplingRate\":{},\"langs\":[{}]}}",
+        audio.component_type, audio.component_tag, audio.is_main, sampling_rate(audio.sampling_rate), langs.join(","))
+
+}
+
+//
+// シリーズのjson作成処理(有効期限未定義の場合は-1)
+//
+fn json_series(series: &EitSeries, json_text: &dyn Fn(&str) -> String) -> String {
+
+    format!("{{\"id\":{},\"repeat\":{},\"pattern\":{},\"expiresAt\":{},\"episode\":{},\"lastEpisode\":{},\"name\":\"{}\"}}",
+        series.series_id, series.repeat_label, series.program_pattern,
+        series.expire_date.map(|expire_date| expire_date * 1000).unwrap_or(-1),
+        series.episode_number, series.last_episode_number, json_text(&series.name))
+
+}
+
+//
+// 関連イベントのjson作成処理(他ネットワークの場合はnetworkIdを付加)
+//
+fn json_related_item(item: &EitRelatedItem) -> Option<String> {
+
+    let item_type = related_item_type(item.group_type)?;
+    let network_id = item.network_id
+        .map(|network_id| format!(",\"networkId\":{}", network_id))
+        .unwrap_or_default();
+
+    Some(format!("{{\"type\":\"{}\"{},\"serviceId\":{},\"eventId\":{}}}",
+        item_type, network_id, item.service_id, item.event_id))
+
+}
+
+//
+// 番組データのMirakurun形式のjson作成処理(開始時刻未定義の番組は出力しない)
+//
+fn mirakurun_program(cmd_opt: &CommanLineOpt, eitcur: &EitControl, svtcur: &SvtControl) -> Option<String> {
+
+    let start_time = eitcur.start()?;
+
+    // 処理用時間長作成(未定義の場合はMirakurunと同じく1ミリ秒)
+    let duration = match eitcur.end() {
+        Some(end_time) => (end_time - start_time) * 1000,
+        None => 1,
+    };
+
+    // 文字情報のjson変換(改行の置き換えを含む)
+    let json_text = |text: &str| json_special_chars(&newline_flatten(text, cmd_opt.is_flatten_newline));
+
+    let mut program = format!("{{\"id\":{},\"eventId\":{},\"serviceId\":{},\"networkId\":{},\
+        \"startAt\":{},\"duration\":{},\"isFree\":{},\"name\":\"{}\"",
+        mirakurun_id(&[svtcur.original_network_id, svtcur.service_id, eitcur.event_id]),
+        eitcur.event_id, svtcur.service_id, svtcur.original_network_id,
+        start_time * 1000, duration, eitcur.free_ca_mode == 0, json_text(&eitcur.title));
+
+    // 説明
+    if !eitcur.subtitle.is_empty() {
+
+        program.push_str(&format!(",\"description\":\"{}\"", json_text(&eitcur.subtitle)));
+
+    };
+
+    // 詳細説明
+    if !eitcur.desc_items.is_empty() {
+
+        program.push_str(&format!(",\"extended\":{{{}}}", json_extended(&eitcur.desc_items, &json_text)));
+
+    };
+
+    // ジャンル
+    if !eitcur.genres.is_empty() {
+
+        let genres: Vec<String> = eitcur.genres.iter()
+            .map(|genre| format!("{{\"lv1\":{},\"lv2\":{},\"un1\":{},\"un2\":{}}}",
+                genre.content_nibble_level_1, genre.content_nibble_level_2, genre.user_nibble_1, genre.user_nibble_2))
+            .collect();
+        program.push_str(&format!(",\"genres\":[{}]", genres.join(",")));
+
+    };
+
+    // 映像(符号化方式、解像度が不明の場合はnull)
+    if eitcur.stream_content != 0 {
+
+        let json_str = |text: Option<&str>| text.map(|text| format!("\"{}\"", text)).unwrap_or("null".to_string());
+        program.push_str(&format!(",\"video\":{{\"type\":{},\"resolution\":{},\"streamContent\":{},\"componentType\":{}}}",
+            json_str(video_type(eitcur.stream_content)), json_str(video_resolution(eitcur.video_type)),
+            eitcur.stream_content, eitcur.video_type));
+
+    };
+
+    // 音声
+    if !eitcur.audios.is_empty() {
+
+        let audios: Vec<String> = eitcur.audios.iter().map(json_audio).collect();
+        program.push_str(&format!(",\"audios\":[{}]", audios.join(",")));
+
+    };
+
+    // シリーズ
+    if let Some(series) = &eitcur.series {
+
+        program.push_str(&format!(",\"series\":{}", json_series(series, &json_text)));
+
+    };
+
+    // 関連イベント
+    let related_items: Vec<String> = eitcur.related_items.iter().filter_map(json_related_item).collect();
+    if !related_items.is_empty() {
+
+        program.push_str(&format!(",\"relatedItems\":[{}]", related_items.join(",")));
+
+    };
+
+    program.push('}');
+
+    // リターン情報
+    Some(program)
+
+}
+
+//
+// サービス毎の番組データのMirakurun形式のjson作成処理
+// (Mirakurunに無い放送休止データは挿入せず、EIT SCHにEIT PFの番組を補って開始時刻順に出力)
+//
+fn mirakurun_service(cmd_opt: &CommanLineOpt, svtcur: &mut SvtControl, programs: &mut Vec<String>) {
+
+    // EITモードフラグがfalseでeitschにデータある場合の処理
+    if !cmd_opt.eit_mode && !svtcur.eitsch.is_empty() {
+
+        // sch_pnt補正処理呼出し
+        sch_pnt_update(svtcur);
+
+    };
+
+    // 出力対象の番組作成(EIT SCHに無いEIT PFの番組を追加)
+    programs.extend(programme_list(cmd_opt, svtcur).iter().filter_map(|eitcur| mirakurun_program(cmd_opt, eitcur, svtcur)));
+
+}
+
+//
+// Mirakurun形式のjsonデータ作成処理
+//
+pub fn dump_mirakurun(cmd_opt: &CommanLineOpt, outfile: &mut File, svttop: &mut [SvtControlTop]) {
+
+    let mut services: Vec<String> = vec![];
+    let mut programs: Vec<String> = vec![];
+
+    // svttop配列分ループ
+    for svt in svttop.iter_mut() {
+
+        let svtcur = &mut svt.svt_control_sub[0];
+
+        // サービス情報作成
+        services.push(format!("{{\"id\":{},\"serviceId\":{},\"networkId\":{},\"transportStreamId\":{},\"name\":\"{}\",\"type\":{}}}",
+            mirakurun_id(&[svtcur.original_network_id, svtcur.service_id]),
+            svtcur.service_id, svtcur.original_network_id, svtcur.transport_stream_id,
+            json_special_chars(&svtcur.servicename), svtcur.service_type));
+
+        // 番組情報作成
+        mirakurun_service(cmd_opt, svtcur, &mut programs);
+
+    };
+
+    // 出力処理
+    writeln!(outfile, "{{").unwrap();
+    writeln!(outfile, "  \"services\": [").unwrap();
+    if !services.is_empty() {
+        writeln!(outfile, "    {}", services.join(",\n    ")).unwrap();
+    };
+    writeln!(outfile, "  ],").unwrap();
+    writeln!(outfile, "  \"programs\": [").unwrap();
+    if !programs.is_empty() {
+        writeln!(outfile, "    {}", programs.join(",\n    ")).unwrap();
+    };
+    writeln!(outfile, "  ]").unwrap();
+    writeln!(outfile, "}}").unwrap();
+
+}
@@ -0,0 +1,164 @@
+use log::{debug};
+use std::collections::{HashMap};
+use std::collections::hash_map::{DefaultHasher};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+
+use crate::{CommanLineOpt, pf_service_flags, programme_repair};
+use crate::json::{json_channel, json_programme};
+use crate::tot::{TotState};
+use crate::ts::{EitControl, SvtControl, SvtControlTop};
+
+// NDJSON出力構造体(出力済みのチャンネル、イベントのレコードのハッシュを管理)
+// (逐次出力後に番組重複補正、放送休止補正などで内容が変わったレコードは受信終了時に再出力)
+// (補正で削除されたイベントは取り消しのレコードを出力しないため、最後に出力したレコードが残る)
+pub struct NdjsonWriter {
+    outfile: File,
+    is_stream: bool,                                   // 受信中の逐次出力(標準入力、パイプの場合)
+    channels: HashMap<(i32, i32), u64>,                // 出力済みチャンネル(オリジナルネットワークID, サービスID) -> ハッシュ
+    programmes: HashMap<(bool, i32, i32), u64>,        // 出力済みイベント(EIT PFフラグ, サービスID, イベントID) -> ハッシュ
+}
+
+//
+// レコードのハッシュ作成処理
+//
+fn record_hash(record: &str) -> u64 {
+
+    let mut hasher = DefaultHasher::new();
+    record.hash(&mut hasher);
+
+    hasher.finish()
+
+}
+
+impl NdjsonWriter {
+
+    //
+    // NDJSON出力の作成処理
+    //
+    pub fn new(outfile: File, is_stream: bool) -> NdjsonWriter {
+
+        NdjsonWriter {
+            outfile,
+            is_stream,
+            channels: HashMap::new(),
+            programmes: HashMap::new(),
+        }
+
+    }
+
+    //
+    // チャンネルレコードの出力処理(同じ内容を出力済みの場合は出力しない)
+    //
+    fn write_channel(&mut self, svtcur: &SvtControl) {
+
+        let record = json_channel(svtcur, Some("channel"));
+        let hash = record_hash(&record);
+
+        if self.channels.insert((svtcur.original_network_id, svtcur.service_id), hash) != Some(hash) {
+
+            writeln!(self.outfile, "{}", record).unwrap();
+
+        };
+
+    }
+
+    //
+    // 番組レコードの出力処理(同じ内容を出力済みの場合は出力しない、補完番組は常に出力)
+    //
+    fn write_programme(&mut self, cmd_opt: &CommanLineOpt, tot: &TotState, svtcur: &SvtControl, eitcur: &EitControl, pf_flg: bool) {
+
+        let record = json_programme(cmd_opt, tot, eitcur, &svtcur.ontv, pf_flg, Some("programme"));
+        let hash = record_hash(&record);
+
+        if !eitcur.is_synthetic() &&
+            self.programmes.insert((pf_flg, eitcur.servid, eitcur.event_id), hash) == Some(hash) {
+
+            return;
+
+        };
+
+        // チャンネルレコードを先に出力
+        self.write_channel(svtcur);
+
+        writeln!(self.outfile, "{}", record).unwrap();
+
+    }
+
+    //
+    // 受信中の逐次出力処理(セクションの解析毎に追加、更新されたイベントを出力)
+    // (受信終了時と同じく、取得対象外のサービス、同じtransport_stream_idの2つ目以降のEIT PFは出力しない)
+    //
+    pub fn stream(&mut self, cmd_opt: &CommanLineOpt, tot: &TotState, svttop: &mut [SvtControlTop]) {
+
+        if !self.is_stream {
+
+            return;
+
+        };
+
+        let pf_flags = pf_service_flags(svttop);
+        for (svt, is_pf) in svttop.iter_mut().zip(pf_flags) {
+
+            let svtcur = &mut svt.svt_control_sub[0];
+
+            // 取得対象外のサービスは出力しない(受信済みのイベントは取得対象になった後に出力)
+            if svtcur.import_stat <= 0 {
+
+                continue;
+
+            };
+
+            // EIT SCH出力なしの場合はEIT PFのみ出力
+            let sch_changed = if cmd_opt.eit_mode { vec![] } else { svtcur.sch_store.take_changed() };
+
+            // 2つ目以降のEIT PFは破棄
+            let pf_changed = svtcur.pf_store.take_changed();
+            let pf_changed = if is_pf { pf_changed } else { vec![] };
+
+            for (mut eitcur, pf_flg) in pf_changed.into_iter().map(|eitcur| (eitcur, true))
+                .chain(sch_changed.into_iter().map(|eitcur| (eitcur, false))) {
+
+                // タイトルの記号の解析処理
+                eitcur.marker_parse(cmd_opt.is_strip_markers);
+                self.write_programme(cmd_opt, tot, svtcur, &eitcur, pf_flg);
+
+            };
+        };
+
+    }
+
+    //
+    // 受信終了時の出力処理(ファイル入力の場合は全レコード、逐次出力の場合は未出力、内容が変わったレコード)
+    //
+    pub fn finish(&mut self, cmd_opt: &CommanLineOpt, tot: &TotState, svttop: &mut [SvtControlTop]) {
+
+        for svt in svttop.iter_mut() {
+
+            let svtcur = &mut svt.svt_control_sub[0];
+
+            // 番組データ補正処理
+            programme_repair(cmd_opt, tot, svtcur);
+
+            self.write_channel(svtcur);
+
+            let svtcur = &svt.svt_control_sub[0];
+            for eitcur in svtcur.eit_pf.iter() {
+
+                self.write_programme(cmd_opt, tot, svtcur, eitcur, true);
+
+            };
+            if !cmd_opt.eit_mode {
+                for eitcur in svtcur.eitsch.iter() {
+
+                    self.write_programme(cmd_opt, tot, svtcur, eitcur, false);
+
+                };
+            };
+        };
+
+        debug!("ndjson channels={} programmes={}", self.channels.len(), self.programmes.len());
+
+    }
+}
@@ -0,0 +1,296 @@
+
+use log::{debug};
+
+use crate::eit::{START_TIME_UNCERTAINTY, DURATION_UNCERTAINTY};
+use crate::store::{version_newer};
+use crate::tot::{TimeFormat, TotState};
+use crate::ts::{EitControl, SvtControl};
+
+// 番組重複の補正方針
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OverlapPolicy {
+    None,      // 補正しない
+    TrustPf,   // EIT PFの時刻を優先(その他の重複は切り詰め)
+    Newest,    // 同一テーブルでバージョン番号の新しい番組を優先(その他の重複は切り詰め)
+    Truncate,  // 前の番組を後の番組の開始時刻で切り詰め
+}
+
+impl OverlapPolicy {
+
+    //
+    // 補正方針名の取得処理
+    //
+    pub fn name(&self) -> &'static str {
+
+        match self {
+            OverlapPolicy::None => "none",
+            OverlapPolicy::TrustPf => "trust-pf",
+            OverlapPolicy::Newest => "newest",
+            OverlapPolicy::Truncate => "truncate",
+        }
+
+    }
+}
+
+//
+// 番組期間(キャラクタ)作成処理(開始時刻、終了時刻が未定義の場合はundefined)
+//
+fn event_span(eitcur: &EitControl, tot: &TotState) -> String {
+
+    let time_text = |time: Option<i64>| time.map(|time| tot.format_time(time, TimeFormat::Iso8601)).unwrap_or("undefined".to_string());
+
+    format!("event_id={}(version={} {}〜{})", eitcur.event_id, eitcur.version_number,
+        time_text(eitcur.start()), time_text(eitcur.end()))
+
+}
+
+//
+// 番組重複補正のレポート出力処理
+// (is_reportがtrueの場合は標準エラー出力、falseの場合はデバッグログに出力し、標準出力への出力に混ざらないようにする)
+//
+fn overlap_report(policy: OverlapPolicy, is_report: bool, service_id: i32, table: &str, prev: &str, next: &str, action: &str) {
+
+    let report = format!("番組重複補正({}) service_id={} {} {} と {} が重複 -> {}", policy.name(), service_id, table, prev, next, action);
+
+    if is_report {
+
+        eprintln!("{}", report);
+
+    }
+    else {
+
+        debug!("{}", report);
+
+    };
+
+}
+
+//
+// 番組重複の補正処理
+//
+pub fn overlap_repair(svtcur: &mut SvtControl, policy: OverlapPolicy, tot: &TotState, is_report: bool) -> usize {
+
+    // 補正しない場合はリターン
+    if policy == OverlapPolicy::None {
+
+        return 0;
+
+    };
+
+    let mut fix_cnt = 0;
+
+    // EIT PF優先の場合はEIT SCHをEIT PFに合わせて補正
+    if policy == OverlapPolicy::TrustPf {
+
+        fix_cnt += overlap_repair_pf(svtcur, policy, tot, is_report);
+
+    };
+
+    // EIT PF、EIT SCHそれぞれの重複を補正
+    fix_cnt += overlap_repair_list(&mut svtcur.eit_pf, svtcur.service_id, "pf", policy, tot, is_report);
+    fix_cnt += overlap_repair_list(&mut svtcur.eitsch, svtcur.service_id, "sch", policy, tot, is_report);
+
+    debug!("overlap_repair service_id={} policy={} fix_cnt={}", svtcur.service_id, policy.name(), fix_cnt);
+
+    fix_cnt
+
+}
+
+//
+// EIT PFを優先した番組重複の補正処理(EIT SCH)
+//
+fn overlap_repair_pf(svtcur: &mut SvtControl, policy: OverlapPolicy, tot: &TotState, is_report: bool) -> usize {
+
+    let mut fix_cnt = 0;
+
+    // eit_pf配列分ループ
+    for cnt in 0..svtcur.eit_pf.len() {
+
+        // 開始時刻、終了時刻が未定義のEIT PFは対象外
+        let pf = &svtcur.eit_pf[cnt];
+        let (pf_start, pf_end) = match (pf.start(), pf.end()) {
+            (Some(pf_start), Some(pf_end)) => (pf_start, pf_end),
+            _ => continue,
+        };
+
+        // eitsch配列分ループ
+        let mut cnt2 = 0;
+        while cnt2 < svtcur.eitsch.len() {
+
+            let sch = &mut svtcur.eitsch[cnt2];
+            let span = event_span(sch, tot);
+
+            // 同じイベントの場合はEIT PFの時刻に変更
+            if sch.event_id == pf.event_id {
+
+                if sch.start() != Some(pf_start) || sch.end() != Some(pf_end) {
+
+                    sch.set_start_time(pf_start);
+                    sch.duration = pf.duration;
+                    sch.event_status &= !(START_TIME_UNCERTAINTY | DURATION_UNCERTAINTY);
+                    overlap_report(policy, is_report, svtcur.service_id, "sch", &span, &event_span(pf, tot), "EIT PFの時刻に変更");
+                    fix_cnt += 1;
+
+                };
+
+                cnt2 += 1;
+                continue;
+
+            };
+
+            // EIT PFと重複する場合の処理
+            match (sch.start(), sch.end()) {
+                (Some(sch_start), Some(sch_end)) if sch_start < pf_end && sch_end > pf_start => {
+
+                    // EIT PFより前に開始する場合はEIT PFの開始時刻で切り詰め
+                    if sch_start < pf_start {
+
+                        sch.duration = (pf_start - sch_start) as i32;
+                        overlap_report(policy, is_report, svtcur.service_id, "sch", &span, &event_span(pf, tot), "EIT PFの開始時刻で切り詰め");
+
+                    }
+                    // EIT PFより後に終了する場合はEIT PFの終了時刻から開始
+                    else if sch_end > pf_end {
+
+                        sch.set_start_time(pf_end);
+                        sch.duration = (sch_end - pf_end) as i32;
+                        overlap_report(policy, is_report, svtcur.service_id, "sch", &span, &event_span(pf, tot), "EIT PFの終了時刻から開始");
+
+                    }
+                    // EIT PFに含まれる場合は削除
+                    else {
+
+                        overlap_report(policy, is_report, svtcur.service_id, "sch", &span, &event_span(pf, tot), "削除");
+                        svtcur.eitsch.remove(cnt2);
+                        fix_cnt += 1;
+
+                        continue;
+
+                    };
+
+                    fix_cnt += 1;
+
+                },
+                _ => {},
+            };
+
+            cnt2 += 1;
+
+        };
+    };
+
+    // 開始時刻順に並べ替え(開始時刻未定義の番組は最後)
+    svtcur.eitsch.sort_by_key(|eitcur| eitcur.start().unwrap_or(i64::MAX));
+
+    fix_cnt
+
+}
+
+//
+// 開始時刻順の番組配列の重複補正処理
+//
+fn overlap_repair_list(events: &mut Vec<EitControl>, service_id: i32, table: &str, policy: OverlapPolicy, tot: &TotState, is_report: bool) -> usize {
+
+    let mut fix_cnt = 0;
+    let mut cnt = 0;
+
+    // 隣り合う番組の重複を判定
+    while cnt + 1 < events.len() {
+
+        let prev = &events[cnt];
+        let next = &events[cnt + 1];
+
+        // 重複していない場合(時刻が未定義の場合を含む)は次を処理
+        let (prev_start, next_start) = match (prev.start(), prev.end(), next.start()) {
+            (Some(prev_start), Some(prev_end), Some(next_start)) if prev_end > next_start => (prev_start, next_start),
+            _ => {
+
+                cnt += 1;
+                continue;
+
+            },
+        };
+
+        let prev_span = event_span(prev, tot);
+        let next_span = event_span(next, tot);
+
+        // バージョン番号を比較する場合の判定(同一テーブルのみ)
+        let same_table = policy == OverlapPolicy::Newest && prev.table_id == next.table_id;
+
+        // 後の番組が新しい場合は前の番組を削除
+        if same_table && version_newer(next.version_number, prev.version_number) {
+
+            overlap_report(policy, is_report, service_id, table, &prev_span, &next_span, "前の番組を削除");
+            events.remove(cnt);
+
+        }
+        // 前の番組が新しい場合は後の番組を削除
+        else if same_table && version_newer(prev.version_number, next.version_number) {
+
+            overlap_report(policy, is_report, service_id, table, &prev_span, &next_span, "後の番組を削除");
+            events.remove(cnt + 1);
+
+        }
+        // 前の番組を後の番組の開始時刻で切り詰め
+        else if prev_start < next_start {
+
+            overlap_report(policy, is_report, service_id, table, &prev_span, &next_span, "前の番組を切り詰め");
+            events[cnt].duration = (next_start - prev_start) as i32;
+            cnt += 1;
+
+        }
+        // 開始時刻が同じ場合は前の番組を削除
+        else {
+
+            overlap_report(policy, is_report, service_id, table, &prev_span, &next_span, "前の番組を削除");
+            events.remove(cnt);
+
+        };
+
+        fix_cnt += 1;
+
+    };
+
+    fix_cnt
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ts::{START_TIME_UNDEFINED};
+    use crate::ts::tests::{event, service};
+
+    #[test]
+    fn trust_pf_with_undefined_start() {
+
+        let base = 1_700_000_000;
+        let mut svtcur = service(
+            vec![event(1, base, 1800, 0)],
+            vec![
+                event(1, base, 1800, 0),
+                event(2, START_TIME_UNDEFINED, 1800, START_TIME_UNCERTAINTY),
+                event(3, base + 1200, 1800, 0),
+            ],
+        );
+
+        assert_eq!(overlap_repair(&mut svtcur, OverlapPolicy::TrustPf, &TotState::default(), false), 1);
+
+        // 重複したEIT SCHはEIT PFの終了時刻から開始、開始時刻未定義の番組は最後
+        let event_ids: Vec<i32> = svtcur.eitsch.iter().map(|eitcur| eitcur.event_id).collect();
+        assert_eq!(event_ids, vec![1, 3, 2]);
+        assert_eq!(svtcur.eitsch[1].start(), Some(base + 1800));
+        assert_eq!(svtcur.eitsch[1].end(), Some(base + 3000));
+
+    }
+
+    #[test]
+    fn event_span_undefined() {
+
+        let eitcur = event(2, START_TIME_UNDEFINED, 1800, START_TIME_UNCERTAINTY);
+
+        assert_eq!(event_span(&eitcur, &TotState::default()), "event_id=2(version=0 undefined〜undefined)");
+
+    }
+}
@@ -3,7 +3,8 @@ use log::{debug};
 
 use crate::arib::{arib_to_string};
 use crate::{CommanLineOpt};
-use crate::ts::{MAXSECLEN, SvtControl, SvtControlTop};
+use crate::store::{EitStore};
+use crate::ts::{MAXSECLEN, EitNullSegmentTop, SvtControl, SvtControlTop};
 
 // SDTヘッダー
 #[derive(Debug, Copy, Clone)]
@@ -168,6 +169,9 @@ pub fn service_id_cehck(cmd_opt: &CommanLineOpt, svttop: &mut Vec<SvtControlTop>
             eitsch: vec![],
             eit_pf: vec![],
             prev_sch: vec![],
+            sch_store: EitStore::default(),
+            pf_store: EitStore::default(),
+            null_segment: EitNullSegmentTop::default(),
             import_cnt: 0,
             import_stat: 0,
             logo_download_data_id: 0,
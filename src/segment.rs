@@ -0,0 +1,232 @@
+
+use log::{debug};
+
+use crate::tot::{JST_OFFSET, TimeFormat, TotState};
+use crate::ts::{EitNullSegment, EitNullSegmentTop};
+
+// EIT SCH(基本情報)のテーブルID範囲
+const TABLE_ID_SCH_FIRST: i32 = 0x50;
+const TABLE_ID_SCH_LAST: i32 = 0x57;
+
+// 1テーブルのセグメント数(1セグメントは8セクション)
+const SEGMENT_COUNT: i32 = 32;
+
+// 1セグメントの期間(秒)
+const SEGMENT_SECONDS: i64 = 3 * 3600;
+
+//
+// EIT SCHの基準時刻取得処理(リターン:基準時刻の日のJST 0時)
+//
+fn schedule_base(now: i64) -> i64 {
+
+    (now + JST_OFFSET as i64).div_euclid(86400) * 86400 - JST_OFFSET as i64
+
+}
+
+impl EitNullSegmentTop {
+
+    //
+    // 受信セグメントの記録処理(is_nullがtrueの場合はnullセグメント)
+    //
+    pub fn record(&mut self, segment: EitNullSegment, last_section_number: i32, last_table_id: i32, is_null: bool) {
+
+        // 基本情報以外は対象外
+        if !(TABLE_ID_SCH_FIRST..=TABLE_ID_SCH_LAST).contains(&segment.table_id) {
+
+            return;
+
+        };
+
+        let table_id = segment.table_id;
+        let segment_number = segment.section_number >> 3;
+
+        // 受信済みセグメント、最終セグメント番号、最終テーブルIDを更新
+        self.service_id = segment.service_id;
+        *self.received_segment.entry(table_id).or_insert(0) |= 1 << segment_number;
+        self.last_segment.insert(table_id, last_section_number >> 3);
+        self.last_table_id = last_table_id.clamp(TABLE_ID_SCH_FIRST, TABLE_ID_SCH_LAST);
+
+        // 同じセグメントのnullセグメントを削除し、nullセグメントの場合は追加
+        self.eit_null_segment_sub.retain(|null_segment|
+            !(null_segment.table_id == table_id && null_segment.section_number >> 3 == segment_number));
+        if is_null {
+
+            debug!("null segment service_id={} table_id=0x{:02x} segment={} version={}",
+                segment.service_id, table_id, segment_number, segment.version_number);
+            self.eit_null_segment_sub.push(segment);
+
+        };
+
+    }
+
+    //
+    // セグメントの受信済み判定処理
+    //
+    fn is_segment_received(&self, table_id: i32, segment_number: i32) -> bool {
+
+        self.received_segment.get(&table_id).is_some_and(|received| received & (1 << segment_number) != 0)
+
+    }
+
+    //
+    // 期間の受信済み判定処理
+    // (nowは基準時刻(TDT/TOTの現在時刻)、nowより前のセグメントは対象外)
+    // (期間内の全セグメントを受信済みの場合にtrue、EIT SCHまたは基準時刻が無く判定できない場合もtrue)
+    //
+    pub fn is_received(&self, start_time: i64, end_time: i64, now: Option<i64>) -> bool {
+
+        // 判定できない場合はtrue
+        let now = match now {
+            Some(now) if !self.received_segment.is_empty() => now,
+            _ => return true,
+        };
+        let base = schedule_base(now);
+
+        // 期間内のセグメント分ループ
+        for window in (start_time - base).div_euclid(SEGMENT_SECONDS)..=(end_time - 1 - base).div_euclid(SEGMENT_SECONDS) {
+
+            // 現在時刻より前のセグメントは対象外
+            if base + (window + 1) * SEGMENT_SECONDS <= now {
+
+                continue;
+
+            };
+
+            // EIT SCHの範囲外、または未受信のセグメントがある場合はfalse
+            if window >= (SEGMENT_COUNT * (TABLE_ID_SCH_LAST - TABLE_ID_SCH_FIRST + 1)) as i64 ||
+                !self.is_segment_received(TABLE_ID_SCH_FIRST + window as i32 / SEGMENT_COUNT, window as i32 % SEGMENT_COUNT) {
+
+                return false;
+
+            };
+        };
+
+        true
+
+    }
+
+    //
+    // 受信漏れ期間の一覧作成処理(nowは基準時刻(TDT/TOTの現在時刻)、nowより前のセグメントは対象外)
+    //
+    pub fn holes(&self, now: Option<i64>) -> Vec<(i64, i64)> {
+
+        let mut holes: Vec<(i64, i64)> = vec![];
+
+        // 判定できない場合は空
+        let now = match now {
+            Some(now) if !self.received_segment.is_empty() => now,
+            _ => return holes,
+        };
+        let base = schedule_base(now);
+
+        // 最終テーブルIDまでのテーブル分ループ
+        for table_id in TABLE_ID_SCH_FIRST..=self.last_table_id {
+
+            // 最終セグメント番号(未受信のテーブルは全セグメント)
+            let last_segment = self.last_segment.get(&table_id).copied().unwrap_or(SEGMENT_COUNT - 1);
+
+            // セグメント分ループ
+            for segment_number in 0..=last_segment {
+
+                // 現在時刻より前、または受信済みのセグメントは対象外
+                let window = ((table_id - TABLE_ID_SCH_FIRST) * SEGMENT_COUNT + segment_number) as i64;
+                let start_time = base + window * SEGMENT_SECONDS;
+                let end_time = start_time + SEGMENT_SECONDS;
+                if end_time <= now || self.is_segment_received(table_id, segment_number) {
+
+                    continue;
+
+                };
+
+                // 連続する期間は結合
+                match holes.last_mut() {
+                    Some(hole) if hole.1 == start_time => hole.1 = end_time,
+                    _ => holes.push((start_time, end_time)),
+                };
+            };
+        };
+
+        holes
+
+    }
+
+    //
+    // 受信漏れ期間のレポート出力処理
+    // (is_reportがtrueの場合は標準エラー出力、falseの場合はデバッグログに出力し、標準出力への出力に混ざらないようにする)
+    //
+    pub fn hole_report(&self, tot: &TotState, is_report: bool) {
+
+        for (start_time, end_time) in self.holes(tot.current_time) {
+
+            let report = format!("EIT受信漏れ service_id={} {}〜{}", self.service_id,
+                tot.format_time(start_time, TimeFormat::Iso8601), tot.format_time(end_time, TimeFormat::Iso8601));
+
+            if is_report {
+
+                eprintln!("{}", report);
+
+            }
+            else {
+
+                debug!("{}", report);
+
+            };
+        };
+
+        debug!("null segment service_id={} null_segment_cnt={}", self.service_id, self.eit_null_segment_sub.len());
+
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // 基準時刻(2023-11-15 00:00:00 JST)
+    const BASE: i64 = 1_699_974_000;
+
+    // テスト用のセグメント記録処理(テーブルID 0x50、最終セグメント番号3)
+    fn record(null_segment: &mut EitNullSegmentTop, segment_number: i32, is_null: bool) {
+
+        let segment = EitNullSegment { table_id: 0x50, service_id: 101, section_number: segment_number << 3, version_number: 0 };
+        null_segment.record(segment, 3 << 3, 0x50, is_null);
+
+    }
+
+    #[test]
+    fn null_segment_is_received() {
+
+        let mut null_segment = EitNullSegmentTop::default();
+        record(&mut null_segment, 0, false);
+        record(&mut null_segment, 1, true);
+        record(&mut null_segment, 3, false);
+
+        // nullセグメント(番組なし)は受信済み、未受信のセグメント2は受信漏れ
+        assert!(null_segment.is_received(BASE + 3 * 3600, BASE + 6 * 3600, Some(BASE)));
+        assert!(!null_segment.is_received(BASE + 6 * 3600, BASE + 9 * 3600, Some(BASE)));
+        assert_eq!(null_segment.eit_null_segment_sub.len(), 1);
+        assert_eq!(null_segment.holes(Some(BASE)), vec![(BASE + 6 * 3600, BASE + 9 * 3600)]);
+
+        // 基準時刻より前のセグメントは対象外
+        assert!(null_segment.is_received(BASE + 6 * 3600, BASE + 9 * 3600, Some(BASE + 9 * 3600)));
+        assert!(null_segment.holes(Some(BASE + 9 * 3600)).is_empty());
+
+    }
+
+    #[test]
+    fn undetermined_is_received() {
+
+        let mut null_segment = EitNullSegmentTop::default();
+
+        // EIT SCH未受信
+        assert!(null_segment.is_received(BASE, BASE + 3600, Some(BASE)));
+
+        // 基準時刻なし
+        record(&mut null_segment, 0, false);
+        assert!(null_segment.is_received(BASE + 6 * 3600, BASE + 9 * 3600, None));
+        assert!(null_segment.holes(None).is_empty());
+
+    }
+
+}
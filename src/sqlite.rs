@@ -0,0 +1,459 @@
+use log::{debug, error};
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use std::process;
+
+use crate::{CommanLineOpt, programme_list, sch_pnt_update};
+use crate::store::{version_newer};
+use crate::ts::{EitControl, SvtControl, SvtControlTop};
+
+// テーブル定義(主キーはオリジナルネットワークID、サービスID、イベントID)
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS services (
+    original_network_id INTEGER NOT NULL,
+    service_id          INTEGER NOT NULL,
+    transport_stream_id INTEGER NOT NULL,
+    service_type        INTEGER NOT NULL,
+    name                TEXT    NOT NULL,
+    channel             TEXT    NOT NULL,
+    PRIMARY KEY (original_network_id, service_id)
+);
+CREATE TABLE IF NOT EXISTS events (
+    original_network_id INTEGER NOT NULL,
+    service_id          INTEGER NOT NULL,
+    event_id            INTEGER NOT NULL,
+    table_id            INTEGER NOT NULL,
+    version_number      INTEGER NOT NULL,
+    start_time          INTEGER,
+    duration            INTEGER,
+    title               TEXT    NOT NULL,
+    description         TEXT    NOT NULL,
+    extended            TEXT    NOT NULL,
+    lang                TEXT    NOT NULL,
+    free_ca_mode        INTEGER NOT NULL,
+    rating              INTEGER NOT NULL,
+    markers             TEXT    NOT NULL,
+    series_id           INTEGER,
+    episode_number      INTEGER,
+    repeat_label        INTEGER,
+    PRIMARY KEY (original_network_id, service_id, event_id)
+);
+CREATE INDEX IF NOT EXISTS events_start_time ON events (start_time);
+CREATE TABLE IF NOT EXISTS genres (
+    original_network_id INTEGER NOT NULL,
+    service_id          INTEGER NOT NULL,
+    event_id            INTEGER NOT NULL,
+    seq                 INTEGER NOT NULL,
+    content_nibble_1    INTEGER NOT NULL,
+    content_nibble_2    INTEGER NOT NULL,
+    user_nibble_1       INTEGER NOT NULL,
+    user_nibble_2       INTEGER NOT NULL,
+    PRIMARY KEY (original_network_id, service_id, event_id, seq)
+);
+CREATE TABLE IF NOT EXISTS components (
+    original_network_id INTEGER NOT NULL,
+    service_id          INTEGER NOT NULL,
+    event_id            INTEGER NOT NULL,
+    seq                 INTEGER NOT NULL,
+    kind                TEXT    NOT NULL,
+    stream_content      INTEGER,
+    component_type      INTEGER NOT NULL,
+    component_tag       INTEGER,
+    is_main             INTEGER,
+    sampling_rate       INTEGER,
+    langs               TEXT,
+    PRIMARY KEY (original_network_id, service_id, event_id, seq)
+);
+CREATE TABLE IF NOT EXISTS extended_items (
+    original_network_id INTEGER NOT NULL,
+    service_id          INTEGER NOT NULL,
+    event_id            INTEGER NOT NULL,
+    seq                 INTEGER NOT NULL,
+    name                TEXT    NOT NULL,
+    text                TEXT    NOT NULL,
+    PRIMARY KEY (original_network_id, service_id, event_id, seq)
+);
+CREATE TABLE IF NOT EXISTS series (
+    original_network_id INTEGER NOT NULL,
+    series_id           INTEGER NOT NULL,
+    name                TEXT    NOT NULL,
+    program_pattern     INTEGER NOT NULL,
+    expire_date         INTEGER,
+    last_episode_number INTEGER NOT NULL,
+    PRIMARY KEY (original_network_id, series_id)
+);
+";
+
+// イベントの子テーブル(イベントの置き換え時に削除)
+const EVENT_CHILD_TABLES: [&str; 3] = ["genres", "components", "extended_items"];
+
+// 更新件数構造体
+#[derive(Debug, Default)]
+struct UpsertCount {
+    inserted: usize,   // 追加
+    replaced: usize,   // 置き換え
+    skipped: usize,    // 同じ、または古いバージョンのため未更新
+}
+
+//
+// EIT PFのテーブルID判定処理
+//
+fn is_table_pf(table_id: i32) -> bool {
+
+    table_id == 0x4e || table_id == 0x4f
+
+}
+
+//
+// イベントの置き換え判定処理
+// (未登録の場合、同じテーブルで新しいバージョンの場合にtrue)
+// (EIT SCHはEIT PFより優先し、EIT PFでEIT SCHの登録済みイベントは置き換えない)
+// (EIT SCHのテーブルIDが異なる場合はバージョンを比較できないため置き換える)
+//
+fn is_replace(stored: Option<(i32, i32)>, eitcur: &EitControl) -> bool {
+
+    let (table_id, version_number) = match stored {
+        Some(stored) => stored,
+        None => return true,
+    };
+
+    if table_id == eitcur.table_id {
+
+        return version_newer(eitcur.version_number, version_number);
+
+    };
+
+    match (is_table_pf(table_id), is_table_pf(eitcur.table_id)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => true,
+    }
+
+}
+
+//
+// サービスの追加、更新処理
+//
+fn upsert_service(tx: &Transaction, svtcur: &SvtControl) -> rusqlite::Result<()> {
+
+    tx.execute(
+        "INSERT INTO services (original_network_id, service_id, transport_stream_id, service_type, name, channel)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (original_network_id, service_id) DO UPDATE SET
+                transport_stream_id = excluded.transport_stream_id, service_type = excluded.service_type,
+                name = excluded.name, channel = excluded.channel",
+        params![svtcur.original_network_id, svtcur.service_id, svtcur.transport_stream_id,
+            svtcur.service_type, svtcur.servicename, svtcur.ontv])?;
+
+    Ok(())
+
+}
+
+//
+// イベントの追加、置き換え処理(バージョンにより置き換えを判定)
+//
+fn upsert_event(tx: &Transaction, network_id: i32, eitcur: &EitControl, count: &mut UpsertCount) -> rusqlite::Result<()> {
+
+    let key = params![network_id, eitcur.servid, eitcur.event_id];
+
+    // 登録済みイベントのテーブルID、バージョンの取得
+    let stored: Option<(i32, i32)> = tx.query_row(
+        "SELECT table_id, version_number FROM events
+            WHERE original_network_id = ?1 AND service_id = ?2 AND event_id = ?3",
+        key, |row| Ok((row.get(0)?, row.get(1)?))).optional()?;
+
+    if !is_replace(stored, eitcur) {
+
+        count.skipped += 1;
+        return Ok(());
+
+    };
+
+    // 置き換えの場合は子テーブルを削除
+    if stored.is_some() {
+
+        for table in EVENT_CHILD_TABLES.iter() {
+
+            tx.execute(&format!("DELETE FROM {} WHERE original_network_id = ?1 AND service_id = ?2 AND event_id = ?3", table), key)?;
+
+        };
+        count.replaced += 1;
+
+    }
+    else {
+
+        count.inserted += 1;
+
+    };
+
+    // イベント
+    let markers: Vec<&str> = eitcur.markers.iter().map(|marker| marker.name()).collect();
+    tx.execute(
+        "INSERT OR REPLACE INTO events (original_network_id, service_id, event_id, table_id, version_number,
+            start_time, duration, title, description, extended, lang, free_ca_mode, rating, markers,
+            series_id, episode_number, repeat_label)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        params![network_id, eitcur.servid, eitcur.event_id, eitcur.table_id, eitcur.version_number,
+            eitcur.start(), eitcur.end().zip(eitcur.start()).map(|(end_time, start_time)| end_time - start_time),
+            eitcur.title, eitcur.subtitle, eitcur.desc, eitcur.lang, eitcur.free_ca_mode, eitcur.rating, markers.join(","),
+            eitcur.series.as_ref().map(|series| series.series_id),
+            eitcur.series.as_ref().map(|series| series.episode_number),
+            eitcur.series.as_ref().map(|series| series.repeat_label)])?;
+
+    // ジャンル
+    for (seq, genre) in eitcur.genres.iter().enumerate() {
+
+        tx.execute(
+            "INSERT INTO genres (original_network_id, service_id, event_id, seq,
+                content_nibble_1, content_nibble_2, user_nibble_1, user_nibble_2)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![network_id, eitcur.servid, eitcur.event_id, seq,
+                genre.content_nibble_level_1, genre.content_nibble_level_2, genre.user_nibble_1, genre.user_nibble_2])?;
+
+    };
+
+    // コンポーネント(映像、音声の順)
+    let mut seq = 0;
+    if eitcur.stream_content != 0 {
+
+        tx.execute(
+            "INSERT INTO components (original_network_id, service_id, event_id, seq, kind, stream_content, component_type)
+                VALUES (?1, ?2, ?3, ?4, 'video', ?5, ?6)",
+            params![network_id, eitcur.servid, eitcur.event_id, seq, eitcur.stream_content, eitcur.video_type])?;
+        seq += 1;
+
+    };
+    for audio in eitcur.audios.iter() {
+
+        tx.execute(
+            "INSERT INTO components (original_network_id, service_id, event_id, seq, kind,
+                component_type, component_tag, is_main, sampling_rate, langs)
+                VALUES (?1, ?2, ?3, ?4, 'audio', ?5, ?6, ?7, ?8, ?9)",
+            params![network_id, eitcur.servid, eitcur.event_id, seq,
+                audio.component_type, audio.component_tag, audio.is_main, audio.sampling_rate, audio.langs.join(",")])?;
+        seq += 1;
+
+    };
+
+    // 詳細説明の項目
+    for (seq, item) in eitcur.desc_items.iter().enumerate() {
+
+        tx.execute(
+            "INSERT INTO extended_items (original_network_id, service_id, event_id, seq, name, text)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![network_id, eitcur.servid, eitcur.event_id, seq, item.name, item.text])?;
+
+    };
+
+    // シリーズ(シリーズID毎に最新の情報で更新)
+    if let Some(series) = &eitcur.series {
+
+        tx.execute(
+            "INSERT INTO series (original_network_id, series_id, name, program_pattern, expire_date, last_episode_number)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT (original_network_id, series_id) DO UPDATE SET
+                    name = excluded.name, program_pattern = excluded.program_pattern,
+                    expire_date = excluded.expire_date, last_episode_number = excluded.last_episode_number",
+            params![network_id, series.series_id, series.name, series.program_pattern,
+                series.expire_date, series.last_episode_number])?;
+
+    };
+
+    Ok(())
+
+}
+
+//
+// サービス毎の番組データの更新処理
+// (放送休止データは挿入せず、EIT SCHにEIT PFの番組を補って更新)
+//
+fn sqlite_service(cmd_opt: &CommanLineOpt, tx: &Transaction, svtcur: &mut SvtControl, count: &mut UpsertCount) -> rusqlite::Result<()> {
+
+    // EITモードフラグがfalseでeitschにデータある場合の処理
+    if !cmd_opt.eit_mode && !svtcur.eitsch.is_empty() {
+
+        // sch_pnt補正処理呼出し
+        sch_pnt_update(svtcur);
+
+    };
+
+    upsert_service(tx, svtcur)?;
+
+    // 更新対象の番組作成(EIT SCHに無いEIT PFの番組を追加)
+    for eitcur in programme_list(cmd_opt, svtcur).iter().filter(|eitcur| !eitcur.is_synthetic()) {
+
+        upsert_event(tx, svtcur.original_network_id, eitcur, count)?;
+
+    };
+
+    Ok(())
+
+}
+
+//
+// SQLiteデータベースの更新処理(1トランザクションで更新)
+//
+fn sqlite_update(cmd_opt: &CommanLineOpt, conn: &mut Connection, svttop: &mut [SvtControlTop]) -> rusqlite::Result<UpsertCount> {
+
+    conn.execute_batch(SCHEMA)?;
+
+    let mut count = UpsertCount::default();
+    let tx = conn.transaction()?;
+
+    for svt in svttop.iter_mut() {
+
+        sqlite_service(cmd_opt, &tx, &mut svt.svt_control_sub[0], &mut count)?;
+
+    };
+
+    tx.commit()?;
+
+    Ok(count)
+
+}
+
+//
+// SQLiteデータベース作成処理(既存のデータベースはイベントのバージョンにより更新)
+//
+pub fn dump_sqlite(cmd_opt: &CommanLineOpt, svttop: &mut [SvtControlTop]) {
+
+    let result = Connection::open(&cmd_opt.outfile)
+        .and_then(|mut conn| sqlite_update(cmd_opt, &mut conn, svttop));
+
+    match result {
+        Ok(count) => {
+            debug!("sqlite {} inserted={} replaced={} skipped={}",
+                cmd_opt.outfile, count.inserted, count.replaced, count.skipped);
+        },
+        Err(err) => {
+            error!("SQLite Error({}:{})", cmd_opt.outfile, err);
+            process::exit(1);
+        },
+    };
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ts::{EitDescItem, EitGenre};
+    use crate::ts::tests::{event};
+
+    // テスト用のデータベース作成処理(メモリ上)
+    fn database() -> Connection {
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+
+        conn
+
+    }
+
+    // テスト用のイベント作成処理(ジャンル、詳細説明の項目を含む)
+    fn event_with_children(table_id: i32, version_number: i32, items: usize) -> EitControl {
+
+        let genre = EitGenre { content_nibble_level_1: 0x0, content_nibble_level_2: 0x1, user_nibble_1: 0, user_nibble_2: 0 };
+        let desc_items = (0..items)
+            .map(|cnt| EitDescItem { name: format!("項目{}", cnt), text: format!("記述{}", cnt) })
+            .collect();
+
+        EitControl { table_id, version_number, title: format!("v{}", version_number),
+            genres: vec![genre; items], desc_items, ..event(1, 1_700_000_000, 1800, 0) }
+
+    }
+
+    // イベントの登録処理(登録後のタイトル、更新件数を返す)
+    fn upsert(conn: &mut Connection, eitcur: &EitControl, count: &mut UpsertCount) -> String {
+
+        let tx = conn.transaction().unwrap();
+        upsert_event(&tx, 4, eitcur, count).unwrap();
+        tx.commit().unwrap();
+
+        conn.query_row("SELECT title FROM events WHERE event_id = 1", [], |row| row.get(0)).unwrap()
+
+    }
+
+    // 子テーブルの件数取得処理
+    fn child_count(conn: &Connection, table: &str) -> i64 {
+
+        conn.query_row(&format!("SELECT COUNT(*) FROM {} WHERE event_id = 1", table), [], |row| row.get(0)).unwrap()
+
+    }
+
+    #[test]
+    fn insert_event() {
+
+        let mut conn = database();
+        let mut count = UpsertCount::default();
+
+        assert_eq!(upsert(&mut conn, &event_with_children(0x50, 3, 2), &mut count), "v3");
+        assert_eq!((count.inserted, count.replaced, count.skipped), (1, 0, 0));
+        assert_eq!(child_count(&conn, "genres"), 2);
+        assert_eq!(child_count(&conn, "extended_items"), 2);
+
+    }
+
+    #[test]
+    fn replace_newer_version() {
+
+        let mut conn = database();
+        let mut count = UpsertCount::default();
+
+        upsert(&mut conn, &event_with_children(0x50, 31, 2), &mut count);
+
+        // 5bitの巡回を超えた新しいバージョン
+        assert_eq!(upsert(&mut conn, &event_with_children(0x50, 0, 1), &mut count), "v0");
+        assert_eq!((count.inserted, count.replaced, count.skipped), (1, 1, 0));
+
+    }
+
+    #[test]
+    fn skip_same_or_older_version() {
+
+        let mut conn = database();
+        let mut count = UpsertCount::default();
+
+        upsert(&mut conn, &event_with_children(0x50, 5, 2), &mut count);
+
+        assert_eq!(upsert(&mut conn, &event_with_children(0x50, 5, 1), &mut count), "v5");
+        assert_eq!(upsert(&mut conn, &event_with_children(0x50, 4, 1), &mut count), "v5");
+        assert_eq!((count.inserted, count.replaced, count.skipped), (1, 0, 2));
+        assert_eq!(child_count(&conn, "genres"), 2);
+
+    }
+
+    #[test]
+    fn schedule_precedes_pf() {
+
+        let mut conn = database();
+        let mut count = UpsertCount::default();
+
+        // EIT PFの登録済みイベントはEIT SCHで置き換え
+        upsert(&mut conn, &event_with_children(0x4e, 5, 1), &mut count);
+        assert_eq!(upsert(&mut conn, &event_with_children(0x50, 2, 1), &mut count), "v2");
+
+        // EIT SCHの登録済みイベントはEIT PFの新しいバージョンでも置き換えない
+        assert_eq!(upsert(&mut conn, &event_with_children(0x4e, 6, 1), &mut count), "v2");
+
+        // EIT PFの同じテーブルでは新しいバージョンで置き換え
+        let mut conn = database();
+        upsert(&mut conn, &event_with_children(0x4e, 5, 1), &mut count);
+        assert_eq!(upsert(&mut conn, &event_with_children(0x4e, 6, 1), &mut count), "v6");
+
+    }
+
+    #[test]
+    fn replace_deletes_child_rows() {
+
+        let mut conn = database();
+        let mut count = UpsertCount::default();
+
+        upsert(&mut conn, &event_with_children(0x50, 1, 3), &mut count);
+        upsert(&mut conn, &event_with_children(0x50, 2, 1), &mut count);
+
+        assert_eq!(child_count(&conn, "genres"), 1);
+        assert_eq!(child_count(&conn, "extended_items"), 1);
+
+    }
+
+}
@@ -0,0 +1,222 @@
+
+use log::{debug};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::ts::{EitControl};
+
+// バージョン番号(5bit)の比較範囲
+const VERSION_MODULO: i32 = 32;
+
+//
+// バージョン番号の新旧判定処理(5bitの巡回を考慮し、newがoldより新しい場合にtrue)
+//
+pub fn version_newer(new: i32, old: i32) -> bool {
+
+    let diff = (new - old).rem_euclid(VERSION_MODULO);
+
+    diff != 0 && diff < VERSION_MODULO / 2
+
+}
+
+// イベントストア構造体(サービスID、イベントIDをキーとしたイベント格納エリア)
+#[derive(Debug, Clone, Default)]
+pub struct EitStore {
+    events: HashMap<(i32, i32), EitControl>,   // (サービスID, イベントID) -> イベント
+    order: BTreeSet<(i64, i32, i32)>,          // (開始時刻, サービスID, イベントID) 開始時刻順の索引
+    max_duration: i64,                         // 格納済みイベントの最大継続時間(重複検索範囲)
+    changed: BTreeSet<(i32, i32)>,             // 追加、更新されたイベント(ストリーム出力で取り出し)
+}
+
+impl EitStore {
+
+    //
+    // イベントの参照処理(開始時刻、継続時間の変更はupsertで行うこと)
+    //
+    pub fn get_mut(&mut self, service_id: i32, event_id: i32) -> Option<&mut EitControl> {
+
+        self.events.get_mut(&(service_id, event_id))
+
+    }
+
+    //
+    // 旧バージョン判定処理(格納済みイベントより古いバージョンの場合にtrue)
+    //
+    pub fn is_stale(&self, service_id: i32, event_id: i32, table_id: i32, version_number: i32) -> bool {
+
+        match self.events.get(&(service_id, event_id)) {
+            Some(eitcur) => eitcur.table_id == table_id && version_newer(eitcur.version_number, version_number),
+            None => false,
+        }
+
+    }
+
+    //
+    // イベントの削除処理
+    //
+    pub fn remove(&mut self, service_id: i32, event_id: i32) -> Option<EitControl> {
+
+        let eitcur = self.events.remove(&(service_id, event_id))?;
+        self.order.remove(&(eitcur.start_time, service_id, event_id));
+
+        Some(eitcur)
+
+    }
+
+    //
+    // イベントの追加、更新処理
+    // (同一テーブルの旧バージョンで時間が重複するイベントは削除)
+    //
+    pub fn upsert(&mut self, eitcur: EitControl) {
+
+        // 既存イベントの索引を削除
+        if let Some(old) = self.events.get(&(eitcur.servid, eitcur.event_id)) {
+
+            self.order.remove(&(old.start_time, old.servid, old.event_id));
+
+        };
+
+        // 時間が重複する旧バージョンのイベントを削除
+        if let (Some(start_time), Some(end_time)) = (eitcur.start(), eitcur.end()) {
+
+            let evict: Vec<(i32, i32)> = self.order
+                .range((start_time - self.max_duration, i32::MIN, i32::MIN)..(end_time, i32::MIN, i32::MIN))
+                .filter_map(|&(_, service_id, event_id)| self.events.get(&(service_id, event_id)))
+                .filter(|old| old.servid == eitcur.servid && old.event_id != eitcur.event_id)
+                .filter(|old| old.table_id == eitcur.table_id && version_newer(eitcur.version_number, old.version_number))
+                .filter(|old| old.end().is_some_and(|old_end| old_end > start_time))
+                .map(|old| (old.servid, old.event_id))
+                .collect();
+
+            for (service_id, event_id) in evict {
+
+                debug!("evict service_id={} event_id={} (event_id={} version={})",
+                    service_id, event_id, eitcur.event_id, eitcur.version_number);
+                self.remove(service_id, event_id);
+
+            };
+        };
+
+        // 最大継続時間の更新
+        if !eitcur.is_duration_undefined() {
+
+            self.max_duration = self.max_duration.max(eitcur.duration as i64);
+
+        };
+
+        // 索引、イベントの格納
+        self.changed.insert((eitcur.servid, eitcur.event_id));
+        self.order.insert((eitcur.start_time, eitcur.servid, eitcur.event_id));
+        self.events.insert((eitcur.servid, eitcur.event_id), eitcur);
+
+    }
+
+    //
+    // 追加、更新されたイベントの取り出し処理(取り出したイベントは未変更に戻す)
+    //
+    pub fn take_changed(&mut self) -> Vec<EitControl> {
+
+        let changed = std::mem::take(&mut self.changed);
+
+        changed.iter()
+            .filter_map(|key| self.events.get(key))
+            .cloned()
+            .collect()
+
+    }
+
+    //
+    // 開始時刻順のイベント配列作成処理
+    //
+    pub fn to_vec(&self) -> Vec<EitControl> {
+
+        self.order.iter()
+            .filter_map(|(_, service_id, event_id)| self.events.get(&(*service_id, *event_id)))
+            .cloned()
+            .collect()
+
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ts::tests::{event};
+
+    // テスト用のイベント作成処理(バージョン指定)
+    fn versioned(event_id: i32, start_time: i64, duration: i32, version_number: i32) -> EitControl {
+
+        EitControl { version_number, ..event(event_id, start_time, duration, 0) }
+
+    }
+
+    // 開始時刻順のイベントID取得処理
+    fn event_ids(store: &EitStore) -> Vec<i32> {
+
+        store.to_vec().iter().map(|eitcur| eitcur.event_id).collect()
+
+    }
+
+    #[test]
+    fn version_newer_wrap() {
+
+        assert!(version_newer(1, 0));
+        assert!(!version_newer(0, 0));
+        assert!(!version_newer(0, 1));
+
+        // 5bitの巡回(31の次は0)
+        assert!(version_newer(0, 31));
+        assert!(version_newer(3, 30));
+        assert!(!version_newer(31, 0));
+
+        // 半周以上離れた場合は古いバージョン
+        assert!(version_newer(15, 0));
+        assert!(!version_newer(16, 0));
+
+    }
+
+    #[test]
+    fn upsert_evicts_overlapping_older_version() {
+
+        let mut store = EitStore::default();
+        store.upsert(versioned(1, 1000, 600, 3));
+        store.upsert(versioned(2, 1600, 600, 3));
+        store.upsert(versioned(3, 2200, 600, 3));
+
+        // イベント1、2と重複する新しいバージョンのイベント
+        store.upsert(versioned(4, 1300, 600, 4));
+        assert_eq!(event_ids(&store), vec![4, 3]);
+
+    }
+
+    #[test]
+    fn upsert_keeps_overlapping_same_version_or_other_table() {
+
+        let mut store = EitStore::default();
+        store.upsert(versioned(1, 1000, 600, 3));
+        store.upsert(EitControl { table_id: 0x51, ..versioned(2, 1000, 600, 2) });
+
+        // 同じバージョン、または異なるテーブルのイベントは削除しない
+        store.upsert(versioned(3, 1300, 600, 3));
+        store.upsert(EitControl { table_id: 0x51, ..versioned(4, 1300, 600, 1) });
+        assert_eq!(event_ids(&store), vec![1, 2, 3, 4]);
+
+    }
+
+    #[test]
+    fn to_vec_order() {
+
+        let mut store = EitStore::default();
+        store.upsert(versioned(3, 3000, 600, 0));
+        store.upsert(versioned(1, 1000, 600, 0));
+        store.upsert(versioned(2, 2000, 600, 0));
+
+        assert_eq!(event_ids(&store), vec![1, 2, 3]);
+
+        // 開始時刻の変更は索引に反映
+        store.upsert(versioned(1, 4000, 600, 1));
+        assert_eq!(event_ids(&store), vec![2, 3, 1]);
+
+    }
+
+}
@@ -0,0 +1,231 @@
+
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::{Arc, OnceLock};
+
+use crate::arib::{put_symbols_char};
+
+// 囲み文字の追加記号とUnicode 5.2の囲み文字の対応表
+const SYMBOL_ENCLOSED_TABLE: [(u32, &str); 35] = [
+    (0x7a50, "\u{1f14a}"),  // [HV]
+    (0x7a51, "\u{1f14c}"),  // [SD]
+    (0x7a52, "\u{1f13f}"),  // [Ｐ]
+    (0x7a53, "\u{1f146}"),  // [Ｗ]
+    (0x7a54, "\u{1f14b}"),  // [MV]
+    (0x7a55, "\u{1f210}"),  // [手]
+    (0x7a56, "\u{1f211}"),  // [字]
+    (0x7a57, "\u{1f212}"),  // [双]
+    (0x7a58, "\u{1f213}"),  // [デ]
+    (0x7a59, "\u{1f142}"),  // [Ｓ]
+    (0x7a5a, "\u{1f214}"),  // [二]
+    (0x7a5b, "\u{1f215}"),  // [多]
+    (0x7a5c, "\u{1f216}"),  // [解]
+    (0x7a5d, "\u{1f14d}"),  // [SS]
+    (0x7a5e, "\u{1f131}"),  // [Ｂ]
+    (0x7a5f, "\u{1f13d}"),  // [Ｎ]
+    (0x7a62, "\u{1f217}"),  // [天]
+    (0x7a63, "\u{1f218}"),  // [交]
+    (0x7a64, "\u{1f219}"),  // [映]
+    (0x7a65, "\u{1f21a}"),  // [無]
+    (0x7a66, "\u{1f21b}"),  // [料]
+    (0x7a67, "\u{26bf}"),   // [年齢制限]
+    (0x7a68, "\u{1f21c}"),  // [前]
+    (0x7a69, "\u{1f21d}"),  // [後]
+    (0x7a6a, "\u{1f21e}"),  // [再]
+    (0x7a6b, "\u{1f21f}"),  // [新]
+    (0x7a6c, "\u{1f220}"),  // [初]
+    (0x7a6d, "\u{1f221}"),  // [終]
+    (0x7a6e, "\u{1f222}"),  // [生]
+    (0x7a6f, "\u{1f223}"),  // [販]
+    (0x7a70, "\u{1f224}"),  // [声]
+    (0x7a71, "\u{1f225}"),  // [吹]
+    (0x7a72, "\u{1f14e}"),  // [PPV]
+    (0x7a73, "\u{3299}"),   // (秘)
+    (0x7c7a, "\u{1f226}"),  // [演]
+];
+
+// プロセス共通の追加記号変換表(起動時に1度だけ設定)
+static SYMBOL_TABLE: OnceLock<Arc<SymbolTable>> = OnceLock::new();
+
+// 追加記号の変換方式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SymbolProfile {
+    Bracket,   // 括弧付きの文字列([字]、[HV]など)
+    Unicode,   // Unicode 5.2の囲み文字(🈑、🅊など)
+    None,      // 囲み文字の記号は出力しない
+}
+
+impl SymbolProfile {
+
+    //
+    // 変換方式名の取得処理
+    //
+    pub fn name(&self) -> &'static str {
+
+        match self {
+            SymbolProfile::Bracket => "bracket",
+            SymbolProfile::Unicode => "unicode",
+            SymbolProfile::None => "none",
+        }
+
+    }
+}
+
+// 追加記号変換表構造体
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    profile: SymbolProfile,       // 変換方式
+    map: HashMap<u32, String>,    // 文字コード -> 置き換え文字列(変換方式より優先)
+}
+
+impl SymbolTable {
+
+    //
+    // 変換表の作成処理
+    //
+    pub fn new(profile: SymbolProfile) -> Self {
+
+        SymbolTable {
+            profile,
+            map: HashMap::new(),
+        }
+
+    }
+
+    //
+    // 置き換え文字列の登録処理
+    //
+    pub fn add_map(&mut self, code: u32, text: &str) {
+
+        self.map.insert(code, text.to_string());
+
+    }
+
+    //
+    // 対応表ファイルの読込み処理(「文字コード(16進数)=文字列」形式、「#」「;」はコメント行、「[」はセクション行)
+    //
+    pub fn load_map(&mut self, path: &str) -> std::io::Result<usize> {
+
+        let buffer = BufReader::new(File::open(path)?);
+        let mut map_cnt = 0;
+
+        // ファイルからリード処理(１行づつ)
+        for line in buffer.lines() {
+
+            let line_data = line?;
+            let line_data = line_data.trim();
+
+            // コメント行、セクション行は読み飛ばし
+            if line_data.is_empty() || line_data.starts_with(['#', ';', '[']) {
+
+                continue;
+
+            };
+
+            // 「=」で分割して登録(文字コードが不正な行は読み飛ばし)
+            match line_data.split_once('=')
+                .and_then(|(code, text)| Some((u32::from_str_radix(code.trim().trim_start_matches("0x"), 16).ok()?, text.trim()))) {
+                Some((code, text)) => {
+
+                    self.add_map(code, text);
+                    map_cnt += 1;
+
+                },
+                None => warn!("symbol map file = {} 不正な行({})", path, line_data),
+            };
+        };
+
+        debug!("symbol map file = {} map_cnt={}", path, map_cnt);
+
+        Ok(map_cnt)
+
+    }
+
+    //
+    // 対応表の登録有無の判定処理
+    //
+    pub fn contains(&self, code: u32) -> bool {
+
+        self.map.contains_key(&code)
+
+    }
+
+    //
+    // 追加記号の文字列変換処理
+    //
+    pub fn lookup(&self, code: u32) -> &str {
+
+        // 対応表に登録された文字列
+        if let Some(text) = self.map.get(&code) {
+
+            return text;
+
+        };
+
+        // 囲み文字の記号は変換方式で変換
+        match SYMBOL_ENCLOSED_TABLE.iter().find(|(enclosed_code, _)| *enclosed_code == code) {
+            Some((_, enclosed)) if self.profile == SymbolProfile::Unicode => enclosed,
+            Some(_) if self.profile == SymbolProfile::None => "",
+            _ => put_symbols_char(code),
+        }
+
+    }
+}
+
+//
+// Unicode 5.2の囲み文字 -> 追加記号の文字コード変換処理
+//
+pub fn enclosed_symbol_code(text: &str) -> Option<u32> {
+
+    SYMBOL_ENCLOSED_TABLE.iter()
+        .find(|(_, enclosed)| *enclosed == text)
+        .map(|(code, _)| *code)
+
+}
+
+//
+// プロセス共通の追加記号変換表の設定処理
+//
+pub fn symbol_table_init(table: SymbolTable) {
+
+    if SYMBOL_TABLE.set(Arc::new(table)).is_err() {
+
+        warn!("symbol table is already initialized");
+
+    };
+
+}
+
+//
+// プロセス共通の追加記号変換表の取得処理
+//
+pub fn symbol_table() -> Option<Arc<SymbolTable>> {
+
+    SYMBOL_TABLE.get().cloned()
+
+}
+
+//
+// 追加記号変換表の作成処理
+//
+pub fn symbol_table_read(profile: SymbolProfile, map_file: Option<&str>) -> SymbolTable {
+
+    let mut table = SymbolTable::new(profile);
+
+    // 対応表ファイルの読込み
+    if let Some(map_file) = map_file {
+
+        if let Err(err) = table.load_map(map_file) {
+
+            warn!("File Open Error({}:{})", map_file, err);
+
+        };
+    };
+
+    debug!("symbol profile={}", profile.name());
+
+    table
+
+}
@@ -0,0 +1,282 @@
+
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use log::{debug};
+
+// 日本標準時(JST)のUTCからのオフセット(秒)
+pub const JST_OFFSET: i32 = 9 * 3600;
+
+// MJDの1970/01/01
+const MJD_UNIX_EPOCH: i64 = 40587;
+
+// ローカルタイムオフセット記述子
+const LOCAL_TIME_OFFSET_DESCRIPTOR: u8 = 0x58;
+
+// TDT/TOTの受信状態構造体
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TotState {
+    pub local_time_offset: i32,      // ローカルタイムオフセット(JSTからの差分秒)
+    pub next_time_offset: i32,       // 変更後のローカルタイムオフセット(JSTからの差分秒)
+    pub time_of_change: i64,         // 変更時刻(未受信の場合はi64::MAX)
+    pub current_time: Option<i64>,   // TDT/TOTの現在時刻(未受信の場合はNone)
+}
+
+impl Default for TotState {
+
+    fn default() -> TotState {
+
+        TotState {
+            local_time_offset: 0,
+            next_time_offset: 0,
+            time_of_change: i64::MAX,
+            current_time: None,
+        }
+
+    }
+}
+
+// 日時出力形式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TimeFormat {
+    Legacy,   // 放送時刻(YYYY-MM-DD HH:MM:SS)
+    Iso8601,  // 放送時刻(ISO-8601 オフセット付き)
+    Utc,      // UTC(ISO-8601)
+}
+
+//
+// JSTのタイムゾーン取得処理
+//
+pub fn jst() -> FixedOffset {
+
+    FixedOffset::east_opt(JST_OFFSET).unwrap()
+
+}
+
+impl TotState {
+
+    //
+    // ローカルタイムオフセット取得処理(変更時刻以降は次のオフセット)
+    //
+    pub fn local_time_offset(&self, timestamp: i64) -> i32 {
+
+        if timestamp >= self.time_of_change {
+            self.next_time_offset
+        }
+        else {
+            self.local_time_offset
+        }
+
+    }
+
+    //
+    // 放送時刻のタイムゾーン取得処理(JST + TOTのローカルタイムオフセット)
+    //
+    pub fn broadcast_offset(&self, timestamp: i64) -> FixedOffset {
+
+        FixedOffset::east_opt(JST_OFFSET + self.local_time_offset(timestamp)).unwrap_or_else(jst)
+
+    }
+
+    //
+    // 放送時刻のDateTime作成処理
+    //
+    pub fn broadcast_time(&self, timestamp: i64) -> DateTime<FixedOffset> {
+
+        self.broadcast_offset(timestamp).timestamp_opt(timestamp, 0).unwrap()
+
+    }
+
+    //
+    // 日時(キャラクタ)作成処理
+    //
+    pub fn format_time(&self, timestamp: i64, time_format: TimeFormat) -> String {
+
+        match time_format {
+            TimeFormat::Legacy => {
+                self.broadcast_time(timestamp).format("%Y-%m-%d %H:%M:%S").to_string()
+            },
+            TimeFormat::Iso8601 => {
+                self.broadcast_time(timestamp).format("%Y-%m-%dT%H:%M:%S%:z").to_string()
+            },
+            TimeFormat::Utc => {
+                Utc.timestamp_opt(timestamp, 0).unwrap().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+            },
+        }
+
+    }
+
+    //
+    // XMLTVの日時(キャラクタ)作成処理(YYYYMMDDHHMMSS +HHMM、UTCの場合は+0000)
+    //
+    pub fn format_xmltv_time(&self, timestamp: i64, time_format: TimeFormat) -> String {
+
+        match time_format {
+            TimeFormat::Utc => {
+                Utc.timestamp_opt(timestamp, 0).unwrap().format("%Y%m%d%H%M%S +0000").to_string()
+            },
+            _ => {
+                self.broadcast_time(timestamp).format("%Y%m%d%H%M%S %z").to_string()
+            },
+        }
+
+    }
+}
+
+//
+// BCD(1バイト)の変換処理
+//
+fn bcd_to_i32(bcd: u8) -> i32 {
+
+    ((bcd >> 4) as i32) * 10 + (bcd & 0x0f) as i32
+
+}
+
+//
+// MJD(JSTの日付)のシリアル時刻変換処理(JSTの0時)
+//
+pub fn mjd_to_timestamp(mjd: i32) -> i64 {
+
+    (mjd as i64 - MJD_UNIX_EPOCH) * 86400 - JST_OFFSET as i64
+
+}
+
+//
+// MJD+BCD(JST)のシリアル時刻変換処理
+//
+fn mjd_bcd_to_timestamp(data: &[u8]) -> i64 {
+
+    let mjd = ((data[0] as i64) << 8) + data[1] as i64;
+
+    (mjd - MJD_UNIX_EPOCH) * 86400 + bcd_to_i32(data[2]) as i64 * 3600 +
+        bcd_to_i32(data[3]) as i64 * 60 + bcd_to_i32(data[4]) as i64 - JST_OFFSET as i64
+
+}
+
+//
+// TDT(Time Date Table)、TOT(Time Offset Table)の解析処理
+//
+pub fn dump_tot(tot: &mut TotState, buf: &[u8]) {
+
+    // TDT、TOT以外はリターン
+    if buf[0] != 0x70 && buf[0] != 0x73 {
+
+        return;
+
+    };
+
+    // 現在時刻の取得
+    let jst_time = mjd_bcd_to_timestamp(&buf[3..8]);
+    tot.current_time = Some(jst_time);
+    debug!("TOT jst_time={}", tot.format_time(jst_time, TimeFormat::Iso8601));
+
+    // TDTは記述子が無いためリターン
+    if buf[0] != 0x73 {
+
+        return;
+
+    };
+
+    // セクション長、記述子ループ長の取得
+    let section_length = (((buf[1] as usize & 0x0f) << 8) + buf[2] as usize + 3).min(buf.len());
+    let descriptors_loop_length = ((buf[8] as usize & 0x0f) << 8) + buf[9] as usize;
+
+    // 記述子ループ
+    let mut index = 10;
+    let end_index = (10 + descriptors_loop_length).min(section_length.saturating_sub(4));
+    while index + 2 <= end_index {
+
+        let descriptor_tag = buf[index];
+        let descriptor_length = buf[index + 1] as usize;
+
+        // ローカルタイムオフセット記述子(先頭の地域のみ使用)
+        if descriptor_tag == LOCAL_TIME_OFFSET_DESCRIPTOR && descriptor_length >= 13 && index + 15 <= end_index {
+
+            let entry = &buf[index + 2..index + 15];
+
+            // 極性(1:マイナス)
+            let polarity = if entry[3] & 0x01 == 1 { -1 } else { 1 };
+
+            // オフセット、変更時刻、次のオフセットの取得
+            let local_time_offset = polarity * (bcd_to_i32(entry[4]) * 3600 + bcd_to_i32(entry[5]) * 60);
+            let time_of_change = mjd_bcd_to_timestamp(&entry[6..11]);
+            let next_time_offset = polarity * (bcd_to_i32(entry[11]) * 3600 + bcd_to_i32(entry[12]) * 60);
+
+            // オフセットが変化した場合にログ出力
+            if tot.local_time_offset != local_time_offset {
+
+                debug!("TOT local_time_offset={}s next_time_offset={}s time_of_change={}",
+                    local_time_offset, next_time_offset, tot.format_time(time_of_change, TimeFormat::Iso8601));
+
+            };
+            tot.local_time_offset = local_time_offset;
+            tot.next_time_offset = next_time_offset;
+            tot.time_of_change = time_of_change;
+
+            break;
+
+        };
+
+        index += descriptor_length + 2;
+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // 2023-11-15 07:13:20 JST
+    const TIMESTAMP: i64 = 1_700_000_000;
+
+    #[test]
+    fn local_time_offset_change() {
+
+        let tot = TotState { local_time_offset: 0, next_time_offset: 3600, time_of_change: TIMESTAMP, current_time: None };
+
+        // 変更時刻の前は現在のオフセット、変更時刻以降は次のオフセット
+        assert_eq!(tot.local_time_offset(TIMESTAMP - 1), 0);
+        assert_eq!(tot.local_time_offset(TIMESTAMP), 3600);
+        assert_eq!(tot.format_time(TIMESTAMP - 1, TimeFormat::Iso8601), "2023-11-15T07:13:19+09:00");
+        assert_eq!(tot.format_time(TIMESTAMP, TimeFormat::Iso8601), "2023-11-15T08:13:20+10:00");
+
+        // 未受信の場合はJST
+        assert_eq!(TotState::default().local_time_offset(TIMESTAMP), 0);
+
+    }
+
+    #[test]
+    fn xmltv_time() {
+
+        let tot = TotState::default();
+
+        assert_eq!(tot.format_xmltv_time(TIMESTAMP, TimeFormat::Legacy), "20231115071320 +0900");
+        assert_eq!(tot.format_xmltv_time(TIMESTAMP, TimeFormat::Iso8601), "20231115071320 +0900");
+        assert_eq!(tot.format_xmltv_time(TIMESTAMP, TimeFormat::Utc), "20231114221320 +0000");
+
+        // ローカルタイムオフセットを反映
+        let tot = TotState { local_time_offset: -1800, ..TotState::default() };
+        assert_eq!(tot.format_xmltv_time(TIMESTAMP, TimeFormat::Legacy), "20231115064320 +0830");
+
+    }
+
+    #[test]
+    fn tot_local_time_offset_descriptor() {
+
+        let mut tot = TotState::default();
+
+        // TOT(2023-11-15 07:13:20、オフセット+01:00、変更時刻2023-11-16 00:00:00、次のオフセット+00:30)
+        let section = [
+            0x73, 0x70, 0x1a, 0xeb, 0x67, 0x07, 0x13, 0x20, 0xf0, 0x0f,
+            0x58, 0x0d, b'J', b'P', b'N', 0x02, 0x01, 0x00, 0xeb, 0x68, 0x00, 0x00, 0x00, 0x00, 0x30,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        dump_tot(&mut tot, &section);
+
+        assert_eq!(tot.current_time, Some(TIMESTAMP));
+        assert_eq!(tot.local_time_offset, 3600);
+        assert_eq!(tot.next_time_offset, 1800);
+        assert_eq!(tot.time_of_change, mjd_to_timestamp(0xeb68));
+
+    }
+
+}
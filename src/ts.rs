@@ -1,8 +1,16 @@
 #[allow(unused_imports)]
 use log::{debug, info, warn};
+use chrono::{TimeZone};
+use chrono::prelude::{Datelike, Timelike};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use crate::eit::{START_TIME_UNCERTAINTY, DURATION_UNCERTAINTY};
+use crate::marker::{TitleMarker};
+use crate::store::{EitStore};
+use crate::tot::{jst};
+
 // 
 #[derive(Debug, Copy, Clone)]
 #[allow(dead_code)]
@@ -57,12 +65,7 @@ pub struct EitControl {
     pub segment_last_section_number: i32,
     pub running_status: i32,
     pub free_ca_mode: i32,
-    pub content_type: i32,                 // コンテントタイプ
-    pub content_subtype: i32,              // コンテントサブタイプ
-    pub genre2: i32,
-    pub sub_genre2: i32,
-    pub genre3: i32,
-    pub sub_genre3: i32,
+    pub genres: Vec<EitGenre>,             // ジャンル(コンテント記述子の全ジャンル)
     pub episode_number: i32,
     pub yy: i32,
     pub mm: i32,
@@ -73,18 +76,142 @@ pub struct EitControl {
     pub duration: i32,
     pub start_time: i64,
     pub title: String,                     // タイトル
+    pub marker_title: String,              // タイトル(記号の判定用、追加記号はbracket形式)
     pub subtitle: String,                  // サブタイトル
     pub desc: String,                      // 詳細説明
+    pub desc_items: Vec<EitDescItem>,      // 詳細説明の項目
     pub desc_length: i32,                  // 詳細説明のレングス
     pub video_type: i32,                   // 映像のタイプ
+    pub stream_content: i32,               // 映像のストリーム形式(コンポーネント記述子)
     pub audio_type: i32,                   // 音声のタイプ
     pub multi_type: i32,                   // 音声の 2 カ国語多重
+    pub audios: Vec<EitAudio>,             // 音声(オーディオコンポーネント記述子の全音声)
+    pub series: Option<EitSeries>,         // シリーズ(シリーズ記述子)
+    pub related_items: Vec<EitRelatedItem>, // 関連イベント(イベントグループ記述子)
     pub event_status: i32,
     pub sch_pnt: i32,
     pub import_cnt: i32,
     pub renew_cnt: i32,                    // 更新カウンタ
     pub tid: i32,
     pub tid_status: i32,
+    pub lang: String,                      // タイトル、説明の言語コード
+    pub lang_text: Vec<EitLangText>,       // 言語別タイトル、説明
+    pub markers: BTreeSet<TitleMarker>,    // タイトルの記号([新]、[再]など)
+    pub rating: i32,                       // パレンタルレート(0:指定なし、0x01〜0x11:視聴可能な最小年齢-3歳)
+}
+
+impl EitControl {
+
+    //
+    // 開始時刻未定義の判定処理
+    //
+    pub fn is_start_time_undefined(&self) -> bool {
+
+        (self.event_status & START_TIME_UNCERTAINTY) != 0
+
+    }
+
+    //
+    // 継続時間未定義の判定処理
+    //
+    pub fn is_duration_undefined(&self) -> bool {
+
+        (self.event_status & DURATION_UNCERTAINTY) != 0
+
+    }
+
+    //
+    // 開始時刻の取得処理(未定義の場合はNone)
+    //
+    pub fn start(&self) -> Option<i64> {
+
+        if self.is_start_time_undefined() { None } else { Some(self.start_time) }
+
+    }
+
+    //
+    // 終了時刻の取得処理(開始時刻、継続時間のどちらかが未定義の場合はNone)
+    //
+    pub fn end(&self) -> Option<i64> {
+
+        if self.is_duration_undefined() { None } else { self.start().map(|start| start + self.duration as i64) }
+
+    }
+
+    //
+    // 開始時刻の変更処理(年月日時分秒も合わせて更新)
+    //
+    pub fn set_start_time(&mut self, start_time: i64) {
+
+        let start_time_dt = jst().timestamp_opt(start_time, 0).unwrap();
+
+        self.yy = start_time_dt.year() - 1900;
+        self.mm = start_time_dt.month() as i32;
+        self.dd = start_time_dt.day() as i32;
+        self.hh = start_time_dt.hour() as i32;
+        self.hm = start_time_dt.minute() as i32;
+        self.ss = start_time_dt.second() as i32;
+        self.start_time = start_time;
+
+    }
+}
+
+// 言語別イベント文字情報構造体
+#[derive(Debug, Clone)]
+pub struct EitLangText {
+    pub lang: String,                      // ISO 639 言語コード
+    pub title: String,                     // タイトル
+    pub marker_title: String,              // タイトル(記号の判定用、追加記号はbracket形式)
+    pub subtitle: String,                  // サブタイトル
+    pub desc: String,                      // 詳細説明
+    pub desc_items: Vec<EitDescItem>,      // 詳細説明の項目
+}
+
+// 詳細説明の項目構造体(拡張形式イベント記述子の項目名、項目記述)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EitDescItem {
+    pub name: String,                      // 項目名(番組内容、出演者など)
+    pub text: String,                      // 項目記述
+}
+
+// 音声構造体(オーディオコンポーネント記述子)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EitAudio {
+    pub component_type: i32,               // コンポーネント種別
+    pub component_tag: i32,                // コンポーネントタグ
+    pub is_main: bool,                     // 主コンポーネントフラグ
+    pub sampling_rate: i32,                // サンプリング周波数(3ビットの符号)
+    pub langs: Vec<String>,                // ISO 639 言語コード(二か国語の場合は2言語)
+}
+
+// シリーズ構造体(シリーズ記述子)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EitSeries {
+    pub series_id: i32,                    // シリーズID
+    pub repeat_label: i32,                 // 再放送ラベル
+    pub program_pattern: i32,              // 編成パターン
+    pub expire_date: Option<i64>,          // 有効期限(シリアル時刻、未定義の場合はNone)
+    pub episode_number: i32,               // 話数
+    pub last_episode_number: i32,          // 番組総数
+    pub name: String,                      // シリーズ名
+}
+
+// 関連イベント構造体(イベントグループ記述子)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EitRelatedItem {
+    pub group_type: i32,                   // グループ種別(1:共有 2,4:リレー 3,5:移動)
+    pub network_id: Option<i32>,           // オリジナルネットワークID(他ネットワークの場合のみ)
+    pub service_id: i32,                   // サービスID
+    pub event_id: i32,                     // イベントID
+}
+
+// ジャンル構造体
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EitGenre {
+    pub content_nibble_level_1: i32,       // 大分類
+    pub content_nibble_level_2: i32,       // 中分類
+    pub user_nibble_1: i32,                // ユーザーニブル1
+    pub user_nibble_2: i32,                // ユーザーニブル2
 }
 
 // サービス情報構造体
@@ -101,6 +228,9 @@ pub struct SvtControl {
     pub eitsch: Vec<EitControl>,
     pub eit_pf: Vec<EitControl>,
     pub prev_sch: Vec<EitControl>,
+    pub sch_store: EitStore,               // EIT SCHのイベントストア
+    pub pf_store: EitStore,                // EIT PFのイベントストア
+    pub null_segment: EitNullSegmentTop,   // EIT SCHの受信セグメント
     pub import_cnt: i32,
     pub import_stat: i32,
     pub logo_download_data_id: u32,
@@ -116,31 +246,31 @@ pub struct SvtControlTop {
     pub svt_control_sub: Vec<SvtControl>,
 }
 
-/*
 // EIT nullセグメント構造体
 #[derive(Debug, Clone)]
-//#[allow(dead_code)]
 pub struct EitNullSegment {
-    table_id: i32,
-    service_id: i32,
-    section_number: i32,
-    version_number: i32,
+    pub table_id: i32,
+    pub service_id: i32,
+    pub section_number: i32,
+    pub version_number: i32,
 }
 
-// EIT nullセグメントトップ構造体
-#[derive(Debug, Clone)]
-//#[allow(dead_code)]
+// EIT nullセグメントトップ構造体(EIT SCHのサブテーブル毎の受信セグメント管理)
+#[derive(Debug, Clone, Default)]
 pub struct EitNullSegmentTop {
     pub service_id: i32,
-    pub eit_null_segment_sub: Vec<EitNullSegment>,
+    pub eit_null_segment_sub: Vec<EitNullSegment>,    // nullセグメント(番組なし)
+    pub received_segment: BTreeMap<i32, u32>,         // table_id毎の受信済みセグメント(ビット毎)
+    pub last_segment: BTreeMap<i32, i32>,             // table_id毎の最終セグメント番号
+    pub last_table_id: i32,                           // 最終テーブルID
 }
-*/
 
 // 定数設定
 pub const MAXSECLEN: usize = 4096;    // SEC構造体最大長
 pub const MAXSECBUF: usize = 4282;    // SEC構造体最大バッファ長
 pub const TSPAYLOADMAX: usize = 184;  // 最大ペイロード長
 pub const LENGTH_PACKET: usize = 188; // 最大パケット長
+pub const START_TIME_UNDEFINED: i64 = i64::MAX;  // 開始時刻未定義時のシリアル時刻(並び順は最後)
 
 static mut RCOUNT: i32 = 0;           // パケットリードカウンター
 static mut CONTINUITY_COUNTER_FLAG: [i32; 4096] = [0; 4096];  // パケット巡回カウンター処理フラグ
@@ -320,6 +450,10 @@ pub fn read_ts(readbuff_file: &mut BufReader<&File>, secs: &mut [SecCache], coun
                                         break;
                                     };
                                 },
+                                0x14 if !(table_id == 0x70 || table_id == 0x73) => {
+                                    debug!("not start pid=0x{:02x} section data", tpk.pid);
+                                    break;
+                                },
                                 _ => {
                                 },
                             };
@@ -330,7 +464,7 @@ pub fn read_ts(readbuff_file: &mut BufReader<&File>, secs: &mut [SecCache], coun
 
                         // pid初回のみの処理
                         if secs[pid_cnt].cont == 0 && tpk.payload_unit_start_indicator == 1 &&
-                            sec_syntax_indicator == 1 &&  tpk.payload[0] < 0x72 {
+                            ((sec_syntax_indicator == 1 &&  tpk.payload[0] < 0x72) || (tpk.pid == 0x14 && table_id == 0x73)) {
 
                             // レングス情報を初期化
                             secs[pid_cnt].seclen = 0;
@@ -637,3 +771,87 @@ pub fn read_ts(readbuff_file: &mut BufReader<&File>, secs: &mut [SecCache], coun
     None
 
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::store::{EitStore};
+
+    // テスト用のイベント作成処理(サービスID 101、EIT SCH)
+    pub(crate) fn event(event_id: i32, start_time: i64, duration: i32, event_status: i32) -> EitControl {
+
+        EitControl {
+            table_id: 0x50,
+            servid: 101,
+            event_id,
+            version_number: 0,
+            section_number: 0,
+            last_section_number: 0,
+            segment_last_section_number: 0,
+            running_status: 0,
+            free_ca_mode: 0,
+            genres: vec![],
+            episode_number: 0,
+            yy: 0,
+            mm: 0,
+            dd: 0,
+            hh: 0,
+            hm: 0,
+            ss: 0,
+            duration,
+            start_time,
+            title: String::new(),
+            marker_title: String::new(),
+            subtitle: String::new(),
+            desc: String::new(),
+            desc_items: vec![],
+            desc_length: 0,
+            video_type: 0,
+            stream_content: 0,
+            audio_type: 0,
+            multi_type: 0,
+            audios: vec![],
+            series: None,
+            related_items: vec![],
+            event_status,
+            sch_pnt: 0,
+            import_cnt: 0,
+            renew_cnt: 0,
+            tid: 0,
+            tid_status: 0,
+            lang: String::new(),
+            lang_text: vec![],
+            markers: BTreeSet::new(),
+            rating: 0,
+        }
+
+    }
+
+    // テスト用のサービス作成処理(サービスID 101)
+    pub(crate) fn service(eit_pf: Vec<EitControl>, eitsch: Vec<EitControl>) -> SvtControl {
+
+        SvtControl {
+            service_id: 101,
+            service_type: 0x01,
+            original_network_id: 4,
+            transport_stream_id: 0,
+            slot: 0,
+            servicename: String::new(),
+            ontv: String::new(),
+            eitsch,
+            eit_pf,
+            prev_sch: vec![],
+            sch_store: EitStore::default(),
+            pf_store: EitStore::default(),
+            null_segment: EitNullSegmentTop::default(),
+            import_cnt: 0,
+            import_stat: 0,
+            logo_download_data_id: 0,
+            logo_version: 0,
+        }
+
+    }
+}
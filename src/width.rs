@@ -0,0 +1,208 @@
+
+use log::{debug, warn};
+use std::sync::{OnceLock};
+use unicode_normalization::UnicodeNormalization;
+
+// プロセス共通の文字幅の正規化方式(起動時に1度だけ設定)
+static TEXT_WIDTH: OnceLock<TextWidth> = OnceLock::new();
+
+// 文字幅の正規化方式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextWidth {
+    Raw,    // 放送のまま
+    Fold,   // 英数字、スペースは半角、カナは全角に統一
+    Nfkc,   // Unicode正規化(NFKC)
+}
+
+impl TextWidth {
+
+    //
+    // 正規化方式名の取得処理
+    //
+    pub fn name(&self) -> &'static str {
+
+        match self {
+            TextWidth::Raw => "raw",
+            TextWidth::Fold => "fold",
+            TextWidth::Nfkc => "nfkc",
+        }
+
+    }
+
+    //
+    // 文字列の正規化処理
+    //
+    pub fn normalize(&self, text: &str) -> String {
+
+        match self {
+            TextWidth::Raw => text.to_string(),
+            TextWidth::Fold => width_fold(&voiced_mark_compose(text)),
+            TextWidth::Nfkc => voiced_mark_compose(text).nfkc().collect(),
+        }
+
+    }
+}
+
+//
+// 半角カナの判定処理
+//
+fn is_halfwidth_kana(c: char) -> bool {
+
+    ('\u{ff61}'..='\u{ff9f}').contains(&c)
+
+}
+
+//
+// 濁点、半濁点の合成処理(「カ゛」->「ガ」、JIS X 0201カタカナの濁点、半濁点は1文字のため)
+//
+fn voiced_mark_compose(text: &str) -> String {
+
+    let mut ret_str = String::with_capacity(text.len());
+
+    for c in text.chars() {
+
+        // 直前のかな、カナと合成できる濁点、半濁点は合成文字に置き換え
+        let combining = match c {
+            '\u{309b}' => '\u{3099}',
+            '\u{309c}' => '\u{309a}',
+            _ => {
+
+                ret_str.push(c);
+                continue;
+
+            },
+        };
+        let composed = ret_str.chars().last()
+            .filter(|kana| ('\u{3041}'..='\u{30ff}').contains(kana))
+            .and_then(|kana| {
+                let mut composed = [kana, combining].into_iter().nfc();
+                match (composed.next(), composed.next()) {
+                    (Some(composed), None) => Some(composed),
+                    _ => None,
+                }
+            });
+
+        match composed {
+            Some(composed) => {
+
+                ret_str.pop();
+                ret_str.push(composed);
+
+            },
+            None => ret_str.push(c),
+        };
+    };
+
+    ret_str
+
+}
+
+//
+// 英数字、スペースの半角化、カナの全角化処理
+//
+fn width_fold(text: &str) -> String {
+
+    let mut ret_str = String::with_capacity(text.len());
+    let mut kana = String::new();
+
+    for c in text.chars() {
+
+        // 半角カナは濁点、半濁点と合わせて全角化するためまとめて処理
+        if is_halfwidth_kana(c) {
+
+            kana.push(c);
+            continue;
+
+        };
+        if !kana.is_empty() {
+
+            ret_str.extend(kana.nfkc());
+            kana.clear();
+
+        };
+
+        match c {
+            // 全角英数字、記号
+            '\u{ff01}'..='\u{ff5e}' => ret_str.push(char::from_u32(c as u32 - 0xff01 + 0x21).unwrap()),
+            // 全角スペース
+            '\u{3000}' => ret_str.push(' '),
+            _ => ret_str.push(c),
+        };
+    };
+    ret_str.extend(kana.nfkc());
+
+    ret_str
+
+}
+
+//
+// プロセス共通の文字幅の正規化方式の設定処理
+//
+pub fn text_width_init(width: TextWidth) {
+
+    if TEXT_WIDTH.set(width).is_err() {
+
+        warn!("text width is already initialized");
+
+    };
+
+    debug!("text width={}", width.name());
+
+}
+
+//
+// プロセス共通の文字幅の正規化方式の取得処理(未設定の場合は放送のまま)
+//
+pub fn text_width() -> TextWidth {
+
+    TEXT_WIDTH.get().copied().unwrap_or(TextWidth::Raw)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn fold_alphanumeric() {
+
+        assert_eq!(width_fold("ＮＨＫ"), "NHK");
+        assert_eq!(width_fold("ＢＳ１　ニュース！"), "BS1 ニュース!");
+
+    }
+
+    #[test]
+    fn fold_halfwidth_kana() {
+
+        // 半角カナは濁点、半濁点と合わせて全角化
+        assert_eq!(width_fold("ｶﾀｶﾅ"), "カタカナ");
+        assert_eq!(width_fold("ｶﾞｯﾂﾎﾟｰｽﾞ"), "ガッツポーズ");
+        assert_eq!(width_fold("ﾆｭｰｽ7"), "ニュース7");
+
+    }
+
+    #[test]
+    fn voiced_mark() {
+
+        // 直前のかな、カナと合成できる場合のみ合成
+        assert_eq!(voiced_mark_compose("カ゛ハ゜"), "ガパ");
+        assert_eq!(voiced_mark_compose("か゛"), "が");
+        assert_eq!(voiced_mark_compose("ア゛"), "ア゛");
+        assert_eq!(voiced_mark_compose("A゛"), "A゛");
+        assert_eq!(voiced_mark_compose("゛"), "゛");
+
+    }
+
+    #[test]
+    fn normalize_modes() {
+
+        let text = "ＮＨＫ　ｶﾞｲﾄﾞ ㈱テレヒ゛";
+
+        assert_eq!(TextWidth::Raw.normalize(text), text);
+        assert_eq!(TextWidth::Fold.normalize(text), "NHK ガイド ㈱テレビ");
+        assert_eq!(TextWidth::Nfkc.normalize(text), "NHK ガイド (株)テレビ");
+
+    }
+
+}
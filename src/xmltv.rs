@@ -0,0 +1,247 @@
+use std::fs::File;
+use std::io::Write;
+use std::iter;
+
+use crate::{CommanLineOpt, newline_flatten, programme_list, programme_repair};
+use crate::{xml_category, xml_lang_code, xml_other_lang, xml_special_chars};
+use crate::marker::{TitleMarker};
+use crate::tot::{TotState};
+use crate::ts::{EitControl, EitDescItem, SvtControl, SvtControlTop};
+
+// XMLの出力形式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum XmlMode {
+    Una,     // 従来形式(UNA互換、xmltv.dtdに無い要素を含む)
+    Strict,  // XMLTV(xmltv.dtdに準拠)
+}
+
+// XMLの説明(<desc>)の出力形式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DescLayout {
+    Short,     // 短形式イベント記述子の説明のみ(従来形式)
+    Append,    // 説明の後に詳細説明の全項目を追加して1つの要素で出力
+    Separate,  // 説明、詳細説明の項目毎に要素を出力
+}
+
+impl DescLayout {
+
+    //
+    // 説明の要素の文字列作成処理(1つ目は短形式イベント記述子の説明)
+    //
+    pub fn desc_texts(&self, subtitle: &str, items: &[EitDescItem]) -> Vec<String> {
+
+        // 詳細説明の項目は「項目名<改行>項目記述」(項目名が無い場合は項目記述のみ)
+        let item_texts = items.iter().map(|item| {
+            if item.name.is_empty() { item.text.clone() } else { format!("{}\n{}", item.name, item.text) }
+        });
+
+        match self {
+            DescLayout::Short => vec![subtitle.to_string()],
+            DescLayout::Append => vec![
+                iter::once(subtitle.to_string()).chain(item_texts)
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            ],
+            DescLayout::Separate => iter::once(subtitle.to_string()).chain(item_texts).collect(),
+        }
+
+    }
+}
+
+//
+// 映像の解像度の取得処理(コンポーネント種別の上位4ビット)
+//
+fn video_quality(video_type: i32) -> Option<&'static str> {
+
+    match (video_type & 0xf0) >> 4 {
+        0x00 | 0x0a => Some("SDTV"),    // 480i、480p
+        0x0b | 0x0c | 0x0e => Some("HDTV"),  // 1080i、720p、1080p
+        0x09 => Some("UHDTV"),          // 2160p
+        _ => None,
+    }
+
+}
+
+//
+// 映像のアスペクト比の取得処理(コンポーネント種別の下位4ビット)
+//
+fn video_aspect(video_type: i32) -> Option<&'static str> {
+
+    match video_type & 0x0f {
+        0x01 => Some("4:3"),
+        0x02..=0x04 => Some("16:9"),
+        _ => None,
+    }
+
+}
+
+//
+// 音声の形式の取得処理(オーディオコンポーネント種別、二か国語放送)
+//
+fn audio_stereo(eitcur: &EitControl) -> Option<&'static str> {
+
+    // 二か国語放送(デュアルモノ、多言語、[二]の記号)
+    if eitcur.audio_type == 0x02 || eitcur.multi_type == 1 || eitcur.markers.contains(&TitleMarker::Bilingual) {
+
+        return Some("bilingual");
+
+    };
+
+    match eitcur.audio_type {
+        0x01 => Some("mono"),
+        0x03 => Some("stereo"),
+        0x04..=0x09 => Some("surround"),
+        _ => None,
+    }
+
+}
+
+//
+// 番組データのXMLTV出力処理(開始時刻未定義の番組は出力しない)
+//
+fn xmltv_programme(cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, eitcur: &EitControl, ontv: &str) {
+
+    let start_time = match eitcur.start() {
+        Some(start_time) => start_time,
+        None => return,
+    };
+
+    // 処理用終了日時属性作成(未定義の場合は省略)
+    let cendtime = eitcur.end()
+        .map(|end_time| format!(" stop=\"{}\"", tot.format_xmltv_time(end_time, cmd_opt.time_format)))
+        .unwrap_or_default();
+
+    // 処理用言語作成(未設定の場合は日本語)
+    let lang = if eitcur.lang.is_empty() { "ja".to_string() } else { xml_lang_code(&eitcur.lang) };
+
+    // 文字情報のxml変換(改行の置き換えを含む)
+    let xml_text = |text: &str| xml_special_chars(newline_flatten(text, cmd_opt.is_flatten_newline));
+
+    writeln!(outfile, "  <programme start=\"{}\"{} channel=\"{}\">",
+        tot.format_xmltv_time(start_time, cmd_opt.time_format), cendtime, xml_special_chars(ontv.to_string())).unwrap();
+
+    // タイトル、説明
+    writeln!(outfile, "    <title lang=\"{}\">{}</title>", lang, xml_text(&eitcur.title)).unwrap();
+    xml_other_lang(cmd_opt, outfile, eitcur, "title");
+    for desc in cmd_opt.desc_layout.desc_texts(&eitcur.subtitle, &eitcur.desc_items) {
+
+        if !desc.is_empty() {
+
+            writeln!(outfile, "    <desc lang=\"{}\">{}</desc>", lang, xml_text(&desc)).unwrap();
+
+        };
+    };
+    xml_other_lang(cmd_opt, outfile, eitcur, "desc");
+
+    // ジャンル
+    xml_category(outfile, &eitcur.genres);
+
+    // 放送休止データ(DTDに属性が無いためkeywordで明示)
+    if cmd_opt.gap_fill.is_mark && eitcur.is_synthetic() {
+
+        writeln!(outfile, "    <keyword>synthetic</keyword>").unwrap();
+
+    };
+
+    // 話数(xmltv_nsは0始まり)
+    if eitcur.episode_number > 0 {
+
+        writeln!(outfile, "    <episode-num system=\"xmltv_ns\">.{}.</episode-num>", eitcur.episode_number - 1).unwrap();
+
+    };
+
+    // 映像
+    let aspect = video_aspect(eitcur.video_type);
+    let quality = video_quality(eitcur.video_type);
+    if eitcur.video_type != 0 && (aspect.is_some() || quality.is_some()) {
+
+        writeln!(outfile, "    <video>").unwrap();
+        if let Some(aspect) = aspect {
+            writeln!(outfile, "      <aspect>{}</aspect>", aspect).unwrap();
+        };
+        if let Some(quality) = quality {
+            writeln!(outfile, "      <quality>{}</quality>", quality).unwrap();
+        };
+        writeln!(outfile, "    </video>").unwrap();
+
+    };
+
+    // 音声
+    if let Some(stereo) = audio_stereo(eitcur) {
+
+        writeln!(outfile, "    <audio>").unwrap();
+        writeln!(outfile, "      <stereo>{}</stereo>", stereo).unwrap();
+        writeln!(outfile, "    </audio>").unwrap();
+
+    };
+
+    // 再放送、初回、最終回、新番組、字幕
+    for element in eitcur.xmltv_markers() {
+
+        writeln!(outfile, "    {}", element).unwrap();
+
+    };
+
+    // パレンタルレート(視聴可能な最小年齢)
+    if (0x01..=0x11).contains(&eitcur.rating) {
+
+        writeln!(outfile, "    <rating system=\"ARIB\">").unwrap();
+        writeln!(outfile, "      <value>{}+</value>", eitcur.rating + 3).unwrap();
+        writeln!(outfile, "    </rating>").unwrap();
+
+    };
+
+    writeln!(outfile, "  </programme>").unwrap();
+
+}
+
+//
+// サービス毎の番組データのXMLTV出力処理
+// (EIT SCHにEIT PFの番組を補って開始時刻順に出力)
+//
+fn xmltv_service(cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, svtcur: &mut SvtControl) {
+
+    // 番組データ補正処理
+    programme_repair(cmd_opt, tot, svtcur);
+
+    // 出力対象の番組作成(EIT SCHに無いEIT PFの番組を追加)
+    for eitcur in programme_list(cmd_opt, svtcur) {
+
+        xmltv_programme(cmd_opt, tot, outfile, eitcur, &svtcur.ontv);
+
+    };
+}
+
+//
+// XMLTVデータ作成処理
+//
+pub fn dump_xmltv(cmd_opt: &CommanLineOpt, tot: &TotState, outfile: &mut File, svttop: &mut [SvtControlTop]) {
+
+    // ヘッダー出力
+    writeln!(outfile, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(outfile, "<!DOCTYPE tv SYSTEM \"xmltv.dtd\">\n").unwrap();
+    writeln!(outfile, "<tv generator-info-name=\"tsEPG2xml\" generator-info-url=\"http://localhost/\">").unwrap();
+
+    // チャンネル出力
+    for svt in svttop.iter() {
+
+        let svtcur = &svt.svt_control_sub[0];
+
+        writeln!(outfile, "  <channel id=\"{}\">", xml_special_chars(svtcur.ontv.clone())).unwrap();
+        writeln!(outfile, "    <display-name lang=\"ja\">{}</display-name>", xml_special_chars(svtcur.servicename.clone())).unwrap();
+        writeln!(outfile, "  </channel>").unwrap();
+
+    };
+
+    // 番組出力
+    for svt in svttop.iter_mut() {
+
+        xmltv_service(cmd_opt, tot, outfile, &mut svt.svt_control_sub[0]);
+
+    };
+
+    // フッター出力
+    writeln!(outfile, "</tv>").unwrap();
+
+}